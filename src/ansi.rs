@@ -0,0 +1,81 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use crate::raw;
+
+/// Text a terminal shows for a line written to it: the escape sequences are removed, and a
+/// carriage return starts the line over, like the progress indicators do
+pub fn strip(line: &str) -> Cow<'_, str> {
+    if !line.contains(['\x1b', '\r']) {
+        return Cow::Borrowed(line);
+    }
+    let mut text = String::with_capacity(line.len());
+    let mut chars = overwritten(line).chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            // Control sequence, ended by a character of `@` to `~`
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // Operating system command (e.g. setting the title), ended by BEL or ESC \
+            Some(']') => {
+                let mut escaped = false;
+                for c in chars.by_ref() {
+                    if c == '\x07' || (escaped && c == '\\') {
+                        break;
+                    }
+                    escaped = c == '\x1b';
+                }
+            }
+            // The other escape sequences are two characters long
+            _ => (),
+        }
+    }
+    Cow::Owned(text)
+}
+
+/// Part of the line written after its last carriage return
+fn overwritten(line: &str) -> &str {
+    line.rsplit('\r')
+        .find(|part| !part.is_empty())
+        .unwrap_or_default()
+}
+
+/// Diagnostics of the tests of `tap` as they were colored by the command, by hierarchical number.
+/// `colored` are the lines `tap` was read from, before being stripped, and only the tests whose
+/// diagnostics have colors are included.
+pub fn diagnostics(tap: &str, colored: &[String]) -> HashMap<String, Vec<String>> {
+    let lines = tap.lines().collect::<Vec<_>>();
+    // The document was rewritten by its source, the lines no longer match
+    if lines.len() != colored.len() {
+        return HashMap::new();
+    }
+    raw::test_lines(tap)
+        .into_iter()
+        .filter_map(|(number, point)| {
+            let start = lines.get(point + 1).filter(|line| line.trim() == "---")?;
+            let end = (point + 2..lines.len()).find(|&i| lines[i].trim() == "...")?;
+            let block = &colored[point + 2..end];
+            if !block.iter().any(|line| line.contains('\x1b')) {
+                return None;
+            }
+            // Indented like the YAML block, the colors starting after the indentation
+            let indentation = &start[..start.len() - start.trim_start().len()];
+            let yaml = block
+                .iter()
+                .map(|line| {
+                    let line = overwritten(line);
+                    line.strip_prefix(indentation).unwrap_or(line).to_owned()
+                })
+                .collect();
+            Some((number, yaml))
+        })
+        .collect()
+}
@@ -0,0 +1,70 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use ansi_to_tui::IntoText;
+use anyhow::Context;
+use tui::text::Text;
+
+use crate::image;
+
+/// Contents of a local artifact, shown in a popup
+pub struct ArtifactView {
+    pub name: String,
+    /// Contents of a text file, or a placeholder for an image the terminal cannot draw
+    pub text: Text<'static>,
+    pub scroll: u16,
+    /// Path of the artifact if it is an image
+    pub image: Option<PathBuf>,
+}
+
+impl ArtifactView {
+    /// Reads the artifact at `path`, which must be a text file or an image
+    pub fn load(name: &str, path: &Path) -> anyhow::Result<Self> {
+        let contents =
+            std::fs::read(path).with_context(|| format!("Could not read {}", path.display()))?;
+        let (text, image) = match image::is_image(path) {
+            true => (
+                Text::from(format!(
+                    "Image of {} bytes, which this terminal cannot display (the kitty and \
+                     iTerm2 graphics protocols are supported). Open it with `o`.",
+                    contents.len()
+                )),
+                Some(path.to_owned()),
+            ),
+            false if std::str::from_utf8(&contents).is_err() => {
+                anyhow::bail!("{name} is not a text file, open it with `o`")
+            }
+            false => (contents.into_text()?, None),
+        };
+        Ok(Self {
+            name: name.to_owned(),
+            text,
+            scroll: 0,
+            image,
+        })
+    }
+}
+
+pub fn is_url(artifact: &str) -> bool {
+    artifact.contains("://")
+}
+
+/// Opens a file or URL with the default application of the desktop
+pub fn open(target: &str) -> anyhow::Result<()> {
+    let opener = match cfg!(target_os = "macos") {
+        true => "open",
+        false => "xdg-open",
+    };
+    let mut child = Command::new(opener)
+        .arg(target)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Could not run `{opener}`"))?;
+    // The application may keep running, it is only waited for in the background
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use std::{collections::BTreeMap, path::Path};
+
+use crate::{storage::Storage, Test};
+
+/// Parts of the keys of the YAML diagnostics holding a measurement
+const TIMING_KEYS: [&str; 5] = ["time", "duration", "elapsed", "latency", "ns_per_iter"];
+/// Parts of the keys of measurements for which a higher value is better
+const THROUGHPUT_KEYS: [&str; 4] = ["throughput", "per_sec", "ops", "rate"];
+
+/// Measurements of the tests, by hierarchical number and then by key
+pub type Measurements = BTreeMap<String, BTreeMap<String, f64>>;
+
+fn higher_is_better(key: &str) -> bool {
+    THROUGHPUT_KEYS.iter().any(|k| key.contains(k))
+}
+
+/// Numeric top-level values of the YAML diagnostics whose key looks like a measurement
+fn measurements(yaml: &str) -> BTreeMap<String, f64> {
+    let Ok(serde_yaml::Value::Mapping(map)) = serde_yaml::from_str(yaml) else {
+        return BTreeMap::new();
+    };
+    map.into_iter()
+        .filter_map(|(key, value)| Some((key.as_str()?.to_owned(), value.as_f64()?)))
+        .filter(|(key, _)| {
+            let key = key.to_lowercase();
+            TIMING_KEYS
+                .iter()
+                .chain(&THROUGHPUT_KEYS)
+                .any(|k| key.contains(k))
+        })
+        .collect()
+}
+
+pub fn collect(tests: &[Test]) -> Measurements {
+    tests
+        .iter()
+        .map(|t| (t.hierarchical_number(), measurements(&t.yaml)))
+        .filter(|(_, m)| !m.is_empty())
+        .collect()
+}
+
+pub fn load_baseline(path: &Path) -> Measurements {
+    std::fs::read(path)
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_baseline(path: &Path, measurements: &Measurements) -> anyhow::Result<()> {
+    Storage::write(path, serde_json::to_vec_pretty(measurements)?)
+}
+
+/// Measurement of the current run compared to the baseline
+pub struct Comparison {
+    pub test: String,
+    pub key: String,
+    pub baseline: Option<f64>,
+    pub current: f64,
+}
+
+impl Comparison {
+    /// Relative change from the baseline, in percents
+    pub fn delta(&self) -> Option<f64> {
+        let baseline = self.baseline.filter(|b| *b != 0.)?;
+        Some((self.current - baseline) / baseline.abs() * 100.)
+    }
+
+    /// Whether the change is an improvement, `None` if there is no change
+    pub fn improved(&self) -> Option<bool> {
+        let delta = self.delta().filter(|d| *d != 0.)?;
+        Some((delta > 0.) == higher_is_better(&self.key.to_lowercase()))
+    }
+}
+
+pub fn compare(current: &Measurements, baseline: &Measurements) -> Vec<Comparison> {
+    current
+        .iter()
+        .flat_map(|(test, values)| {
+            values.iter().map(move |(key, &value)| Comparison {
+                test: test.clone(),
+                key: key.clone(),
+                baseline: baseline.get(test).and_then(|b| b.get(key)).copied(),
+                current: value,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,131 @@
+use std::io::Write;
+
+use crate::{history::Run, transcript, tree, Test, TestResult};
+
+/// Result of a test in an archived run, `None` if the test is not part of the run
+struct Point<'a> {
+    run: &'a Run,
+    index: usize,
+    result: Option<TestResult>,
+}
+
+/// Whether `test` is the one identified by `id`: its hierarchical number, its description or the
+/// path of descriptions of its subtests
+fn matches(tests: &[Test], index: usize, id: &str) -> bool {
+    let test = &tests[index];
+    test.hierarchical_number() == id
+        || test.desc.as_deref() == Some(id)
+        || tree::description_path(tests, index) == id
+}
+
+/// Finds the run where the test identified by `id` started failing, walking the `runs` from the
+/// newest one, and prints the range of runs where the change happened
+pub fn bisect(
+    out: &mut impl Write,
+    runs: &[Run],
+    parse: impl Fn(&str) -> anyhow::Result<Vec<Test>>,
+    id: &str,
+) -> anyhow::Result<()> {
+    if runs.is_empty() {
+        anyhow::bail!("No run of the current commands was archived");
+    }
+
+    let mut points = Vec::with_capacity(runs.len());
+    let mut name = None;
+    for (index, run) in runs.iter().enumerate() {
+        let tests = match run.read().and_then(|tap| parse(&tap)) {
+            Ok(tests) => tests,
+            Err(e) => {
+                writeln!(out, "Skipping the run of {}: {e:#}", time(run))?;
+                continue;
+            }
+        };
+        let test = (0..tests.len()).find(|&i| matches(&tests, i, id));
+        if let Some(i) = test {
+            name = Some(tree::breadcrumb(&tests, i));
+        }
+        points.push(Point {
+            run,
+            index,
+            result: test.map(|i| tests[i].status()),
+        });
+    }
+    let Some(name) = name else {
+        anyhow::bail!("No archived run has a test `{id}`");
+    };
+
+    let present = points
+        .iter()
+        .filter(|p| p.result.is_some())
+        .collect::<Vec<_>>();
+    let newest = present.last().expect("the test is in a run");
+    if newest.result != Some(TestResult::Fail) {
+        writeln!(
+            out,
+            "{name} does not fail in the last run containing it ({})",
+            describe(newest, runs.len())
+        )?;
+        return Ok(());
+    }
+
+    // Oldest run of the failures leading to the newest run, and the run without failure before it
+    let streak = present
+        .iter()
+        .rev()
+        .take_while(|p| p.result == Some(TestResult::Fail))
+        .count();
+    let first_bad = present[present.len() - streak];
+    match present.len().checked_sub(streak + 1).map(|i| present[i]) {
+        None => writeln!(
+            out,
+            "{name} failed in all the {streak} runs containing it, the oldest one being {}",
+            describe(first_bad, runs.len())
+        )?,
+        Some(last_good) => {
+            writeln!(
+                out,
+                "{name} started failing in {}",
+                describe(first_bad, runs.len())
+            )?;
+            writeln!(
+                out,
+                "Last run where it did not fail ({}): {}",
+                last_good.result.expect("the test is in the run").name(),
+                describe(last_good, runs.len())
+            )?;
+            let missing = first_bad.index - last_good.index - 1;
+            if missing != 0 {
+                writeln!(out, "{missing} runs in between did not contain it")?;
+            }
+            if let (Some(good), Some(bad)) = (&last_good.run.commit, &first_bad.run.commit) {
+                match good.hash == bad.hash {
+                    true => writeln!(
+                        out,
+                        "Both runs are on commit {}, the change was not committed in between",
+                        good.short()
+                    )?,
+                    false => writeln!(
+                        out,
+                        "Suspected commits: git log {}..{}",
+                        good.short(),
+                        bad.short()
+                    )?,
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn time(run: &Run) -> String {
+    transcript::timestamp(run.time)
+}
+
+/// Describes the run of `point` as `run N of M, <time>[, commit <commit>]`
+fn describe(point: &Point, total: usize) -> String {
+    let mut description = format!("run {} of {total}, {}", point.index + 1, time(point.run));
+    if let Some(commit) = &point.run.commit {
+        description.push_str(&format!(", commit {commit}"));
+    }
+    description
+}
@@ -0,0 +1,12 @@
+use std::io::Write;
+
+use base64::Engine;
+
+/// Copies `text` to the system clipboard through the terminal with an OSC 52 escape sequence,
+/// which also works over SSH and inside tmux (with `set-clipboard on`)
+pub fn copy(text: &str) -> std::io::Result<()> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{encoded}\x07")?;
+    stdout.flush()
+}
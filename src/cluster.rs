@@ -0,0 +1,73 @@
+use std::{
+    collections::HashSet,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+/// Number of consecutive words making a shingle
+const SHINGLE_WORDS: usize = 3;
+/// Share of shingles two messages must have in common to be in the same cluster
+const THRESHOLD: f64 = 0.5;
+
+/// Words of a message, the parts which differ between occurrences of a same problem (numbers,
+/// addresses, hashes) being replaced by `#`
+fn words(message: &str) -> Vec<String> {
+    message
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let variable = word.chars().any(|c| c.is_ascii_digit())
+                || (word.len() >= 8 && word.chars().all(|c| c.is_ascii_hexdigit()));
+            match variable {
+                true => "#".to_owned(),
+                false => word.to_owned(),
+            }
+        })
+        .collect()
+}
+
+/// Hashes of the runs of consecutive words of a message, or of the message itself when it is
+/// shorter than a run
+fn shingles(message: &str) -> HashSet<u64> {
+    let words = words(message);
+    let hash = |words: &[String]| {
+        let mut hasher = DefaultHasher::new();
+        words.hash(&mut hasher);
+        hasher.finish()
+    };
+    match words.len() {
+        n if n < SHINGLE_WORDS => HashSet::from([hash(&words)]),
+        _ => words.windows(SHINGLE_WORDS).map(hash).collect(),
+    }
+}
+
+/// Jaccard index of two sets of shingles
+fn similarity(a: &HashSet<u64>, b: &HashSet<u64>) -> f64 {
+    let common = a.intersection(b).count();
+    common as f64 / (a.len() + b.len() - common) as f64
+}
+
+/// Groups the similar messages, as their indices, the largest groups first. The first message of
+/// a group is the one it was compared with, representing it.
+pub fn group(messages: &[&str]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<(HashSet<u64>, Vec<usize>)> = Vec::new();
+    for (index, message) in messages.iter().enumerate() {
+        let shingles = shingles(message);
+        let closest = groups
+            .iter_mut()
+            .map(|(representative, members)| (similarity(representative, &shingles), members))
+            .filter(|(similarity, _)| *similarity >= THRESHOLD)
+            .max_by(|(a, _), (b, _)| a.total_cmp(b));
+        match closest {
+            Some((_, members)) => members.push(index),
+            None => groups.push((shingles, vec![index])),
+        }
+    }
+    let mut groups = groups
+        .into_iter()
+        .map(|(_, members)| members)
+        .collect::<Vec<_>>();
+    // Stable, the groups of a same size stay in the order of their first message
+    groups.sort_by_key(|members| std::cmp::Reverse(members.len()));
+    groups
+}
@@ -0,0 +1,29 @@
+use std::{
+    env,
+    io::{self, IsTerminal},
+    sync::OnceLock,
+};
+
+/// Whether a variable is set to a value other than `0` or `false`
+fn set(var: &str) -> bool {
+    env::var(var).is_ok_and(|value| !value.is_empty() && value != "0" && value != "false")
+}
+
+/// Whether an output which is a `terminal` or not gets colors, following the conventions of
+/// `FORCE_COLOR` and `CLICOLOR_FORCE`, which force them, then of `NO_COLOR` and `CLICOLOR=0`,
+/// which disable them
+pub fn enabled(terminal: bool) -> bool {
+    if set("FORCE_COLOR") || set("CLICOLOR_FORCE") {
+        true
+    } else {
+        let disabled = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+            || env::var("CLICOLOR").is_ok_and(|value| value == "0");
+        terminal && !disabled
+    }
+}
+
+/// Whether the standard output gets colors, decided once
+pub fn stdout() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| enabled(io::stdout().is_terminal()))
+}
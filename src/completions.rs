@@ -0,0 +1,69 @@
+use std::io::Write;
+
+use clap::CommandFactory;
+use clap_complete::Shell;
+
+use crate::Args;
+
+/// Generates the completion script for `shell`.
+///
+/// The scripts generated by clap only know about static values, so for the shells that support
+/// it the completion of `--profile` is patched to call `tapr profiles`, listing the profiles of
+/// the config file discovered from the directory the completion is requested in.
+pub fn generate(shell: Shell, out: &mut impl Write) -> std::io::Result<()> {
+    let mut command = Args::command();
+    let name = command.get_name().to_string();
+
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut command, &name, &mut script);
+    let script = String::from_utf8(script).expect("completion script is not UTF-8");
+
+    match shell {
+        Shell::Bash => {
+            out.write_all(script.as_bytes())?;
+            write!(
+                out,
+                r#"
+_{name}_with_profiles() {{
+    case "${{COMP_WORDS[COMP_CWORD-1]}}" in
+        --profile|-P)
+            COMPREPLY=($(compgen -W "$({name} profiles 2>/dev/null)" -- "${{COMP_WORDS[COMP_CWORD]}}"))
+            ;;
+        *)
+            _{name} "$@"
+            ;;
+    esac
+}}
+
+complete -F _{name}_with_profiles -o bashdefault -o default {name}
+"#
+            )
+        }
+        Shell::Zsh => {
+            let profiles = format!(
+                r#"(( $+functions[_{name}_profiles] )) ||
+_{name}_profiles() {{
+    local profiles; profiles=(${{(f)"$({name} profiles 2>/dev/null)"}})
+    _describe -t profiles 'profiles' profiles "$@"
+}}
+
+if [ "$funcstack[1]" = "_{name}" ]; then"#
+            );
+            let script = script
+                .replace(":PROFILE:_default'", &format!(":PROFILE:_{name}_profiles'"))
+                .replace(
+                    &format!(r#"if [ "$funcstack[1]" = "_{name}" ]; then"#),
+                    &profiles,
+                );
+            out.write_all(script.as_bytes())
+        }
+        Shell::Fish => {
+            out.write_all(script.as_bytes())?;
+            writeln!(
+                out,
+                "complete -c {name} -s P -l profile -x -a '({name} profiles 2>/dev/null)'"
+            )
+        }
+        _ => out.write_all(script.as_bytes()),
+    }
+}
@@ -0,0 +1,884 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::Context;
+use itertools::Itertools;
+use serde::{Deserialize, Deserializer, Serialize};
+
+pub const CONFIG_FILE: &str = ".tap-runner.toml";
+const ENV_PREFIX: &str = "TAP_RUNNER_";
+/// Variable giving the project config file instead of looking it up, like `--config`
+const CONFIG_ENV: &str = "TAP_RUNNER_CONFIG";
+
+/// Splits a command given as a single string into its arguments, with the quoting rules of a POSIX
+/// shell, e.g. `cargo test -- --exact "parser::tests"`
+pub fn split_command(command: &str) -> anyhow::Result<Vec<String>> {
+    match shlex::split(command) {
+        Some(args) if !args.is_empty() => Ok(args),
+        Some(_) => anyhow::bail!("The command is empty"),
+        None => anyhow::bail!("Could not split `{command}` into arguments, a quote is not closed"),
+    }
+}
+
+/// Arguments of a command given as a string on the command line or in a variable, split like by
+/// a shell, or on the commas when it has no spaces, e.g. `cargo,build`
+pub fn parse_command(command: &str) -> anyhow::Result<Vec<String>> {
+    match command.contains(',') && !command.contains(char::is_whitespace) {
+        true => Ok(command.split(',').map(ToOwned::to_owned).collect()),
+        false => split_command(command),
+    }
+}
+
+/// Command of the config files, given as the list of its arguments, or as a single string split
+/// by [`split_command`]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Command {
+    Args(Vec<String>),
+    Line(String),
+}
+
+impl Command {
+    fn args<E: serde::de::Error>(self) -> Result<Vec<String>, E> {
+        match self {
+            Command::Args(args) => Ok(args),
+            Command::Line(line) => split_command(&line).map_err(E::custom),
+        }
+    }
+}
+
+fn command<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<String>>, D::Error> {
+    Option::<Command>::deserialize(deserializer)?
+        .map(Command::args)
+        .transpose()
+}
+
+fn commands<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<Vec<String>>>, D::Error> {
+    Option::<Vec<Command>>::deserialize(deserializer)?
+        .map(|commands| commands.into_iter().map(Command::args).collect())
+        .transpose()
+}
+
+/// Values that can be set at each configuration layer. Unset values are inherited from the
+/// layers with a lower precedence.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    /// Given as its arguments, or as a single string split like by a shell
+    #[serde(
+        default,
+        deserialize_with = "command",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub run_command: Option<Vec<String>>,
+    #[serde(
+        default,
+        deserialize_with = "command",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub build_command: Option<Vec<String>>,
+    /// Commands run one after the other before the build command, such as generating fixtures,
+    /// the build stopping at the first one which fails
+    #[serde(
+        default,
+        deserialize_with = "commands",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pre_build: Option<Vec<Vec<String>>>,
+    /// Template of the command running a single test, `{number}`, `{desc}` and `{path}` are
+    /// replaced by the number, description and path of descriptions of the test
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rerun_test_command: Option<Vec<String>>,
+    /// Template of the command running all the tests of a subtest, with the same placeholders as
+    /// `rerun_test_command`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rerun_subtest_command: Option<Vec<String>>,
+    /// Template of the command running all the failing tests at once, `{numbers}`,
+    /// `{descriptions}` and `{paths}` are replaced by the values of the failing tests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rerun_command: Option<Vec<String>>,
+    /// Shell command explaining the selected failure, e.g. `git log -L {line},{line}:{file}`,
+    /// `{number}`, `{desc}`, `{file}` and `{line}` being replaced by the values of the failure
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explain: Option<String>,
+    /// Interpreters the run command is run under one after the other, e.g. `["python3.11",
+    /// "python3.12"]`, each one replacing `{interp}` in the command or being prepended to it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interpreters: Option<Vec<String>>,
+    /// Shard of the tests run by the run command, e.g. `2/8`, given to the command by replacing
+    /// `{shard}`, `{shard_index}` and `{shard_count}` and in the `TAP_SHARD*` variables
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shard: Option<String>,
+    /// Number of shards the run command is run as in parallel, each one being a subtest of the
+    /// runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shards: Option<usize>,
+    /// Seed of the runs, given to the run and rerun commands by replacing `{seed}` and in the
+    /// `TAP_SEED` variable, a new one being drawn for each run when it is not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location_filter: Option<String>,
+    /// jq filter producing the tags of the tests from their diagnostics, instead of their `tags`
+    /// key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag_filter: Option<String>,
+    /// Globs of the files the build depends on, the build is skipped if none of them changed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sources: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root: Option<PathBuf>,
+    /// Directory the build, test and rerun commands are run in, relative to the project root
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+    /// Directory the build commands are run in instead of `cwd`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_cwd: Option<PathBuf>,
+    /// Run the test command in a pseudo-terminal, for the harnesses which only color their
+    /// diagnostics when writing to a terminal
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pty: Option<bool>,
+    /// Extract the TAP document from a larger log, the lines before it and the ones which are not
+    /// TAP being dropped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extract: Option<bool>,
+    /// Regex matching the prefix of the lines of the log, stripped when extracting the document.
+    /// The prefix is detected on the first line of the document when it is not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_prefix: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Flake whose development shell the commands are run in, with `nix develop`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nix: Option<String>,
+    /// Run the commands in the environment of the `.envrc` of the project, by default when
+    /// `direnv` is installed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direnv: Option<bool>,
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+    /// Shell commands receiving the events of the runs on their stdin
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plugins: Option<Vec<String>>,
+    /// Rhai script processing the results
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub script: Option<PathBuf>,
+    /// Golden TAP file the output of the runs is compared with, the differences being reported
+    /// as failing tests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub golden: Option<PathBuf>,
+    /// File of the failures accepted as expected, relative to the project root
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expectations: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "LayoutSettings::is_empty")]
+    pub layout: LayoutSettings,
+    #[serde(default, skip_serializing_if = "TemplateSettings::is_empty")]
+    pub templates: TemplateSettings,
+    #[serde(default, skip_serializing_if = "ThemeSettings::is_empty")]
+    pub theme: ThemeSettings,
+    /// Select the first failure which did not fail in the previous run after each run, instead
+    /// of the first one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub select_new_failure: Option<bool>,
+    /// Moving the selection past the end of the failures or of the tree selects the other end
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wrap_selection: Option<bool>,
+    /// Where the TAP document is read from, e.g. `file:report.tap`, by default the run command
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Keys remapped to the key of another action, e.g. `x = "q"` or `q = "none"` to disable it
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub keys: BTreeMap<String, String>,
+    /// Move with `h`, `j`, `k`, `l`, `g` and `G` like the arrows, `<home>` and `<end>`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vim_keys: Option<bool>,
+}
+
+/// Shell commands run around each run of the tests
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Hooks {
+    /// Run before the build, the run is aborted if it fails
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_run: Option<String>,
+    /// Run after each run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_run: Option<String>,
+    /// Run after the runs with failing tests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_fail: Option<String>,
+    /// Run after the first run without failing tests following runs with some
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_green: Option<String>,
+}
+
+impl Hooks {
+    fn is_empty(&self) -> bool {
+        self == &Hooks::default()
+    }
+
+    fn merge(self, other: Hooks) -> Hooks {
+        Hooks {
+            pre_run: other.pre_run.or(self.pre_run),
+            post_run: other.post_run.or(self.post_run),
+            on_fail: other.on_fail.or(self.on_fail),
+            on_green: other.on_green.or(self.on_green),
+        }
+    }
+}
+
+/// Formats of the rows of the lists, e.g. `"{number} {desc} ({duration})"`
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateSettings {
+    /// Rows of the failures
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+    /// Rows of the skipped tests pane
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<String>,
+    /// Rows of the passing tests in the tree view
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub passed: Option<String>,
+}
+
+impl TemplateSettings {
+    fn is_empty(&self) -> bool {
+        self == &TemplateSettings::default()
+    }
+
+    fn merge(self, other: TemplateSettings) -> TemplateSettings {
+        TemplateSettings {
+            failure: other.failure.or(self.failure),
+            skipped: other.skipped.or(self.skipped),
+            passed: other.passed.or(self.passed),
+        }
+    }
+}
+
+/// Colors of the results and of the selection, as `#rrggbb`, a 256-color palette number or the
+/// name of a terminal color, overriding the ones of the named theme
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ThemeSettings {
+    /// Theme shipped with tap_runner the colors are taken from, `default` if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub success: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub todo: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bonus: Option<String>,
+    /// Background of the selected row of the focused list
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<String>,
+    /// Background of the selected row of the other lists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unfocused_highlight: Option<String>,
+}
+
+impl ThemeSettings {
+    fn is_empty(&self) -> bool {
+        self == &ThemeSettings::default()
+    }
+
+    fn merge(self, other: ThemeSettings) -> ThemeSettings {
+        // Selecting another theme resets the colors overridden in the previous layers
+        let base = match other.name.is_some() {
+            true => ThemeSettings::default(),
+            false => self,
+        };
+        ThemeSettings {
+            name: other.name.or(base.name),
+            skip: other.skip.or(base.skip),
+            success: other.success.or(base.success),
+            fail: other.fail.or(base.fail),
+            todo: other.todo.or(base.todo),
+            bonus: other.bonus.or(base.bonus),
+            highlight: other.highlight.or(base.highlight),
+            unfocused_highlight: other.unfocused_highlight.or(base.unfocused_highlight),
+        }
+    }
+}
+
+/// Pane of the main screen
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Pane {
+    Status,
+    Skipped,
+    Todo,
+    Body,
+}
+
+/// Part of the rows too long for their pane which is cut off
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Truncation {
+    /// The end, like the terminal does
+    End,
+    /// The middle, keeping the end of the path-like descriptions
+    Middle,
+}
+
+/// Sizes and order of the panes of the main screen
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct LayoutSettings {
+    /// Height of the status pane, including its borders
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_height: Option<u16>,
+    /// Maximum height of the skipped tests pane, including its borders
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped_height: Option<u16>,
+    /// Maximum height of the TODO tests pane, including its borders
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub todo_height: Option<u16>,
+    /// Percentage of the width of the body given to the preview
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview_ratio: Option<u16>,
+    /// Order of the panes, from top to bottom
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<Vec<Pane>>,
+    /// Width of each cell of the status pane, in columns
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cell_width: Option<u16>,
+    /// Blank columns between the cells of the status pane
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cell_gap: Option<u16>,
+    /// Character drawn in the color of the result in each cell, instead of filling it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cell_glyph: Option<char>,
+    /// Maximum number of cells on each row of the status pane
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cells_per_row: Option<u16>,
+    /// Show the keys of the main actions of the focused pane at the bottom
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hints: Option<bool>,
+    /// How the descriptions too long for the lists and the titles are shortened
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub truncation: Option<Truncation>,
+}
+
+impl LayoutSettings {
+    const DEFAULT_ORDER: [Pane; 4] = [Pane::Status, Pane::Skipped, Pane::Todo, Pane::Body];
+
+    fn is_empty(&self) -> bool {
+        self == &LayoutSettings::default()
+    }
+
+    fn merge(self, other: LayoutSettings) -> LayoutSettings {
+        LayoutSettings {
+            status_height: other.status_height.or(self.status_height),
+            skipped_height: other.skipped_height.or(self.skipped_height),
+            todo_height: other.todo_height.or(self.todo_height),
+            preview_ratio: other.preview_ratio.or(self.preview_ratio),
+            order: other.order.or(self.order),
+            cell_width: other.cell_width.or(self.cell_width),
+            cell_gap: other.cell_gap.or(self.cell_gap),
+            cell_glyph: other.cell_glyph.or(self.cell_glyph),
+            cells_per_row: other.cells_per_row.or(self.cells_per_row),
+            hints: other.hints.or(self.hints),
+            truncation: other.truncation.or(self.truncation),
+        }
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let Some(ratio) = self.preview_ratio {
+            anyhow::ensure!(
+                ratio <= 100,
+                "layout.preview_ratio must be a percentage, got {ratio}"
+            );
+        }
+        anyhow::ensure!(
+            self.cell_width != Some(0) && self.cells_per_row != Some(0),
+            "layout.cell_width and layout.cells_per_row must be positive"
+        );
+        if let Some(order) = &self.order {
+            // The TODO pane came later, the orders without it stay valid
+            let required = Self::DEFAULT_ORDER.iter().filter(|&&p| p != Pane::Todo);
+            let count = |pane| order.iter().filter(|&&p| p == pane).count();
+            anyhow::ensure!(
+                required.clone().all(|&p| count(p) == 1) && count(Pane::Todo) <= 1,
+                "layout.order must contain each of `status`, `skipped` and `body` exactly once, \
+                 and `todo` at most once"
+            );
+        }
+        Ok(())
+    }
+
+    pub fn status_height(&self) -> u16 {
+        self.status_height.unwrap_or(5)
+    }
+
+    pub fn skipped_height(&self) -> u16 {
+        self.skipped_height.unwrap_or(12)
+    }
+
+    pub fn todo_height(&self) -> u16 {
+        self.todo_height.unwrap_or(8)
+    }
+
+    pub fn preview_ratio(&self) -> u16 {
+        self.preview_ratio.unwrap_or(50)
+    }
+
+    pub fn cell_width(&self) -> u16 {
+        self.cell_width.unwrap_or(1)
+    }
+
+    pub fn cell_gap(&self) -> u16 {
+        self.cell_gap.unwrap_or(0)
+    }
+
+    pub fn hints(&self) -> bool {
+        self.hints.unwrap_or(true)
+    }
+
+    pub fn truncation(&self) -> Truncation {
+        self.truncation.unwrap_or(Truncation::Middle)
+    }
+
+    /// Order of the panes, the TODO pane following the skipped one when it is not given
+    pub fn order(&self) -> Vec<Pane> {
+        let Some(order) = &self.order else {
+            return Self::DEFAULT_ORDER.to_vec();
+        };
+        let mut order = order.clone();
+        if !order.contains(&Pane::Todo) {
+            let skipped = order.iter().position(|&p| p == Pane::Skipped).unwrap();
+            order.insert(skipped + 1, Pane::Todo);
+        }
+        order
+    }
+}
+
+impl Settings {
+    /// Returns `self` with the values set in `other` taking precedence
+    pub fn merge(self, other: Settings) -> Settings {
+        let mut env = self.env;
+        env.extend(other.env);
+        let mut keys = self.keys;
+        keys.extend(other.keys);
+
+        Settings {
+            run_command: other.run_command.or(self.run_command),
+            build_command: other.build_command.or(self.build_command),
+            pre_build: other.pre_build.or(self.pre_build),
+            rerun_test_command: other.rerun_test_command.or(self.rerun_test_command),
+            rerun_subtest_command: other.rerun_subtest_command.or(self.rerun_subtest_command),
+            rerun_command: other.rerun_command.or(self.rerun_command),
+            explain: other.explain.or(self.explain),
+            interpreters: other.interpreters.or(self.interpreters),
+            shard: other.shard.or(self.shard),
+            shards: other.shards.or(self.shards),
+            seed: other.seed.or(self.seed),
+            location_filter: other.location_filter.or(self.location_filter),
+            tag_filter: other.tag_filter.or(self.tag_filter),
+            sources: other.sources.or(self.sources),
+            preview: other.preview.or(self.preview),
+            root: other.root.or(self.root),
+            cwd: other.cwd.or(self.cwd),
+            build_cwd: other.build_cwd.or(self.build_cwd),
+            pty: other.pty.or(self.pty),
+            extract: other.extract.or(self.extract),
+            log_prefix: other.log_prefix.or(self.log_prefix),
+            env,
+            nix: other.nix.or(self.nix),
+            direnv: other.direnv.or(self.direnv),
+            hooks: self.hooks.merge(other.hooks),
+            plugins: other.plugins.or(self.plugins),
+            script: other.script.or(self.script),
+            golden: other.golden.or(self.golden),
+            expectations: other.expectations.or(self.expectations),
+            layout: self.layout.merge(other.layout),
+            templates: self.templates.merge(other.templates),
+            theme: self.theme.merge(other.theme),
+            select_new_failure: other.select_new_failure.or(self.select_new_failure),
+            wrap_selection: other.wrap_selection.or(self.wrap_selection),
+            source: other.source.or(self.source),
+            keys,
+            vim_keys: other.vim_keys.or(self.vim_keys),
+        }
+    }
+
+    /// Reads the settings from the `TAP_RUNNER_*` environment variables
+    pub fn from_env() -> anyhow::Result<Settings> {
+        fn var(name: &str) -> anyhow::Result<Option<String>> {
+            match std::env::var(format!("{ENV_PREFIX}{name}")) {
+                Ok(v) => Ok(Some(v)),
+                Err(std::env::VarError::NotPresent) => Ok(None),
+                Err(e) => Err(e).with_context(|| format!("Invalid {ENV_PREFIX}{name}")),
+            }
+        }
+
+        fn command(name: &str) -> anyhow::Result<Option<Vec<String>>> {
+            Ok(var(name)?.map(|c| c.split(',').map(ToOwned::to_owned).collect()))
+        }
+
+        let command_line = |name: &str| -> anyhow::Result<Option<Vec<String>>> {
+            var(name)?
+                .map(|c| parse_command(&c).with_context(|| format!("Invalid {ENV_PREFIX}{name}")))
+                .transpose()
+        };
+
+        Ok(Settings {
+            run_command: command_line("RUN_COMMAND")?,
+            build_command: command_line("BUILD_COMMAND")?,
+            pre_build: None,
+            rerun_test_command: command("RERUN_TEST_COMMAND")?,
+            rerun_subtest_command: command("RERUN_SUBTEST_COMMAND")?,
+            rerun_command: command("RERUN_COMMAND")?,
+            explain: var("EXPLAIN")?,
+            interpreters: command("INTERPRETERS")?,
+            shard: var("SHARD")?,
+            shards: var("SHARDS")?
+                .map(|v| {
+                    v.parse()
+                        .map_err(|_| anyhow::anyhow!("Invalid {ENV_PREFIX}SHARDS: `{v}`"))
+                })
+                .transpose()?,
+            seed: var("SEED")?
+                .map(|v| {
+                    v.parse()
+                        .map_err(|_| anyhow::anyhow!("Invalid {ENV_PREFIX}SEED: `{v}`"))
+                })
+                .transpose()?,
+            location_filter: var("LOCATION_FILTER")?,
+            tag_filter: var("TAG_FILTER")?,
+            sources: None,
+            preview: var("PREVIEW")?
+                .map(|v| match v.as_str() {
+                    "1" | "true" | "yes" | "on" => Ok(true),
+                    "0" | "false" | "no" | "off" | "" => Ok(false),
+                    _ => Err(anyhow::anyhow!("Invalid {ENV_PREFIX}PREVIEW: `{v}`")),
+                })
+                .transpose()?,
+            root: var("ROOT")?.map(PathBuf::from),
+            cwd: var("CWD")?.map(PathBuf::from),
+            build_cwd: var("BUILD_CWD")?.map(PathBuf::from),
+            pty: None,
+            extract: None,
+            log_prefix: var("LOG_PREFIX")?,
+            env: BTreeMap::new(),
+            nix: var("NIX")?,
+            direnv: None,
+            hooks: Hooks::default(),
+            plugins: None,
+            script: None,
+            golden: var("GOLDEN")?.map(PathBuf::from),
+            expectations: var("EXPECTATIONS")?.map(PathBuf::from),
+            layout: LayoutSettings::default(),
+            templates: TemplateSettings::default(),
+            theme: ThemeSettings {
+                name: var("THEME")?,
+                ..ThemeSettings::default()
+            },
+            select_new_failure: None,
+            wrap_selection: None,
+            source: var("SOURCE")?,
+            keys: BTreeMap::new(),
+            vim_keys: None,
+        })
+    }
+
+    /// Whether the tests must run again for the changes from `old` to apply, the settings
+    /// processing their output (filters, script, golden file, expectations, templates and layout),
+    /// the theme and the key bindings applying to the results already shown
+    pub fn needs_run(&self, old: &Settings) -> bool {
+        let running = |settings: &Settings| Settings {
+            explain: None,
+            location_filter: None,
+            tag_filter: None,
+            preview: None,
+            root: None,
+            script: None,
+            golden: None,
+            expectations: None,
+            layout: LayoutSettings::default(),
+            templates: TemplateSettings::default(),
+            theme: ThemeSettings::default(),
+            select_new_failure: None,
+            wrap_selection: None,
+            keys: BTreeMap::new(),
+            vim_keys: None,
+            ..settings.clone()
+        };
+        running(self) != running(old)
+    }
+
+    /// Describes which settings differ from `old`
+    pub fn changes_from(&self, old: &Settings) -> Vec<String> {
+        [
+            ("run command", old.run_command != self.run_command),
+            ("build command", old.build_command != self.build_command),
+            ("pre-build commands", old.pre_build != self.pre_build),
+            (
+                "rerun test command",
+                old.rerun_test_command != self.rerun_test_command,
+            ),
+            (
+                "rerun subtest command",
+                old.rerun_subtest_command != self.rerun_subtest_command,
+            ),
+            ("rerun command", old.rerun_command != self.rerun_command),
+            ("explain command", old.explain != self.explain),
+            ("interpreters", old.interpreters != self.interpreters),
+            ("shard", old.shard != self.shard),
+            ("shards", old.shards != self.shards),
+            ("seed", old.seed != self.seed),
+            (
+                "location filter",
+                old.location_filter != self.location_filter,
+            ),
+            ("tag filter", old.tag_filter != self.tag_filter),
+            ("sources", old.sources != self.sources),
+            ("preview", old.preview != self.preview),
+            ("root", old.root != self.root),
+            (
+                "working directory",
+                old.cwd != self.cwd || old.build_cwd != self.build_cwd,
+            ),
+            ("pseudo-terminal", old.pty != self.pty),
+            (
+                "log extraction",
+                old.extract != self.extract || old.log_prefix != self.log_prefix,
+            ),
+            ("environment", old.env != self.env),
+            ("nix", old.nix != self.nix),
+            ("direnv", old.direnv != self.direnv),
+            ("hooks", old.hooks != self.hooks),
+            ("plugins", old.plugins != self.plugins),
+            ("script", old.script != self.script),
+            ("golden file", old.golden != self.golden),
+            ("expectations file", old.expectations != self.expectations),
+            ("layout", old.layout != self.layout),
+            ("templates", old.templates != self.templates),
+            ("theme", old.theme != self.theme),
+            (
+                "failure selection",
+                old.select_new_failure != self.select_new_failure,
+            ),
+            (
+                "selection wrapping",
+                old.wrap_selection != self.wrap_selection,
+            ),
+            ("source", old.source != self.source),
+            (
+                "key bindings",
+                old.keys != self.keys || old.vim_keys != self.vim_keys,
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, changed)| *changed)
+        .map(|(field, _)| format!("{field} changed"))
+        .collect()
+    }
+}
+
+/// The configuration read from the user and project config files
+#[derive(Debug, Default)]
+pub struct Config {
+    /// Files the config was loaded from, by increasing precedence
+    pub files: Vec<PathBuf>,
+    /// Project config file given with `--config`, loaded again when the config is reloaded
+    pub explicit: Option<PathBuf>,
+    /// Files which did not exist when the config was loaded, but would be loaded if created
+    pub missing: Vec<PathBuf>,
+    pub settings: Settings,
+    pub profile: BTreeMap<String, Settings>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read config file {}", path.display()))?;
+
+        let parse = || -> anyhow::Result<Self> {
+            let mut table: toml::Table = toml::from_str(&content)?;
+            let profile = match table.remove("profile") {
+                None => BTreeMap::new(),
+                Some(p) => p.try_into().context("Invalid profiles")?,
+            };
+
+            Ok(Self {
+                files: vec![path.to_owned()],
+                explicit: None,
+                missing: Vec::new(),
+                settings: toml::Value::Table(table).try_into()?,
+                profile,
+            })
+        };
+
+        parse().with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    /// Returns `self` with the values of `other` taking precedence
+    pub fn merge(self, other: Config) -> Config {
+        let mut profile = self.profile;
+        for (name, settings) in other.profile {
+            let merged = match profile.remove(&name) {
+                None => settings,
+                Some(base) => base.merge(settings),
+            };
+            profile.insert(name, merged);
+        }
+
+        Config {
+            files: self.files.into_iter().chain(other.files).collect(),
+            explicit: other.explicit.or(self.explicit),
+            missing: self.missing.into_iter().chain(other.missing).collect(),
+            settings: self.settings.merge(other.settings),
+            profile,
+        }
+    }
+
+    /// Last modification times of the files the config was loaded from, and of the missing ones
+    /// once they are created
+    pub fn modified(&self) -> Vec<Option<SystemTime>> {
+        self.files
+            .iter()
+            .chain(&self.missing)
+            .map(|p| std::fs::metadata(p).and_then(|m| m.modified()).ok())
+            .collect()
+    }
+
+    /// Describes the differences with an `old` version of the config, as seen by a user of the
+    /// `active` profile
+    pub fn changes_from(&self, old: &Config, active: Option<&str>) -> Vec<String> {
+        let mut changes = Vec::new();
+
+        let added = self
+            .profile
+            .keys()
+            .filter(|p| !old.profile.contains_key(*p))
+            .collect_vec();
+        let removed = old
+            .profile
+            .keys()
+            .filter(|p| !self.profile.contains_key(*p))
+            .collect_vec();
+        for (what, profiles) in [("added", added), ("removed", removed)] {
+            if !profiles.is_empty() {
+                changes.push(format!("profiles {what}: {}", profiles.iter().join(", ")));
+            }
+        }
+
+        if let (Ok(new), Ok(old)) = (self.layered(active), old.layered(active)) {
+            changes.extend(new.changes_from(&old));
+        }
+
+        changes
+    }
+
+    /// Path of the user config file
+    pub fn user_file() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("tap-runner").join("config.toml"))
+    }
+
+    /// Project config file given explicitly, with `--config` (`explicit`) or the environment
+    fn explicit_file(explicit: Option<&Path>) -> Option<PathBuf> {
+        explicit.map(Path::to_owned).or_else(|| {
+            std::env::var_os(CONFIG_ENV)
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from)
+        })
+    }
+
+    /// Looks for a project config file in the current directory or any of its ancestors, unless
+    /// one was given explicitly
+    pub fn find(explicit: Option<&Path>) -> anyhow::Result<Option<PathBuf>> {
+        if let Some(path) = Self::explicit_file(explicit) {
+            if !path.is_file() {
+                anyhow::bail!("The config file {} does not exist", path.display());
+            }
+            return Ok(Some(path));
+        }
+        let cwd = std::env::current_dir()?;
+        Ok(cwd
+            .ancestors()
+            .map(|dir| dir.join(CONFIG_FILE))
+            .find(|path| path.is_file()))
+    }
+
+    /// Directory of the project config file, or the current directory if there is none or it was
+    /// given explicitly
+    pub fn project_root(explicit: Option<&Path>) -> anyhow::Result<PathBuf> {
+        if Self::explicit_file(explicit).is_some() {
+            return Ok(std::env::current_dir()?);
+        }
+        match Self::find(None)? {
+            Some(file) => Ok(file.parent().expect("config file has a parent").to_owned()),
+            None => Ok(std::env::current_dir()?),
+        }
+    }
+
+    /// Loads the user config file, overridden by the project config file, `explicit` if given
+    pub fn discover(explicit: Option<&Path>) -> anyhow::Result<Self> {
+        let user = Self::user_file().filter(|p| p.is_file());
+        let project = Self::find(explicit)?;
+
+        // A project config file created closer to the current directory takes precedence
+        let mut missing = Vec::new();
+        missing.extend(Self::user_file().filter(|_| user.is_none()));
+        if Self::explicit_file(explicit).is_none() {
+            let cwd = std::env::current_dir()?;
+            missing.extend(
+                cwd.ancestors()
+                    .map(|dir| dir.join(CONFIG_FILE))
+                    .take_while(|path| !path.is_file()),
+            );
+        }
+
+        let config = [user, project]
+            .into_iter()
+            .flatten()
+            .try_fold(Self::default(), |config, path| {
+                anyhow::Ok(config.merge(Self::load(&path)?))
+            })?;
+        Ok(Self {
+            explicit: explicit.map(Path::to_owned),
+            missing,
+            ..config
+        })
+    }
+
+    pub fn profile(&self, name: &str) -> anyhow::Result<&Settings> {
+        self.profile.get(name).with_context(|| {
+            format!(
+                "No profile named `{name}`, available profiles: {}",
+                self.profile.keys().map(|p| format!("`{p}`")).join(", ")
+            )
+        })
+    }
+
+    /// Settings from the config files, with the `profile` ones taking precedence
+    pub fn layered(&self, profile: Option<&str>) -> anyhow::Result<Settings> {
+        let settings = self.settings.clone();
+        Ok(match profile {
+            None => settings,
+            Some(name) => settings.merge(self.profile(name)?.clone()),
+        })
+    }
+
+    /// Computes the effective settings, by increasing precedence: the config files, the
+    /// `profile`, the environment and the `cli` settings
+    pub fn resolve(&self, profile: Option<&str>, cli: &Settings) -> anyhow::Result<Settings> {
+        Ok(self
+            .layered(profile)?
+            .merge(Settings::from_env()?)
+            .merge(cli.clone()))
+    }
+}
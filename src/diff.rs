@@ -0,0 +1,144 @@
+/// Kind of a line of a unified diff
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    /// `@@ -a,b +c,d @@` header of a group of changes
+    Hunk,
+    Context,
+    Removed,
+    Added,
+}
+
+pub struct Line {
+    pub kind: Kind,
+    pub text: String,
+}
+
+/// Size of the table of the longest common subsequence above which the changed lines are all
+/// replaced, instead of taking too much time and memory
+const MAX_TABLE: usize = 16_000_000;
+
+/// Operation transforming the old lines into the new ones
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Keep,
+    Remove,
+    Add,
+}
+
+/// Longest common subsequence of the lines, as the operations transforming `old` into `new`
+fn edit_script(old: &[&str], new: &[&str]) -> Vec<Op> {
+    if old.len().saturating_mul(new.len()) > MAX_TABLE {
+        let removed = std::iter::repeat_n(Op::Remove, old.len());
+        return removed
+            .chain(std::iter::repeat_n(Op::Add, new.len()))
+            .collect();
+    }
+
+    // lcs[i][j] is the length of the LCS of old[i..] and new[j..]
+    let mut lcs = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = match old[i] == new[j] {
+                true => lcs[i + 1][j + 1] + 1,
+                false => lcs[i + 1][j].max(lcs[i][j + 1]),
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut ops = Vec::with_capacity(old.len().max(new.len()));
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ops.push(Op::Keep);
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(Op::Remove);
+            i += 1;
+        } else {
+            ops.push(Op::Add);
+            j += 1;
+        }
+    }
+    ops
+}
+
+/// Unified diff between `old` and `new`, with `context` unchanged lines around the changes.
+/// Empty if the texts have the same lines.
+pub fn unified(old: &str, new: &str, context: usize) -> Vec<Line> {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+
+    // The common prefix and suffix are skipped to keep the quadratic part small
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let ops = std::iter::repeat_n(Op::Keep, prefix)
+        .chain(edit_script(
+            &old[prefix..old.len() - suffix],
+            &new[prefix..new.len() - suffix],
+        ))
+        .chain(std::iter::repeat_n(Op::Keep, suffix))
+        .collect::<Vec<_>>();
+
+    // Position in the old and new lines before each operation
+    let mut positions = Vec::with_capacity(ops.len() + 1);
+    let (mut i, mut j) = (0, 0);
+    for op in &ops {
+        positions.push((i, j));
+        match op {
+            Op::Keep => (i, j) = (i + 1, j + 1),
+            Op::Remove => i += 1,
+            Op::Add => j += 1,
+        }
+    }
+    positions.push((i, j));
+
+    let changes = (0..ops.len()).filter(|&k| ops[k] != Op::Keep);
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for k in changes {
+        let start = k.saturating_sub(context);
+        let end = (k + 1 + context).min(ops.len());
+        match hunks.last_mut() {
+            Some((_, last)) if start <= *last => *last = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut lines = Vec::new();
+    for (start, end) in hunks {
+        let ((old_start, new_start), (old_end, new_end)) = (positions[start], positions[end]);
+        lines.push(Line {
+            kind: Kind::Hunk,
+            text: format!(
+                "@@ -{},{} +{},{} @@",
+                old_start + 1,
+                old_end - old_start,
+                new_start + 1,
+                new_end - new_start
+            ),
+        });
+        for k in start..end {
+            let (i, j) = positions[k];
+            lines.push(match ops[k] {
+                Op::Keep => Line {
+                    kind: Kind::Context,
+                    text: format!(" {}", new[j]),
+                },
+                Op::Remove => Line {
+                    kind: Kind::Removed,
+                    text: format!("-{}", old[i]),
+                },
+                Op::Add => Line {
+                    kind: Kind::Added,
+                    text: format!("+{}", new[j]),
+                },
+            });
+        }
+    }
+    lines
+}
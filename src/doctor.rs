@@ -0,0 +1,263 @@
+use std::{
+    env,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    color,
+    config::{Config, Settings},
+    editor, image, App, Start,
+};
+
+/// Outcome of a check
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// Result of a check, with how to fix it when it did not pass
+struct Check {
+    status: Status,
+    message: String,
+    hint: Option<String>,
+}
+
+impl Check {
+    fn ok(message: impl Into<String>) -> Self {
+        Check {
+            status: Status::Ok,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn warning(message: impl Into<String>, hint: impl Into<String>) -> Self {
+        Check {
+            status: Status::Warning,
+            message: message.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn error(message: impl Into<String>, hint: impl Into<String>) -> Self {
+        Check {
+            status: Status::Error,
+            message: message.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+/// Looks `program` up in the `PATH`, or relative to the current directory when it is a path
+pub fn find_program(program: &str) -> anyhow::Result<PathBuf> {
+    which::which(program).map_err(|e| match e {
+        which::Error::CannotFindBinaryPath => anyhow::anyhow!("Can't find executable `{program}`"),
+        e => anyhow::anyhow!("Could not look for executable `{program}`: {e}"),
+    })
+}
+
+/// Checks that `bat`, which draws the preview, is installed
+pub fn check_preview() -> anyhow::Result<()> {
+    match find_program("bat") {
+        Ok(_) => Ok(()),
+        Err(e) => anyhow::bail!("{e}, could not enable --preview"),
+    }
+}
+
+/// Checks the configuration files and the settings of the `profile`, or of all the profiles if
+/// none is given
+fn configuration(explicit: Option<&Path>, profile: Option<String>) -> Vec<Check> {
+    let mut checks = Vec::new();
+    match Config::user_file().filter(|p| p.is_file()) {
+        Some(path) => checks.push(Check::ok(format!("User config file {}", path.display()))),
+        None => checks.push(Check::ok("No user config file")),
+    }
+    match Config::find(explicit) {
+        Ok(Some(path)) => checks.push(Check::ok(format!("Project config file {}", path.display()))),
+        Ok(None) => checks.push(Check::ok("No project config file")),
+        Err(e) => checks.push(Check::error(
+            format!("{e:#}"),
+            "Fix the path given to --config",
+        )),
+    }
+    let config = match Config::discover(explicit) {
+        Ok(config) => config,
+        Err(e) => {
+            checks.push(Check::error(
+                format!("{e:#}"),
+                "Fix the config file, `tapr --show-config` shows the settings it gives",
+            ));
+            return checks;
+        }
+    };
+
+    let profiles = match profile {
+        Some(profile) => vec![Some(profile)],
+        None if config.profile.is_empty() => vec![None],
+        None => config.profile.keys().cloned().map(Some).collect(),
+    };
+    for profile in profiles {
+        let name = match &profile {
+            Some(profile) => format!("profile `{profile}`"),
+            None => "default settings".to_owned(),
+        };
+        if profile.is_none() {
+            // An invalid configuration is reported below
+            let resolved = config.resolve(None, &Settings::default());
+            let unconfigured =
+                resolved.is_ok_and(|s| s.run_command.is_none() && s.source.is_none());
+            if unconfigured {
+                checks.push(Check::warning(
+                    "No run command configured",
+                    "Give it on the command line, or add a `run_command` or a profile to the \
+                     config file",
+                ));
+                continue;
+            }
+        }
+        let app = Config::discover(explicit)
+            .and_then(|config| App::new(config, Settings::default(), profile, Start::Idle, false));
+        let app = match app {
+            Ok(app) => app,
+            Err(e) => {
+                checks.push(Check::error(
+                    format!("Invalid {name}: {e:#}"),
+                    "Fix the settings in the config file",
+                ));
+                continue;
+            }
+        };
+        checks.push(Check::ok(format!("Valid {name}")));
+        if !app.wrapper.is_empty() {
+            checks.push(Check::ok(format!(
+                "The commands of the {name} run through `{}`, only it is checked",
+                app.wrapper.join(" ")
+            )));
+        }
+        for (setting, command) in app.programs() {
+            checks.push(program(&name, setting, &command));
+        }
+    }
+    checks
+}
+
+/// Checks that the `program` of the `setting` of the profile `name` can be run
+fn program(name: &str, setting: &str, program: &str) -> Check {
+    match find_program(program) {
+        Ok(path) => Check::ok(format!(
+            "`{program}` of the {setting} of the {name}: {}",
+            path.display()
+        )),
+        Err(e) => Check::error(
+            format!("{e}, needed by the {setting} of the {name}"),
+            "Install it, or fix the command in the config file",
+        ),
+    }
+}
+
+/// Checks the external programs used by some of the features
+fn tools() -> Vec<Check> {
+    let tools: [(&str, &str); 3] = [
+        ("bat", "Install it to enable --preview"),
+        (
+            "sh",
+            "The hooks, the plugins and the explain command need a POSIX shell",
+        ),
+        ("git", "Install it to record the commit of each run"),
+    ];
+    let mut checks = tools
+        .iter()
+        .map(|(tool, hint)| match find_program(tool) {
+            Ok(path) => Check::ok(format!("`{tool}`: {}", path.display())),
+            Err(e) => Check::warning(e.to_string(), *hint),
+        })
+        .collect::<Vec<_>>();
+
+    let editor = editor();
+    let program = editor.split_whitespace().next().unwrap_or("vi");
+    checks.push(match find_program(program) {
+        Ok(path) => Check::ok(format!("Editor `{editor}`: {}", path.display())),
+        Err(e) => Check::warning(
+            format!("{e}, used to edit the failing tests with `e`"),
+            "Set $VISUAL or $EDITOR to an installed editor",
+        ),
+    });
+    checks
+}
+
+/// Checks the capabilities of the terminal
+fn terminal() -> Vec<Check> {
+    let mut checks = Vec::new();
+    checks.push(match io::stdout().is_terminal() {
+        true => Check::ok("The output is a terminal"),
+        false => Check::warning(
+            "The output is not a terminal",
+            "Run the TUI in a terminal, or use --once to run the tests without it",
+        ),
+    });
+    checks.push(match env::var("TERM") {
+        Ok(term) if !term.is_empty() && term != "dumb" => Check::ok(format!("TERM is `{term}`")),
+        _ => Check::warning(
+            "TERM is not set, or is `dumb`",
+            "Set TERM to the terminal type, such as `xterm-256color`",
+        ),
+    });
+    if !color::enabled(true) {
+        checks.push(Check::ok(
+            "The colors are disabled by NO_COLOR or CLICOLOR, the results are shown as symbols",
+        ));
+    }
+    checks.push(match env::var("COLORTERM") {
+        Ok(colors) if colors == "truecolor" || colors == "24bit" => {
+            Check::ok("The terminal supports 24-bit colors")
+        }
+        _ => Check::warning(
+            "COLORTERM does not announce 24-bit colors",
+            "The RGB colors, such as the one of the selection, may be approximated by the terminal",
+        ),
+    });
+    checks.push(match image::Protocol::detect() {
+        Some(protocol) => Check::ok(format!("Images are drawn with the {protocol:?} protocol")),
+        None => Check::warning(
+            "No graphics protocol detected, the images can't be drawn in the terminal",
+            "Use kitty, iTerm2 or WezTerm to see the image artifacts in the preview",
+        ),
+    });
+    checks
+}
+
+/// Runs all the checks and prints their results to `out`, returns whether none failed
+pub fn run(
+    out: &mut impl Write,
+    explicit: Option<&Path>,
+    profile: Option<String>,
+) -> anyhow::Result<bool> {
+    let sections = [
+        ("Configuration", configuration(explicit, profile)),
+        ("Tools", tools()),
+        ("Terminal", terminal()),
+    ];
+    let mut failed = false;
+    for (i, (section, checks)) in sections.iter().enumerate() {
+        if i != 0 {
+            writeln!(out)?;
+        }
+        writeln!(out, "{section}")?;
+        for check in checks {
+            let mark = match check.status {
+                Status::Ok => "✔",
+                Status::Warning => "⚠",
+                Status::Error => "✘",
+            };
+            writeln!(out, "  {mark} {}", check.message)?;
+            if let Some(hint) = &check.hint {
+                writeln!(out, "      {hint}")?;
+            }
+            failed |= check.status == Status::Error;
+        }
+    }
+    Ok(!failed)
+}
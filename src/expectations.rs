@@ -0,0 +1,61 @@
+use std::{
+    collections::BTreeMap,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use crate::storage::Storage;
+
+/// Default name of the expectations file, in the project root
+pub const FILE: &str = ".tap-runner-expected.toml";
+
+/// Failures accepted with `Y`: their message, by identity of the test (its path of descriptions,
+/// or its number). The failures with the same message are expected, until the entry is removed
+/// from the file.
+#[derive(Default)]
+pub struct Expectations {
+    path: PathBuf,
+    messages: BTreeMap<String, String>,
+}
+
+impl Expectations {
+    /// Reads the expectations file at `path`, which has none if it does not exist
+    pub fn load(path: PathBuf) -> anyhow::Result<Self> {
+        let messages = match std::fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents)
+                .with_context(|| format!("Invalid expectations file {}", path.display()))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Could not read {}", path.display()));
+            }
+        };
+        Ok(Self { path, messages })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the failure of the test `key` with this `message` is expected
+    pub fn matches(&self, key: &str, message: &str) -> bool {
+        self.messages
+            .get(key)
+            .is_some_and(|m| m.trim() == message.trim())
+    }
+
+    /// Expects the test `key` to fail with `message` from now on
+    pub fn accept(&mut self, key: String, message: &str) {
+        self.messages.insert(key, message.trim().to_owned());
+    }
+
+    /// Stops expecting the test `key` to fail
+    pub fn remove(&mut self, key: &str) {
+        self.messages.remove(key);
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        Storage::write(&self.path, toml::to_string(&self.messages)?)
+    }
+}
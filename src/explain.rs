@@ -0,0 +1,51 @@
+use std::path::Path;
+
+use crate::job::Job;
+
+/// Placeholders of the explain command, and the variables giving their values to the command
+const PLACEHOLDERS: &[(&str, &str)] = &[
+    ("{number}", "TAP_RUNNER_NUMBER"),
+    ("{desc}", "TAP_RUNNER_DESC"),
+    ("{file}", "TAP_RUNNER_FILE"),
+    ("{line}", "TAP_RUNNER_LINE"),
+];
+
+/// Output of the explain command run for a failure, shown in a popup
+pub struct Explanation {
+    /// Number and description of the failure
+    pub test: String,
+    pub job: Job,
+    pub scroll: u16,
+}
+
+/// Shell command of the explain `template`, the placeholders being replaced by references to the
+/// variables given by [`variables`], so that their values need no quoting
+pub fn command(template: &str) -> String {
+    PLACEHOLDERS
+        .iter()
+        .fold(template.to_owned(), |command, (placeholder, var)| {
+            command.replace(placeholder, &format!("\"${var}\""))
+        })
+}
+
+/// Whether the explain `template` needs the location of the failure
+pub fn needs_location(template: &str) -> bool {
+    template.contains("{file}") || template.contains("{line}")
+}
+
+/// Variables describing the failure to the explain command
+pub fn variables(
+    number: &str,
+    desc: Option<&str>,
+    location: Option<(&Path, usize)>,
+) -> Vec<(&'static str, String)> {
+    let (file, line) = match location {
+        Some((file, line)) => (file.display().to_string(), line.to_string()),
+        None => Default::default(),
+    };
+    [number.to_owned(), desc.unwrap_or("").to_owned(), file, line]
+        .into_iter()
+        .zip(PLACEHOLDERS)
+        .map(|(value, (_, var))| (*var, value))
+        .collect()
+}
@@ -0,0 +1,97 @@
+use regex::Regex;
+
+/// Prefix of the lines of a log a TAP document is extracted from with `--extract`, such as the
+/// timestamps of a CI log
+#[derive(Clone)]
+pub enum Prefix {
+    /// The text before the first line of the document, of the same width on every line
+    Detected,
+    /// Stripped from the lines it matches at their start
+    Regex(Regex),
+}
+
+/// Extracts the lines of a TAP document from a log, the lines before the document starts and
+/// the ones which are not TAP being dropped
+pub struct Extractor<'a> {
+    prefix: &'a Prefix,
+    /// Width of the detected prefix, set once the document started (to zero with a regex)
+    width: Option<usize>,
+}
+
+impl<'a> Extractor<'a> {
+    pub fn new(prefix: &'a Prefix) -> Self {
+        Self {
+            prefix,
+            width: None,
+        }
+    }
+
+    /// Line of the document in a line of the log, `None` if it is not part of it
+    pub fn line<'l>(&mut self, line: &'l str) -> Option<&'l str> {
+        let content = match self.prefix {
+            Prefix::Regex(regex) => match regex.find(line) {
+                Some(prefix) if prefix.start() == 0 => &line[prefix.end()..],
+                _ => line,
+            },
+            Prefix::Detected => match self.width {
+                Some(width) => line.get(width..)?,
+                None => {
+                    let width = starts_document(line)?;
+                    self.width = Some(width);
+                    return Some(&line[width..]);
+                }
+            },
+        };
+        if self.width.is_none() {
+            starts_document(content).filter(|&start| start == 0)?;
+            self.width = Some(0);
+        }
+        is_tap(content).then_some(content)
+    }
+}
+
+/// Start of the version or plan line starting a document in `line`, after its prefix
+fn starts_document(line: &str) -> Option<usize> {
+    line.match_indices("TAP version ")
+        .chain(line.match_indices("1.."))
+        .map(|(start, _)| start)
+        .find(|&start| {
+            let rest = &line[start..];
+            match rest.strip_prefix("TAP version ") {
+                Some(version) => version.trim_end().parse::<u32>().is_ok(),
+                None => is_plan(rest),
+            }
+        })
+}
+
+/// Whether `line` is a plan, `1..<count>` with an optional comment
+fn is_plan(line: &str) -> bool {
+    let Some(count) = line.strip_prefix("1..") else {
+        return false;
+    };
+    let end = count
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(count.len());
+    end != 0 && {
+        let rest = count[end..].trim_start();
+        rest.is_empty() || rest.starts_with('#')
+    }
+}
+
+/// Whether `line` can be part of a TAP document: a TAP line, or an indented line of a subtest or
+/// of diagnostics
+fn is_tap(line: &str) -> bool {
+    let point = |keyword: &str| {
+        line.strip_prefix(keyword)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+    };
+    line.trim().is_empty()
+        || line.starts_with(char::is_whitespace)
+        || line.starts_with('#')
+        || line.starts_with("TAP version ")
+        || line.starts_with("Bail out!")
+        || line.starts_with("pragma ")
+        || is_plan(line)
+        || point("ok")
+        || point("not ok")
+}
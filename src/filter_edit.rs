@@ -0,0 +1,42 @@
+/// Editor of the location filter, opened with `L`, the filter being applied to the results shown
+/// as it is typed
+pub struct FilterEdit {
+    pub text: String,
+    /// Filter active when the editor was opened, restored with `<esc>`
+    pub original: Option<String>,
+    /// Why the filter as typed can't be applied, the last valid one staying active
+    pub error: Option<String>,
+    /// Warnings of the tests with the filter applied
+    pub warning: Option<String>,
+}
+
+impl FilterEdit {
+    pub fn new(original: Option<String>) -> Self {
+        Self {
+            text: original.clone().unwrap_or_default(),
+            original,
+            error: None,
+            warning: None,
+        }
+    }
+
+    /// Filter as typed, `None` to remove the filter
+    pub fn filter(&self) -> Option<&str> {
+        Some(self.text.trim()).filter(|t| !t.is_empty())
+    }
+
+    /// Text of the input bar
+    pub fn prompt(&self) -> String {
+        format!("Location filter: {}█", self.text)
+    }
+
+    /// Line below the input bar
+    pub fn status(&self) -> String {
+        match (&self.error, &self.warning) {
+            (Some(message), _) | (None, Some(message)) => {
+                message.lines().next().unwrap_or_default().to_owned()
+            }
+            (None, None) => "<enter> keeps the filter, <esc> restores the previous one".to_owned(),
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use itertools::Itertools;
+
+use crate::storage::stable_hash;
+
+/// Computes a fingerprint of the files matching the `sources` globs, changing whenever a file is
+/// added, removed or modified
+pub fn sources(sources: &[String]) -> anyhow::Result<u64> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for pattern in sources {
+        for entry in glob::glob(pattern).with_context(|| format!("Invalid glob `{pattern}`"))? {
+            files.push(entry?);
+        }
+    }
+
+    let mut data = Vec::new();
+    for file in files.into_iter().sorted().dedup() {
+        let metadata = std::fs::metadata(&file)
+            .with_context(|| format!("Could not read metadata of {}", file.display()))?;
+        data.extend(file.to_string_lossy().as_bytes());
+        data.extend(format!("\0{:?}\0{}\0", metadata.modified()?, metadata.len()).as_bytes());
+    }
+
+    Ok(stable_hash(&data))
+}
@@ -0,0 +1,46 @@
+use std::{fmt, path::Path, process::Command};
+
+use serde::{Deserialize, Serialize};
+
+/// Commit checked out when the tests were run
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Commit {
+    pub hash: String,
+    /// The tracked files had uncommitted changes
+    pub dirty: bool,
+}
+
+impl Commit {
+    /// Commit checked out in the repository containing `dir`, `None` outside of a repository
+    pub fn head(dir: &Path) -> Option<Commit> {
+        let git = |args: &[&str]| {
+            let output = Command::new("git")
+                .args(args)
+                .current_dir(dir)
+                .output()
+                .ok()?;
+            output
+                .status
+                .success()
+                .then(|| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+        };
+        Some(Commit {
+            hash: git(&["rev-parse", "HEAD"])?,
+            dirty: !git(&["status", "--porcelain", "--untracked-files=no"])?.is_empty(),
+        })
+    }
+
+    pub fn short(&self) -> &str {
+        &self.hash[..self.hash.len().min(10)]
+    }
+}
+
+impl fmt::Display for Commit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.short())?;
+        if self.dirty {
+            write!(f, " (dirty)")?;
+        }
+        Ok(())
+    }
+}
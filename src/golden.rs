@@ -0,0 +1,77 @@
+use std::path::Path;
+
+use tap_runner::{Location, Test};
+
+use crate::diff::{self, Kind};
+
+/// Category of the pseudo-failures reporting the divergences from the golden file
+const CATEGORY: &str = "golden";
+
+/// Normalizes a TAP document before comparing it with the golden file: the line endings, the
+/// trailing whitespace and the blank lines at the end are not significant
+fn normalize(tap: &str) -> String {
+    let lines = tap.lines().map(str::trim_end).collect::<Vec<_>>();
+    let end = lines
+        .iter()
+        .rposition(|l| !l.is_empty())
+        .map_or(0, |i| i + 1);
+    lines[..end].join("\n")
+}
+
+/// Line of the golden file a hunk starts at, from its `@@ -a,b +c,d @@` header
+fn golden_line(header: &str) -> usize {
+    header
+        .trim_start_matches("@@ -")
+        .split(',')
+        .next()
+        .and_then(|start| start.parse().ok())
+        .unwrap_or(1)
+}
+
+/// Divergences of the produced `tap` from the `golden` document read at `path`, as failing
+/// tests numbered from `first`, one per group of changed lines
+pub fn divergences(path: &Path, golden: &str, tap: &str, first: usize) -> Vec<Test> {
+    let lines = diff::unified(&normalize(golden), &normalize(tap), 2);
+    let file = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+
+    let mut hunks: Vec<Vec<diff::Line>> = Vec::new();
+    for line in lines {
+        match (line.kind, hunks.last_mut()) {
+            (Kind::Hunk, _) | (_, None) => hunks.push(vec![line]),
+            (_, Some(hunk)) => hunk.push(line),
+        }
+    }
+
+    hunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, hunk)| {
+            // The first changed line, after the unchanged ones given as context
+            let context = hunk[1..]
+                .iter()
+                .take_while(|l| l.kind == Kind::Context)
+                .count();
+            let line = golden_line(&hunk[0].text) + context;
+            let mut yaml = format!("golden: {}\ndiff: |-", path.display());
+            for diff_line in &hunk {
+                yaml.push_str("\n  ");
+                yaml.push_str(&diff_line.text);
+            }
+            Test {
+                result: false,
+                number: first + i,
+                desc: Some(format!("Differs from the golden file at line {line}")),
+                directive: None,
+                yaml,
+                location: Some(Location {
+                    file: file.display().to_string(),
+                    line,
+                }),
+                category: Some(CATEGORY.to_owned()),
+                tags: Vec::new(),
+                warnings: Vec::new(),
+                parents: Vec::new(),
+            }
+        })
+        .collect()
+}
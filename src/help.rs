@@ -0,0 +1,288 @@
+use clap::CommandFactory;
+use crossterm::event::KeyCode;
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+};
+
+use crate::{
+    hints::Hint,
+    keymap::{self, Keymap},
+    Args,
+};
+
+/// Keys of the actions, by the view handling them, shown in the overlay opened with `?`
+const SECTIONS: &[(&str, &[Hint])] = &[
+    (
+        "Runs",
+        &[
+            (&[KeyCode::Char('r')], "relaunch the build and the tests"),
+            (
+                &[KeyCode::Char('B')],
+                "relaunch the build, even if the sources did not change",
+            ),
+            (&[KeyCode::Char('b')], "launch only the build"),
+            (
+                &[KeyCode::Char('O')],
+                "show the output of the last build again",
+            ),
+            (&[KeyCode::Char('t')], "relaunch only the tests"),
+            (
+                &[KeyCode::Enter, KeyCode::Char('x')],
+                "rerun the selected failing test",
+            ),
+            (&[KeyCode::Char('f')], "rerun all the failing tests at once"),
+            (
+                &[KeyCode::Char('['), KeyCode::Char(']')],
+                "show the previous/next past run",
+            ),
+            (&[KeyCode::Char('P')], "pick the profile"),
+            (&[KeyCode::Char('q')], "quit"),
+        ],
+    ),
+    (
+        "Failing tests",
+        &[
+            (
+                &[KeyCode::Up, KeyCode::Down],
+                "select the previous/next failing test",
+            ),
+            (&[KeyCode::Esc], "unselect the failing test"),
+            (
+                &[KeyCode::Tab, KeyCode::BackTab],
+                "focus the next/previous pane",
+            ),
+            (
+                &[KeyCode::Char('/')],
+                "search the failing tests (tab: all the tests)",
+            ),
+            (
+                &[KeyCode::Char('n'), KeyCode::Char('N')],
+                "select the next/previous match",
+            ),
+            (&[KeyCode::Char('s')], "list the new failures first"),
+            (
+                &[KeyCode::Char('e')],
+                "edit the file of the selected failing test at its line",
+            ),
+            (
+                &[KeyCode::Char('i')],
+                "explain the selected failing test with the explain command",
+            ),
+            (
+                &[KeyCode::Char('y')],
+                "copy the selected failing test to the clipboard",
+            ),
+            (&[KeyCode::Char('m')], "mark the selected failing test"),
+            (
+                &[KeyCode::Char('Y')],
+                "accept the failure of the selected test as expected, or stop expecting it",
+            ),
+            (
+                &[KeyCode::Char('A')],
+                "apply an action to the marked failing tests",
+            ),
+            (
+                &[KeyCode::Char('w')],
+                "wrap the long lines of the selected failing test",
+            ),
+            (
+                &[KeyCode::Char('a')],
+                "list the artifacts of the selected failing test",
+            ),
+            (&[KeyCode::Char('#')], "list only the tests of a tag"),
+            (&[KeyCode::Char('L')], "edit the location filter"),
+            (
+                &[KeyCode::Char('R')],
+                "reload the configuration, without rerunning",
+            ),
+        ],
+    ),
+    (
+        "Views",
+        &[
+            (
+                &[KeyCode::Char('T')],
+                "toggle the tree view of all the tests",
+            ),
+            (&[KeyCode::Char('M')], "toggle the benchmark view"),
+            (
+                &[KeyCode::Char('K')],
+                "toggle the clusters of similar failures",
+            ),
+            (
+                &[KeyCode::Char('I')],
+                "toggle the timeline of the suites of the last run",
+            ),
+            (
+                &[KeyCode::Char('S')],
+                "toggle the output of the last run on stderr",
+            ),
+            (
+                &[KeyCode::Char('D')],
+                "toggle the diff with the previous run",
+            ),
+            (
+                &[KeyCode::Char('V')],
+                "toggle the raw output, at the line of the selected failing test",
+            ),
+            (
+                &[KeyCode::Char('c')],
+                "show each failing test on a single line",
+            ),
+            (&[KeyCode::Char('z')], "zoom on the failing tests"),
+            (
+                &[KeyCode::Char('F')],
+                "freeze the results into a snapshot pane, or close it",
+            ),
+            (
+                &[KeyCode::Char('o')],
+                "move the preview to the right or below",
+            ),
+            (
+                &[KeyCode::Char('<'), KeyCode::Char('>')],
+                "grow/shrink the preview",
+            ),
+            (&[KeyCode::Char('J')], "export the results as JSON"),
+            (
+                &[KeyCode::Char('X')],
+                "export the transcript of the session",
+            ),
+            (&[KeyCode::Char('?')], "show this help"),
+        ],
+    ),
+    (
+        "Tree view",
+        &[
+            (
+                &[KeyCode::Left, KeyCode::Right],
+                "collapse/expand the selected subtest",
+            ),
+            (
+                &[KeyCode::Char('E'), KeyCode::Char('C')],
+                "expand/collapse all the subtests",
+            ),
+            (
+                &[KeyCode::Char('1')],
+                "expand the subtests up to depth 1, up to 9 with the digits",
+            ),
+            (
+                &[KeyCode::Enter, KeyCode::Char('x')],
+                "rerun the selected subtest",
+            ),
+        ],
+    ),
+    (
+        "Benchmark, diff and raw output views",
+        &[
+            (
+                &[KeyCode::Char('s')],
+                "save the measurements as the baseline",
+            ),
+            (
+                &[KeyCode::PageUp, KeyCode::PageDown],
+                "scroll the diff or the raw output by pages",
+            ),
+            (
+                &[KeyCode::Home, KeyCode::End],
+                "scroll to the start/end of the diff or of the raw output",
+            ),
+        ],
+    ),
+    (
+        "Macros",
+        &[
+            (
+                &[KeyCode::Char('Q')],
+                "record the keys in the register typed next, until pressed again",
+            ),
+            (
+                &[KeyCode::Char('@')],
+                "replay the register typed next, `@` for the last one",
+            ),
+        ],
+    ),
+];
+
+/// Lines of the help overlay: the keys of the actions as remapped by `keymap`, and the command
+/// line options
+pub fn lines(keymap: &Keymap) -> Vec<Spans<'static>> {
+    let title = |title: &str| {
+        Spans::from(Span::styled(
+            title.to_owned(),
+            Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+        ))
+    };
+    // The description goes on the next line when the keys don't fit in their column
+    let entry = |keys: String, description: String| {
+        let keys = Span::styled(format!("  {keys:<24}"), Style::default().fg(Color::Yellow));
+        match keys.content.chars().count() > 26 {
+            true if description.is_empty() => vec![Spans::from(keys)],
+            true => vec![
+                Spans::from(keys),
+                Spans::from(Span::raw(format!("{:26}{description}", ""))),
+            ],
+            false => vec![Spans::from(vec![keys, Span::raw(description)])],
+        }
+    };
+
+    let mut lines = Vec::new();
+    for (section, hints) in SECTIONS {
+        lines.push(title(section));
+        for (keys, description) in *hints {
+            let keys = keys
+                .iter()
+                .filter_map(|&key| keymap.key_for(key))
+                .map(keymap::key_name)
+                .collect::<Vec<_>>();
+            // Disabled by the key bindings
+            if keys.is_empty() {
+                continue;
+            }
+            lines.extend(entry(keys.join("/"), description.to_string()));
+        }
+        lines.push(Spans::default());
+    }
+
+    lines.push(title("Command line options"));
+    for arg in Args::command().get_arguments() {
+        let Some(long) = arg.get_long() else {
+            continue;
+        };
+        let mut option = format!("--{long}");
+        if arg.get_action().takes_values() {
+            let value = match arg.get_value_names() {
+                Some(names) => names.join("> <"),
+                None => arg.get_id().as_str().to_uppercase(),
+            };
+            option.push_str(&format!(" <{value}>"));
+        }
+        let help = arg.get_help().map(ToString::to_string).unwrap_or_default();
+        lines.extend(entry(option, help.lines().next().unwrap_or("").to_owned()));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[test]
+    fn vim_keys_do_not_shadow_actions() {
+        let keymap = Keymap::new(&BTreeMap::new(), true).unwrap();
+        for (section, hints) in SECTIONS {
+            for (keys, description) in *hints {
+                for &key in *keys {
+                    assert_eq!(
+                        keymap.translate(key),
+                        key,
+                        "`{}` ({section}: {description}) is taken by vim_keys",
+                        keymap::key_name(key)
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use crossterm::event::KeyCode;
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+};
+
+use crate::keymap::{self, Keymap};
+
+/// Keys of an action and its description, shown in the hint bar
+pub type Hint = (&'static [KeyCode], &'static str);
+
+pub const FAILURES: &[Hint] = &[
+    (&[KeyCode::Up, KeyCode::Down], "select"),
+    (&[KeyCode::Enter], "rerun test"),
+    (&[KeyCode::Char('f')], "rerun failures"),
+    (&[KeyCode::Char('e')], "edit"),
+    (&[KeyCode::Char('r')], "rerun all"),
+    (&[KeyCode::Char('/')], "search"),
+    (&[KeyCode::Char('m')], "mark"),
+    (&[KeyCode::Char('T')], "tree"),
+    (&[KeyCode::Tab], "next pane"),
+    (&[KeyCode::Char('?')], "help"),
+    (&[KeyCode::Char('q')], "quit"),
+];
+
+pub const MATCHES: &[Hint] = &[
+    (
+        &[KeyCode::Char('n'), KeyCode::Char('N')],
+        "next/previous match",
+    ),
+    (&[KeyCode::Char('/')], "search again"),
+    (&[KeyCode::Enter], "rerun test"),
+    (&[KeyCode::Tab], "next pane"),
+    (&[KeyCode::Char('q')], "quit"),
+];
+
+pub const PAST_RUN: &[Hint] = &[
+    (&[KeyCode::Up, KeyCode::Down], "select"),
+    (&[KeyCode::Char('['), KeyCode::Char(']')], "older/newer run"),
+    (&[KeyCode::Char('D')], "diff"),
+    (&[KeyCode::Char('q')], "quit"),
+];
+
+pub const PANE: &[Hint] = &[
+    (&[KeyCode::Up, KeyCode::Down], "scroll"),
+    (&[KeyCode::Tab, KeyCode::BackTab], "next/previous pane"),
+    (&[KeyCode::Char('z')], "zoom"),
+    (&[KeyCode::Char('r')], "rerun all"),
+    (&[KeyCode::Char('q')], "quit"),
+];
+
+/// Keys of the panes listing tests, the skipped and TODO ones
+pub const LIST: &[Hint] = &[
+    (&[KeyCode::Up, KeyCode::Down], "select"),
+    (&[KeyCode::Tab, KeyCode::BackTab], "next/previous pane"),
+    (&[KeyCode::Char('r')], "rerun all"),
+    (&[KeyCode::Char('q')], "quit"),
+];
+
+pub const BUILD: &[Hint] = &[
+    (&[KeyCode::Up, KeyCode::Down], "scroll"),
+    (&[KeyCode::Esc], "hide"),
+    (&[KeyCode::Tab], "next pane"),
+    (&[KeyCode::Char('q')], "quit"),
+];
+
+pub const TREE: &[Hint] = &[
+    (&[KeyCode::Up, KeyCode::Down], "select"),
+    (&[KeyCode::Left, KeyCode::Right], "collapse/expand"),
+    (
+        &[KeyCode::Char('E'), KeyCode::Char('C')],
+        "expand/collapse all",
+    ),
+    (&[KeyCode::Enter], "rerun subtest"),
+    (&[KeyCode::Char('T')], "close"),
+    (&[KeyCode::Char('q')], "quit"),
+];
+
+pub const BENCH: &[Hint] = &[
+    (&[KeyCode::Up, KeyCode::Down], "select"),
+    (&[KeyCode::Char('s')], "save baseline"),
+    (&[KeyCode::Char('M')], "close"),
+    (&[KeyCode::Char('q')], "quit"),
+];
+
+pub const DIFF: &[Hint] = &[
+    (&[KeyCode::Up, KeyCode::Down], "scroll"),
+    (&[KeyCode::PageUp, KeyCode::PageDown], "page"),
+    (&[KeyCode::Char('D')], "close"),
+    (&[KeyCode::Char('q')], "quit"),
+];
+
+pub const RAW: &[Hint] = &[
+    (&[KeyCode::Up, KeyCode::Down], "scroll"),
+    (&[KeyCode::PageUp, KeyCode::PageDown], "page"),
+    (&[KeyCode::Char('V')], "close"),
+    (&[KeyCode::Char('q')], "quit"),
+];
+
+pub const CLUSTERS: &[Hint] = &[
+    (&[KeyCode::Up, KeyCode::Down], "select"),
+    (&[KeyCode::Enter], "go to example"),
+    (&[KeyCode::Char('m')], "mark cluster"),
+    (&[KeyCode::Char('K')], "close"),
+    (&[KeyCode::Char('q')], "quit"),
+];
+
+pub const STDERR: &[Hint] = &[
+    (&[KeyCode::Up, KeyCode::Down], "scroll"),
+    (&[KeyCode::PageUp, KeyCode::PageDown], "page"),
+    (&[KeyCode::Char('S')], "close"),
+    (&[KeyCode::Char('q')], "quit"),
+];
+
+pub const TIMELINE: &[Hint] = &[
+    (&[KeyCode::Up, KeyCode::Down], "scroll"),
+    (&[KeyCode::PageUp, KeyCode::PageDown], "page"),
+    (&[KeyCode::Char('I')], "close"),
+    (&[KeyCode::Char('q')], "quit"),
+];
+
+pub const SEARCH: &[Hint] = &[
+    (&[KeyCode::Enter], "select first match"),
+    (&[KeyCode::Tab], "failures/all tests"),
+    (&[KeyCode::Esc], "stop"),
+];
+
+pub const FILTER_EDIT: &[Hint] = &[(&[KeyCode::Enter], "keep"), (&[KeyCode::Esc], "restore")];
+
+pub const EXPLANATION: &[Hint] = &[
+    (&[KeyCode::Up, KeyCode::Down], "scroll"),
+    (&[KeyCode::PageUp, KeyCode::PageDown], "page"),
+    (&[KeyCode::Esc], "close"),
+];
+
+pub const HELP: &[Hint] = &[
+    (&[KeyCode::Up, KeyCode::Down], "scroll"),
+    (&[KeyCode::PageUp, KeyCode::PageDown], "page"),
+    (&[KeyCode::Esc], "close"),
+];
+
+pub const PICKER: &[Hint] = &[
+    (&[KeyCode::Up, KeyCode::Down], "select"),
+    (&[KeyCode::Enter], "pick"),
+    (&[KeyCode::Esc], "close"),
+];
+
+pub const TASK_PICKER: &[Hint] = &[
+    (&[KeyCode::Up, KeyCode::Down], "select"),
+    (&[KeyCode::Enter], "run"),
+    (&[KeyCode::Char('b')], "build command"),
+    (&[KeyCode::Char('q')], "quit"),
+];
+
+pub const ARTIFACT_PICKER: &[Hint] = &[
+    (&[KeyCode::Up, KeyCode::Down], "select"),
+    (&[KeyCode::Enter], "preview"),
+    (&[KeyCode::Char('o')], "open"),
+    (&[KeyCode::Esc], "close"),
+];
+
+pub const ARTIFACT_VIEW: &[Hint] = &[
+    (&[KeyCode::Up, KeyCode::Down], "scroll"),
+    (&[KeyCode::PageUp, KeyCode::PageDown], "page"),
+    (&[KeyCode::Char('o')], "open"),
+    (&[KeyCode::Esc], "close"),
+];
+
+/// Line of the hint bar, showing the keys the actions are bound to in `keymap`, the actions whose
+/// keys were all disabled being left out
+pub fn line(hints: &[Hint], keymap: &Keymap) -> Spans<'static> {
+    let mut spans = Vec::new();
+    for (keys, action) in hints {
+        let keys = keys
+            .iter()
+            .filter_map(|&key| keymap.key_for(key))
+            .map(keymap::key_name)
+            .collect::<Vec<_>>();
+        if keys.is_empty() {
+            continue;
+        }
+        if !spans.is_empty() {
+            spans.push(Span::raw("  "));
+        }
+        spans.push(Span::styled(
+            keys.join("/"),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ));
+        spans.push(Span::styled(
+            format!(" {action}"),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    Spans::from(spans)
+}
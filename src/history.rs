@@ -0,0 +1,104 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{git::Commit, storage::Storage};
+
+/// Number of runs kept for each command, the oldest ones being removed
+const MAX_RUNS: usize = 200;
+
+/// Outputs of the past runs of a command, archived in the state directory as
+/// `<milliseconds since the epoch>.tap`, along with a `.json` file describing the run
+pub struct History {
+    dir: PathBuf,
+}
+
+/// Archived run
+#[derive(Clone, Debug)]
+pub struct Run {
+    pub path: PathBuf,
+    pub time: SystemTime,
+    /// Commit the tests were run on, if they were run in a git repository
+    pub commit: Option<Commit>,
+    /// Seed given to the run command
+    pub seed: Option<u64>,
+}
+
+/// Description of a run, stored next to its output
+#[derive(Serialize, Deserialize, Default)]
+struct Info {
+    commit: Option<Commit>,
+    seed: Option<u64>,
+}
+
+impl Run {
+    pub fn read(&self) -> anyhow::Result<String> {
+        Ok(std::fs::read_to_string(&self.path)?)
+    }
+}
+
+impl History {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Archives the output of a run, done on `commit` with `seed`
+    pub fn record(
+        &self,
+        tap: &str,
+        commit: Option<&Commit>,
+        seed: Option<u64>,
+    ) -> anyhow::Result<()> {
+        let time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+        let path = self.dir.join(format!("{}.tap", time.as_millis()));
+        let info = Info {
+            commit: commit.cloned(),
+            seed,
+        };
+        Storage::write(&path.with_extension("json"), serde_json::to_vec(&info)?)?;
+        Storage::write(&path, tap)?;
+
+        let runs = self.runs();
+        for run in &runs[..runs.len().saturating_sub(MAX_RUNS)] {
+            let _ = std::fs::remove_file(&run.path);
+            let _ = std::fs::remove_file(run.path.with_extension("json"));
+        }
+        Ok(())
+    }
+
+    /// Archived runs, oldest first
+    pub fn runs(&self) -> Vec<Run> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut runs = entries
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                let millis = run_millis(&path)?;
+                let info: Info = std::fs::read(path.with_extension("json"))
+                    .ok()
+                    .and_then(|info| serde_json::from_slice(&info).ok())
+                    .unwrap_or_default();
+                Some(Run {
+                    time: SystemTime::UNIX_EPOCH + Duration::from_millis(millis),
+                    commit: info.commit,
+                    seed: info.seed,
+                    path,
+                })
+            })
+            .collect::<Vec<_>>();
+        runs.sort_by_key(|r| r.time);
+        runs
+    }
+}
+
+fn run_millis(path: &Path) -> Option<u64> {
+    if path.extension()? != "tap" {
+        return None;
+    }
+    path.file_stem()?.to_str()?.parse().ok()
+}
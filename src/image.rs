@@ -0,0 +1,134 @@
+use std::{
+    env,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use base64::Engine;
+use tui::layout::Rect;
+
+const EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "gif", "webp", "bmp"];
+/// Size of the chunks the kitty protocol requires the images to be sent in
+const KITTY_CHUNK: usize = 4096;
+
+/// Terminal graphics protocol used to draw images
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Protocol {
+    Kitty,
+    /// Inline images of iTerm2, also supported by WezTerm
+    Iterm2,
+}
+
+impl Protocol {
+    /// Protocol supported by the terminal, guessed from its environment variables
+    pub fn detect() -> Option<Self> {
+        let term = env::var("TERM").unwrap_or_default();
+        let program = env::var("TERM_PROGRAM").unwrap_or_default();
+        if term == "xterm-kitty" || env::var_os("KITTY_WINDOW_ID").is_some() {
+            Some(Protocol::Kitty)
+        } else if matches!(program.as_str(), "iTerm.app" | "WezTerm") {
+            Some(Protocol::Iterm2)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the protocol can draw the image at `path`
+    pub fn supports(&self, path: &Path) -> bool {
+        match self {
+            // Other formats would need to be decoded first
+            Protocol::Kitty => extension(path).as_deref() == Some("png"),
+            Protocol::Iterm2 => true,
+        }
+    }
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension().map(|e| e.to_string_lossy().to_lowercase())
+}
+
+pub fn is_image(path: &Path) -> bool {
+    extension(path).is_some_and(|e| EXTENSIONS.contains(&e.as_str()))
+}
+
+/// Image drawn over an area of the screen, after the frame is drawn
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Placement {
+    pub path: PathBuf,
+    pub area: Rect,
+}
+
+/// Width and height of a PNG image, read from its header
+fn png_size(data: &[u8]) -> Option<(u32, u32)> {
+    if data.len() < 24 || !data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return None;
+    }
+    let width = u32::from_be_bytes(data[16..20].try_into().ok()?);
+    let height = u32::from_be_bytes(data[20..24].try_into().ok()?);
+    Some((width, height))
+}
+
+/// Largest size in cells fitting in `area` with the aspect ratio of the image, assuming the cells
+/// are twice as high as they are wide
+fn fit(area: Rect, (width, height): (u32, u32)) -> (u16, u16) {
+    if width == 0 || height == 0 {
+        return (area.width, area.height);
+    }
+    let rows = (area.width as u64 * height as u64 / width as u64 / 2) as u16;
+    match rows <= area.height {
+        true => (area.width, rows.max(1)),
+        false => {
+            let columns = (area.height as u64 * 2 * width as u64 / height as u64) as u16;
+            (columns.clamp(1, area.width), area.height)
+        }
+    }
+}
+
+/// Draws the image of the `placement`
+pub fn show(out: &mut impl Write, protocol: Protocol, placement: &Placement) -> anyhow::Result<()> {
+    let data = std::fs::read(&placement.path)?;
+    let encoded = base64::engine::general_purpose::STANDARD.encode(&data);
+    let area = placement.area;
+    crossterm::queue!(out, crossterm::cursor::MoveTo(area.x, area.y))?;
+
+    match protocol {
+        Protocol::Kitty => {
+            let (columns, rows) = match png_size(&data) {
+                Some(size) => fit(area, size),
+                None => (area.width, area.height),
+            };
+            let chunks = encoded.as_bytes().chunks(KITTY_CHUNK).collect::<Vec<_>>();
+            for (i, chunk) in chunks.iter().enumerate() {
+                let more = (i + 1 < chunks.len()) as u8;
+                // q=2 silences the responses, which would be read as key presses, and C=1
+                // leaves the cursor in place
+                let control = match i {
+                    0 => format!("a=T,f=100,q=2,C=1,c={columns},r={rows},m={more}"),
+                    _ => format!("m={more}"),
+                };
+                write!(out, "\x1b_G{control};")?;
+                out.write_all(chunk)?;
+                write!(out, "\x1b\\")?;
+            }
+        }
+        Protocol::Iterm2 => write!(
+            out,
+            "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{encoded}\x07",
+            data.len(),
+            area.width,
+            area.height
+        )?,
+    }
+
+    out.flush()?;
+    Ok(())
+}
+
+/// Removes the images drawn with the kitty protocol, the others being overwritten by the text
+pub fn clear(out: &mut impl Write, protocol: Protocol) -> std::io::Result<()> {
+    if protocol == Protocol::Kitty {
+        write!(out, "\x1b_Ga=d,q=2\x1b\\")?;
+        out.flush()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::mpsc,
+};
+
+use anyhow::Context as _;
+use tap_runner::{Test, TestResult};
+
+use crate::source::{emit, indent, passed, Context, TapSource};
+
+/// Variable giving the interpreter to the run command
+const INTERPRETER_VAR: &str = "TAP_RUNNER_INTERPRETER";
+
+/// Runs the test command of a source once per interpreter, the document of each run becoming a
+/// subtest named after its interpreter
+pub struct Interpreters {
+    source: Box<dyn TapSource>,
+    interpreters: Vec<String>,
+}
+
+/// Runs the tests of `source` under the `interpreters`, if any
+pub fn wrap(source: Box<dyn TapSource>, interpreters: Vec<String>) -> Box<dyn TapSource> {
+    match interpreters.is_empty() {
+        true => source,
+        false => Box::new(Interpreters {
+            source,
+            interpreters,
+        }),
+    }
+}
+
+/// Run command under `interpreter`, replacing `{interp}` in its arguments, or prepending the
+/// interpreter if there is no placeholder
+fn command(command: &[String], interpreter: &str) -> Vec<String> {
+    match command.iter().any(|arg| arg.contains("{interp}")) {
+        true => command
+            .iter()
+            .map(|arg| arg.replace("{interp}", interpreter))
+            .collect(),
+        false => std::iter::once(interpreter.to_owned())
+            .chain(command.iter().cloned())
+            .collect(),
+    }
+}
+
+impl TapSource for Interpreters {
+    fn needs_command(&self) -> bool {
+        true
+    }
+
+    fn describe(&self, context: &Context) -> String {
+        self.interpreters
+            .iter()
+            .map(|interpreter| {
+                let command = command(context.command, interpreter);
+                self.source.describe(&Context {
+                    command: &command,
+                    ..*context
+                })
+            })
+            .collect::<Vec<_>>()
+            .join(", then ")
+    }
+
+    fn read(&mut self, context: &Context) -> anyhow::Result<String> {
+        let mut tap = String::new();
+        emit(&mut tap, context, "TAP version 14".to_owned());
+        emit(&mut tap, context, format!("1..{}", self.interpreters.len()));
+
+        for (number, interpreter) in self.interpreters.iter().enumerate() {
+            emit(&mut tap, context, format!("# Subtest: {interpreter}"));
+            let command = command(context.command, interpreter);
+            let mut env = context.env.clone();
+            env.insert(INTERPRETER_VAR.to_owned(), interpreter.clone());
+
+            // The lines are forwarded as they are read, indented into the subtest
+            let document = std::thread::scope(|scope| {
+                let (tx, rx) = mpsc::channel::<String>();
+                if let Some(lines) = context.lines {
+                    scope.spawn(move || {
+                        for line in rx {
+                            let _ = lines.send(indent(&line));
+                        }
+                    });
+                }
+                let document = self.source.read(&Context {
+                    command: &command,
+                    env: &env,
+                    lines: context.lines.map(|_| &tx),
+                    ..*context
+                });
+                drop(tx);
+                document
+            })
+            .with_context(|| format!("Could not run the tests under {interpreter}"))?;
+
+            for line in document.lines().filter(|l| !l.starts_with("TAP version")) {
+                tap.push_str(&indent(line));
+                tap.push('\n');
+            }
+            let ending = match passed(&document) {
+                true => "ok",
+                false => "not ok",
+            };
+            emit(
+                &mut tap,
+                context,
+                format!("{ending} {} - {interpreter}", number + 1),
+            );
+        }
+        Ok(tap)
+    }
+
+    fn continuous(&self) -> bool {
+        self.source.continuous()
+    }
+}
+
+/// Interpreters under which each failing test fails, for the tests which do not fail under all
+/// the interpreters they ran under. The tests are matched across the subtests of the
+/// interpreters by their description path, or by their number if they have no description.
+pub fn partial_failures(tests: &[Test]) -> HashMap<usize, Vec<String>> {
+    // Results of each test by interpreter, the subtests being seen before their tests when
+    // walking the tests backwards
+    let mut results = BTreeMap::<String, Vec<(usize, &str, TestResult)>>::new();
+    let mut parents: Vec<(&str, usize)> = Vec::new();
+    for (index, test) in tests.iter().enumerate().rev() {
+        parents.truncate(test.parents.len());
+        let desc = test.desc.as_deref().unwrap_or("");
+        if let Some(((interpreter, _), path)) = parents.split_first() {
+            let key = match &test.desc {
+                Some(_) => {
+                    let descs = path.iter().map(|(desc, _)| *desc);
+                    format!("/{}", descs.chain([desc]).collect::<Vec<_>>().join("/"))
+                }
+                None => {
+                    let numbers = path.iter().map(|(_, number)| number.to_string());
+                    let numbers = numbers.chain([test.number.to_string()]);
+                    format!("#{}", numbers.collect::<Vec<_>>().join("."))
+                }
+            };
+            results
+                .entry(key)
+                .or_default()
+                .push((index, interpreter, test.status()));
+        }
+        parents.push((desc, test.number));
+    }
+
+    let mut partial = HashMap::new();
+    for runs in results.values() {
+        let failing = runs
+            .iter()
+            .filter(|(_, _, result)| *result == TestResult::Fail)
+            .collect::<Vec<_>>();
+        if failing.is_empty() || failing.len() == runs.len() {
+            continue;
+        }
+        // Listed in the order the interpreters ran
+        let interpreters = failing
+            .iter()
+            .rev()
+            .map(|(_, interpreter, _)| interpreter.to_string())
+            .collect::<Vec<_>>();
+        for (index, _, _) in failing {
+            partial.insert(*index, interpreters.clone());
+        }
+    }
+    partial
+}
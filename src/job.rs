@@ -0,0 +1,226 @@
+use std::{
+    io::Read,
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    time::Instant,
+};
+
+enum Event {
+    Output(Vec<u8>),
+    /// Output of the command on stderr, when it is kept apart
+    Errors(Vec<u8>),
+    /// The command at this index of the sequence started
+    Stage(usize),
+    Finished(std::io::Result<bool>),
+}
+
+/// A command running in the background, whose output is collected as it is produced
+pub struct Job {
+    events: Receiver<Event>,
+    pub started_at: Instant,
+    pub output: Vec<u8>,
+    /// Output on stderr, if it is not mixed with the output
+    pub errors: Vec<u8>,
+    /// Whether the command succeeded, once it has finished
+    pub success: Option<bool>,
+    /// Index of the command of the sequence running, or of the one which failed
+    pub stage: usize,
+}
+
+/// Sends the output of `reader` as it is produced, returns whether the command succeeded, or
+/// `None` if the job was dropped
+fn forward(reader: &mut duct::ReaderHandle, tx: &Sender<Event>) -> Option<std::io::Result<bool>> {
+    let mut buffer = [0; 4096];
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => {
+                if tx.send(Event::Output(buffer[..n].to_vec())).is_err() {
+                    let _ = reader.kill();
+                    return None;
+                }
+            }
+            Err(e) => return Some(Err(e)),
+        }
+    }
+
+    Some(reader.try_wait().map(|output| {
+        if let Some(errors) = output.map(|o| &o.stderr).filter(|e| !e.is_empty()) {
+            let _ = tx.send(Event::Errors(errors.clone()));
+        }
+        output.is_some_and(|o| o.status.success())
+    }))
+}
+
+impl Job {
+    pub fn spawn(expression: duct::Expression) -> std::io::Result<Self> {
+        Self::sequence(vec![(String::new(), expression)])
+    }
+
+    /// Runs a command whose output is parsed, its output on stderr being kept apart in `errors`
+    pub fn separate(expression: duct::Expression) -> std::io::Result<Self> {
+        Self::start(vec![(String::new(), expression.stderr_capture())])
+    }
+
+    /// Runs the commands one after the other, stopping at the first one which fails. The header
+    /// of each command, if not empty, is written to the output before it.
+    pub fn sequence(commands: Vec<(String, duct::Expression)>) -> std::io::Result<Self> {
+        Self::start(
+            commands
+                .into_iter()
+                .map(|(header, expression)| (header, expression.stderr_to_stdout()))
+                .collect(),
+        )
+    }
+
+    fn start(commands: Vec<(String, duct::Expression)>) -> std::io::Result<Self> {
+        let mut commands = commands
+            .into_iter()
+            .map(|(header, expression)| (header, expression.unchecked()));
+        let Some((header, first)) = commands.next() else {
+            return Ok(Self::finished(Vec::new(), true));
+        };
+        let mut reader = first.reader()?;
+        let (tx, events) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let mut header = header;
+            for stage in 1.. {
+                if !header.is_empty() {
+                    let _ = tx.send(Event::Output(header.into_bytes()));
+                }
+                let Some(status) = forward(&mut reader, &tx) else {
+                    return;
+                };
+                let next = match status {
+                    Ok(true) => commands.next(),
+                    _ => None,
+                };
+                let Some((next_header, next)) = next else {
+                    let _ = tx.send(Event::Finished(status));
+                    return;
+                };
+                let _ = tx.send(Event::Stage(stage));
+                reader = match next.reader() {
+                    Ok(reader) => reader,
+                    Err(e) => {
+                        let _ = tx.send(Event::Finished(Err(e)));
+                        return;
+                    }
+                };
+                header = next_header;
+            }
+        });
+
+        Ok(Self {
+            events,
+            started_at: Instant::now(),
+            output: Vec::new(),
+            errors: Vec::new(),
+            success: None,
+            stage: 0,
+        })
+    }
+
+    /// A command which has already finished, to show its `output`
+    pub fn finished(output: Vec<u8>, success: bool) -> Self {
+        let (_, events) = mpsc::channel();
+        Self {
+            events,
+            started_at: Instant::now(),
+            output,
+            errors: Vec::new(),
+            success: Some(success),
+            stage: 0,
+        }
+    }
+
+    pub fn running(&self) -> bool {
+        self.success.is_none()
+    }
+
+    /// Collects the output produced since the last call, returns the result of the command when
+    /// it finishes
+    pub fn poll(&mut self) -> Option<std::io::Result<bool>> {
+        if !self.running() {
+            return None;
+        }
+
+        loop {
+            let result = match self.events.try_recv() {
+                Ok(event) => self.handle(event),
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => Some(self.disconnected()),
+            };
+            if result.is_some() {
+                return result;
+            }
+        }
+    }
+
+    /// Waits for the command to finish, collecting its output, returns its result unless it had
+    /// already finished
+    pub fn wait(&mut self) -> Option<std::io::Result<bool>> {
+        while self.running() {
+            let result = match self.events.recv() {
+                Ok(event) => self.handle(event),
+                Err(_) => Some(self.disconnected()),
+            };
+            if result.is_some() {
+                return result;
+            }
+        }
+        None
+    }
+
+    fn handle(&mut self, event: Event) -> Option<std::io::Result<bool>> {
+        match event {
+            Event::Output(out) => self.output.extend(out),
+            Event::Errors(errors) => self.errors.extend(errors),
+            Event::Stage(stage) => self.stage = stage,
+            Event::Finished(status) => {
+                self.success = Some(*status.as_ref().unwrap_or(&false));
+                return Some(status);
+            }
+        }
+        None
+    }
+
+    fn disconnected(&mut self) -> std::io::Result<bool> {
+        self.success = Some(false);
+        Err(std::io::Error::other("job thread exited unexpectedly"))
+    }
+}
+
+/// A function running on a worker thread, whose result is collected once it returns
+pub struct Worker<T> {
+    result: Receiver<T>,
+    pub started_at: Instant,
+}
+
+impl<T: Send + 'static> Worker<T> {
+    pub fn spawn(f: impl FnOnce() -> T + Send + 'static) -> Self {
+        let (tx, result) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(f());
+        });
+
+        Self {
+            result,
+            started_at: Instant::now(),
+        }
+    }
+
+    /// Returns the result of the function once it has returned, `None` if it panicked
+    pub fn poll(&self) -> Option<Option<T>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(Some(result)),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(None),
+        }
+    }
+
+    /// Waits for the function to return, `None` if it panicked
+    pub fn wait(self) -> Option<T> {
+        self.result.recv().ok()
+    }
+}
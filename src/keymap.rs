@@ -0,0 +1,102 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crossterm::event::KeyCode;
+
+/// Keys of the arrows and of the ends of the lists in vim, enabled by `vim_keys`
+const VIM_KEYS: [(char, KeyCode); 6] = [
+    ('h', KeyCode::Left),
+    ('j', KeyCode::Down),
+    ('k', KeyCode::Up),
+    ('l', KeyCode::Right),
+    ('g', KeyCode::Home),
+    ('G', KeyCode::End),
+];
+
+/// Keys remapped by the `keys` setting, translated to the key of their action before it is handled
+#[derive(Default)]
+pub struct Keymap(HashMap<KeyCode, KeyCode>);
+
+impl Keymap {
+    /// Keymap of the `keys` setting, mapping the name of a key to the name of the key whose action
+    /// it triggers, with the vim keys first if enabled
+    pub fn new(keys: &BTreeMap<String, String>, vim: bool) -> anyhow::Result<Self> {
+        let mut map = HashMap::new();
+        if vim {
+            map.extend(VIM_KEYS.map(|(key, action)| (KeyCode::Char(key), action)));
+        }
+        for (key, action) in keys {
+            map.insert(parse_key(key)?, parse_key(action)?);
+        }
+        Ok(Self(map))
+    }
+
+    pub fn translate(&self, code: KeyCode) -> KeyCode {
+        self.0.get(&code).copied().unwrap_or(code)
+    }
+
+    /// Key triggering the action of `action`, `None` if it was disabled
+    pub fn key_for(&self, action: KeyCode) -> Option<KeyCode> {
+        if !self.0.contains_key(&action) {
+            return Some(action);
+        }
+        self.0
+            .iter()
+            .filter(|&(_, &a)| a == action)
+            .map(|(&key, _)| key)
+            .min_by_key(|&key| key_name(key))
+    }
+}
+
+/// Name of a key, as parsed by [`parse_key`] except for the arrows
+pub fn key_name(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "space".to_owned(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Up => "↑".to_owned(),
+        KeyCode::Down => "↓".to_owned(),
+        KeyCode::Left => "←".to_owned(),
+        KeyCode::Right => "→".to_owned(),
+        KeyCode::Home => "home".to_owned(),
+        KeyCode::End => "end".to_owned(),
+        KeyCode::PageUp => "pageup".to_owned(),
+        KeyCode::PageDown => "pagedown".to_owned(),
+        KeyCode::Enter => "enter".to_owned(),
+        KeyCode::Esc => "esc".to_owned(),
+        KeyCode::Tab => "tab".to_owned(),
+        KeyCode::BackTab => "backtab".to_owned(),
+        KeyCode::Backspace => "backspace".to_owned(),
+        KeyCode::F(n) => format!("f{n}"),
+        _ => "none".to_owned(),
+    }
+}
+
+/// Parses the name of a key: a character, `space`, `up`, `down`, `left`, `right`, `home`, `end`,
+/// `pageup`, `pagedown`, `enter`, `esc`, `tab`, `backtab`, `backspace`, `f1` to `f12`, or `none` to
+/// disable the key mapped to it
+pub fn parse_key(name: &str) -> anyhow::Result<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyCode::Char(c));
+    }
+    Ok(match name.to_lowercase().as_str() {
+        "space" => KeyCode::Char(' '),
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "backspace" => KeyCode::Backspace,
+        "none" => KeyCode::Null,
+        lower => match lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+            Some(n @ 1..=12) => KeyCode::F(n),
+            _ => anyhow::bail!("Unknown key `{name}`"),
+        },
+    })
+}
@@ -0,0 +1,16 @@
+//! TAP aggregation of tap_runner, usable without the TUI.
+//!
+//! [`parse_tap`] flattens a TAP document and its subtests into a list of [`Test`], from which
+//! the [`tree`] of subtests, a [`Summary`] of the results and text reports
+//! ([`Test::write`]) are built. [`stream`] recognizes the test points of a document as it is
+//! being produced.
+
+mod parse;
+pub mod stream;
+mod summary;
+mod test;
+pub mod tree;
+
+pub use parse::{filter_strings, parse_filter, parse_tap, Document, SkippedSuite};
+pub use summary::Summary;
+pub use test::{describe, Directive, Location, Test, TestResult};
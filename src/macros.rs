@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use crossterm::event::KeyCode;
+
+/// Keyboard macros: `Q<register>` records the following keys until `Q` is pressed again, and
+/// `[count]@<register>` replays them (`@@` replays the last register used)
+#[derive(Default)]
+pub struct Macros {
+    registers: HashMap<char, Vec<KeyCode>>,
+    /// Register being recorded, with the keys pressed so far
+    recording: Option<(char, Vec<KeyCode>)>,
+    pending: Option<Pending>,
+    /// Count typed before `@`
+    count: Option<usize>,
+    last: Option<char>,
+}
+
+/// Macro command waiting for its register
+enum Pending {
+    Record,
+    Replay(usize),
+}
+
+/// Effect of a key press on the macros
+pub enum Press {
+    /// The key is not part of a macro command and must be handled
+    Key,
+    /// The key is part of a macro command that is not complete yet, or was cancelled
+    Pending,
+    Recording(char),
+    /// The recording of the register ended, with the number of keys recorded
+    Recorded(char, usize),
+    /// The keys must be handled `count` times
+    Replay {
+        register: char,
+        keys: Vec<KeyCode>,
+        count: usize,
+    },
+    /// The register to replay holds no keys
+    Empty(char),
+}
+
+impl Macros {
+    /// Register being recorded
+    pub fn recording(&self) -> Option<char> {
+        self.recording.as_ref().map(|(register, _)| *register)
+    }
+
+    /// Whether the next key is read as the register of a macro
+    pub fn pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Interprets a key press. When `typing` in a text field the keys are only recorded, and the
+    /// digits are only read as a count if `counting`.
+    pub fn press(&mut self, code: KeyCode, typing: bool, counting: bool) -> Press {
+        if typing {
+            return self.record(code);
+        }
+
+        if let Some(pending) = self.pending.take() {
+            let KeyCode::Char(register) = code else {
+                return Press::Pending;
+            };
+            return match pending {
+                Pending::Record if register.is_ascii_lowercase() => {
+                    self.recording = Some((register, Vec::new()));
+                    Press::Recording(register)
+                }
+                Pending::Replay(count) => {
+                    let register = match register {
+                        '@' => self.last,
+                        r if r.is_ascii_lowercase() => Some(r),
+                        _ => None,
+                    };
+                    let Some(register) = register else {
+                        return Press::Pending;
+                    };
+                    self.last = Some(register);
+                    match self.registers.get(&register) {
+                        Some(keys) if !keys.is_empty() => Press::Replay {
+                            register,
+                            keys: keys.clone(),
+                            count,
+                        },
+                        _ => Press::Empty(register),
+                    }
+                }
+                Pending::Record => Press::Pending,
+            };
+        }
+
+        match code {
+            KeyCode::Char('Q') => match self.recording.take() {
+                Some((register, keys)) => {
+                    let len = keys.len();
+                    self.registers.insert(register, keys);
+                    Press::Recorded(register, len)
+                }
+                None => {
+                    self.count = None;
+                    self.pending = Some(Pending::Record);
+                    Press::Pending
+                }
+            },
+            KeyCode::Char('@') => {
+                self.pending = Some(Pending::Replay(self.count.take().unwrap_or(1)));
+                Press::Pending
+            }
+            KeyCode::Char(c @ '0'..='9') if counting && (c != '0' || self.count.is_some()) => {
+                let digit = c.to_digit(10).unwrap() as usize;
+                self.count = Some(
+                    self.count
+                        .unwrap_or(0)
+                        .saturating_mul(10)
+                        .saturating_add(digit),
+                );
+                Press::Pending
+            }
+            _ => {
+                self.count = None;
+                self.record(code)
+            }
+        }
+    }
+
+    fn record(&mut self, code: KeyCode) -> Press {
+        if let Some((_, keys)) = &mut self.recording {
+            keys.push(code);
+        }
+        Press::Key
+    }
+}
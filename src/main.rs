@@ -1,612 +1,6233 @@
 use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
     env,
+    ffi::OsString,
+    io::Write,
     path::{Path, PathBuf},
-    process::Command,
-    str::FromStr,
-    time::{Duration, Instant},
+    process::{Command, ExitCode, ExitStatus},
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime},
 };
 
 use ansi_to_tui::IntoText;
 use anyhow::Context;
+use artifact::ArtifactView;
 use clap::Parser;
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
-    terminal::{EnterAlternateScreen, LeaveAlternateScreen},
+    event::{DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseEvent},
+    style::{self, Stylize},
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, SetTitle},
 };
 use itertools::Itertools;
-use jaq_core::{Definitions, Filter};
-use tap_parser::{DirectiveKind, TapParser, TapStatement, TapTest};
+use jaq_core::Filter;
+use tap_runner::{
+    describe, filter_strings, parse_filter, parse_tap, stream, tree, Document, Location,
+    SkippedSuite, Summary, Test, TestResult,
+};
 use tui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::Color,
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
+    style::{Color, Modifier, Style},
     text::{Span, Spans, Text},
-    widgets::{Block, BorderType, Borders, ListItem, Paragraph, Wrap},
+    widgets::{
+        Block, BorderType, Borders, Cell, Clear, ListItem, Paragraph, Row, Table, TableState, Wrap,
+    },
     Frame, Terminal,
 };
 
+use config::{Config, Hooks, LayoutSettings, Pane, Settings, TemplateSettings, ThemeSettings};
+use expectations::Expectations;
+use explain::Explanation;
+use filter_edit::FilterEdit;
+use git::Commit;
+use hints::Hint;
+use history::History;
+use job::{Job, Worker};
+use keymap::Keymap;
+use macros::Macros;
+use plugin::Plugin;
+use raw::RawView;
+use script::Script;
+use search::Search;
+use session::{Scroll, Session, TreeSession};
+use snapshot::{Frozen, Snapshot};
+use source::TapSource;
+use stderr::StderrLog;
+use storage::Storage;
+use template::Templates;
+use theme::Theme;
+use timeline::Timeline;
+use toast::{Toast, Toasts};
+use transcript::Transcript;
 use widgets::{ColoredList, StatefulList};
+mod ansi;
+mod artifact;
+mod bench;
+mod bisect;
+mod clipboard;
+mod cluster;
+mod color;
+mod completions;
+mod config;
+mod diff;
+mod doctor;
+mod expectations;
+mod explain;
+mod extract;
+mod filter_edit;
+mod fingerprint;
+mod git;
+mod golden;
+mod help;
+mod hints;
+mod history;
+mod image;
+mod interp;
+mod job;
+mod keymap;
+mod macros;
+mod mirror;
+mod plugin;
+mod pty;
+mod raw;
+mod report;
+mod script;
+mod search;
+mod session;
+mod shard;
+mod snapshot;
+mod source;
+mod stderr;
+mod storage;
+mod tasks;
+mod template;
+mod theme;
+mod timeline;
+mod toast;
+mod transcript;
 mod widgets;
 
-pub struct ErrorTracker {
-    error: String,
-    created_at: Instant,
+struct Failure {
+    number: String,
+    desc: Option<String>,
+    yaml: String,
+    location: Option<Location>,
+    category: Option<String>,
+    warnings: Vec<String>,
+    /// Index of the test in the status grid
+    index: usize,
+    /// Result of the test the last time it was rerun on its own
+    rerun: Option<TestResult>,
+    /// Marked with `m` to apply a bulk action
+    marked: bool,
+    /// Long lines are wrapped instead of clipped, toggled with `w`
+    wrapped: bool,
+    /// The test did not fail in the previous run, `None` without a previous run
+    new: Option<bool>,
+    /// The failure was accepted in the expectations file, and does not fail the run
+    expected: bool,
+    /// Files and URLs listed by the diagnostics
+    artifacts: Vec<String>,
+    tags: Vec<String>,
+    /// Interpreters the test fails under, if it passes under other ones
+    only_under: Vec<String>,
+    /// Diagnostics with the colors of the command, when it was run in a pseudo-terminal
+    colored: Option<Vec<String>>,
+    /// Lines the command wrote on stderr after the test point of the test, or during its rerun
+    stderr: Vec<String>,
 }
 
-impl ErrorTracker {
-    pub fn new<E>(e: E) -> Self
-    where
-        E: ToString,
-    {
-        Self {
-            error: e.to_string(),
-            created_at: Instant::now(),
+impl Failure {
+    /// Message the failure is clustered by, its diagnostics or else its description
+    fn message(&self) -> &str {
+        match self.yaml.trim() {
+            "" => self.desc.as_deref().unwrap_or(""),
+            yaml => yaml,
+        }
+    }
+
+    /// First line of the failure in the list, with the `spinner` frame if it is being rerun.
+    /// When `compact`, the location is included as the rest of the item is not shown. A `row`
+    /// rendered from the failure template replaces the description of the test.
+    fn header(
+        &self,
+        spinner: Option<char>,
+        compact: bool,
+        row: Option<String>,
+    ) -> Vec<Span<'static>> {
+        let mut header = Vec::new();
+        if let Some(frame) = spinner {
+            header.push(Span::raw(format!("{frame} ")));
+        }
+        if self.marked {
+            header.push(Span::styled("● ", Style::default().fg(Color::Magenta)));
+        }
+        if self.expected {
+            header.push(Span::styled(
+                "EXPECTED ",
+                Style::default()
+                    .fg(Color::DarkGray)
+                    .add_modifier(Modifier::ITALIC),
+            ));
+        }
+        match self.new {
+            Some(true) => header.push(Span::styled(
+                "NEW ",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )),
+            Some(false) => {
+                header.push(Span::styled("AGAIN ", Style::default().fg(Color::DarkGray)))
+            }
+            None => (),
+        }
+        // The template decides which fields are shown
+        let templated = row.is_some();
+        if let Some(row) = row {
+            header.push(Span::raw(row));
+        } else if compact {
+            let mut parts = vec![self.number.clone()];
+            parts.extend(self.desc.clone());
+            parts.extend(
+                self.location
+                    .as_ref()
+                    .map(|l| format!("{}:{}", l.file, l.line)),
+            );
+            header.push(Span::raw(parts.join(" — ")));
+        } else {
+            header.push(Span::raw(describe(&self.number, self.desc.as_deref())));
+        }
+        if let Some(category) = self.category.as_ref().filter(|_| !templated) {
+            header.push(Span::styled(
+                format!(" [{category}]"),
+                Style::default().fg(Color::LightMagenta),
+            ));
+        }
+        if !templated {
+            header.extend(self.tags.iter().map(tag_span));
+        }
+        if !self.only_under.is_empty() {
+            header.push(only_under_span(&self.only_under));
+        }
+        match self.rerun {
+            Some(TestResult::Success) => header.push(Span::styled(
+                " (passed on rerun)",
+                Style::default().fg(Color::Green),
+            )),
+            Some(TestResult::Skip) => header.push(Span::styled(
+                " (skipped on rerun)",
+                Style::default().fg(Color::Yellow),
+            )),
+            Some(TestResult::Todo) => header.push(Span::styled(
+                " (marked TODO on rerun)",
+                Style::default().fg(Color::Magenta),
+            )),
+            Some(TestResult::Bonus) => header.push(Span::styled(
+                " (passed on rerun, as TODO)",
+                Style::default().fg(Color::Cyan),
+            )),
+            _ => (),
+        }
+        header
+    }
+
+    /// Lines following the header: warnings, plugin `notes`, location and diagnostics
+    fn details(&self, notes: &[String]) -> Vec<Spans<'static>> {
+        let mut lines = Vec::new();
+        for warning in &self.warnings {
+            lines.push(Spans::from(Span::styled(
+                format!("⚠ {warning}"),
+                Style::default().fg(Color::Yellow),
+            )));
+        }
+        for note in notes {
+            lines.push(Spans::from(Span::styled(
+                format!("» {note}"),
+                Style::default().fg(Color::Cyan),
+            )));
+        }
+        lines.push("----------".into());
+        if let Some(location) = &self.location {
+            lines.push(format!("Failure in '{}' at line {}", location.file, location.line).into());
+        };
+        for artifact in &self.artifacts {
+            lines.push(Spans::from(Span::styled(
+                format!("⧉ {artifact}"),
+                Style::default().fg(Color::LightBlue),
+            )));
         }
+        match &self.colored {
+            Some(colored) => lines.extend(colored.iter().flat_map(|line| {
+                line.into_text()
+                    .map(|text| text.lines)
+                    .unwrap_or_else(|_| vec![Spans::from(ansi::strip(line).into_owned())])
+            })),
+            None => lines.extend(
+                self.yaml
+                    .split('\n')
+                    .filter(|s| !s.is_empty())
+                    .map(|t| Spans::from(t.to_owned())),
+            ),
+        }
+        if !self.stderr.is_empty() {
+            lines.push(Spans::from(Span::styled(
+                "stderr:",
+                Style::default().fg(Color::DarkGray),
+            )));
+            lines.extend(self.stderr.iter().map(|line| {
+                Spans::from(Span::styled(
+                    line.clone(),
+                    Style::default().fg(Color::LightRed),
+                ))
+            }));
+        }
+        lines.push("----------".into());
+        lines
     }
 }
 
-#[derive(Debug)]
-struct Directive {
-    key: tap_parser::DirectiveKind,
-    reason: Option<String>,
+/// Action applied to all the marked failures
+#[derive(Clone, Copy)]
+enum BulkAction {
+    Rerun,
+    Edit,
+    Copy,
+    Export,
+    Accept,
 }
 
-#[derive(Debug)]
-struct Test {
-    result: bool,
-    number: usize,
-    desc: Option<String>,
-    directive: Option<Directive>,
-    yaml: String,
-    location: Option<Location>,
+impl BulkAction {
+    const ALL: [BulkAction; 5] = [
+        Self::Rerun,
+        Self::Edit,
+        Self::Copy,
+        Self::Export,
+        Self::Accept,
+    ];
 
-    parents: Vec<usize>,
+    fn label(&self) -> &'static str {
+        match self {
+            BulkAction::Rerun => "Rerun",
+            BulkAction::Edit => "Open in the editor",
+            BulkAction::Copy => "Copy to the clipboard",
+            BulkAction::Export => "Export to a report",
+            BulkAction::Accept => "Accept as expected",
+        }
+    }
 }
 
-#[derive(Debug)]
-struct Location {
-    file: String,
-    line: usize,
+/// Past run shown instead of the last one, browsed with `[` and `]`
+struct PastRun {
+    runs: Vec<history::Run>,
+    position: usize,
+    /// Position of the last run, after the archived runs if it was not archived
+    last: usize,
+    /// Whether the results of the last run were stale
+    stale: bool,
 }
 
-impl FromStr for Location {
-    type Err = anyhow::Error;
+/// Entry of the tag picker, `None` listing the tests of all the tags
+struct TagEntry {
+    tag: Option<String>,
+    tests: usize,
+    failures: usize,
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let Some((file, line)) = s.split_once(':') else {
-            anyhow::bail!("Missing `:` in location")
+impl TagEntry {
+    fn label(&self) -> String {
+        let tag = match &self.tag {
+            Some(tag) => format!("#{tag}"),
+            None => "All tags".to_owned(),
         };
-
-        Ok(Self {
-            file: file.into(),
-            line: line.parse()?,
-        })
+        format!("{tag} ({} failing / {} tests)", self.failures, self.tests)
     }
 }
 
-enum TestResult {
-    Skip,
-    Success,
+/// What the app does once configured
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Start {
+    /// Runs the tests
+    Run,
+    /// Restores the last session and shows the last run, running the tests once it is drawn
+    Restore,
+    /// Only reads the configuration
+    Idle,
+}
+
+/// Output of the headless mode
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq, Debug, Default)]
+enum Format {
+    /// Print the results once the tests have run
+    #[default]
+    Summary,
+    /// Print each test as it completes, then the results
+    Stream,
+}
+
+/// Condition making the headless mode exit with a non-zero code
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+enum FailOn {
+    /// A test failed
     Fail,
+    /// A test had problems in its diagnostics, or with its location
+    ParseError,
+    /// A test marked as TODO passed
+    TodoPass,
+    /// A test was skipped
+    Skip,
+    /// The number of tests run differs from the plan
+    PlanMismatch,
+    /// The test command exited with a non-zero status
+    ExitStatus,
+    /// The document or a subtest was skipped entirely by a `1..0 # SKIP` plan
+    SkipAll,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum OutputLevel {
+    /// Print nothing, only the exit code reports the result
+    Quiet,
+    /// Print the test counts
+    Summary,
+    /// Print the test counts and the list of failing tests
+    Normal,
+    /// Print the test counts and the details of each failing test
+    Verbose,
+}
+
+/// Run of the tests waiting for the `pre_run` hook and the build, which run in the build job
+struct PendingTests {
+    /// Identity of the failure selected before the run
+    selected: Option<String>,
+    /// The first command of the build job is the `pre_run` hook
+    hook: bool,
+    /// The build job runs the build steps, which were not skipped
+    built: bool,
+}
+
+/// Test command running on a worker thread, so that the UI stays responsive
+struct TestRun {
+    worker: Worker<anyhow::Result<String>>,
+    /// Lines of the document, as they are read
+    lines: Receiver<String>,
+    /// Tests of the lines read so far, shown until the whole document is parsed
+    live: stream::LiveDocument,
+    /// Identity of the failure selected before the run, selected again once it finishes
+    selected: Option<String>,
+    /// The source keeps producing documents: the results of the previous one are shown until the
+    /// first line of the next one is read
+    waiting: bool,
+    /// Seed given to the run command
+    seed: Option<u64>,
+    /// When the line of the last test point was read
+    point_at: Duration,
+    /// Lines read from a pseudo-terminal, with their colors
+    colored: Vec<String>,
+    /// Output of the command on stderr, and the test points it follows
+    stderr: Receiver<source::Stderr>,
+    /// Exit status of the commands run
+    statuses: Receiver<ExitStatus>,
+}
+
+/// Diff of the raw TAP output of the previous and current runs
+struct DiffView {
+    lines: Vec<diff::Line>,
+    scroll: usize,
+}
+
+/// Failures grouped by the similarity of their messages, toggled with `K`
+struct ClusterView {
+    /// Numbers of the failing tests of each cluster, the first one representing it
+    clusters: StatefulList<Vec<String>>,
+}
+
+/// Times the suites of the last run took, toggled with `I`
+struct TimelineView {
+    scroll: usize,
+}
+
+/// Output of the last run on stderr, toggled with `S`
+struct StderrView {
+    scroll: usize,
+}
+
+/// Measurements of the tests compared to a saved baseline
+struct BenchView {
+    baseline: bench::Measurements,
+    state: TableState,
+}
+
+struct TreeView {
+    rows: StatefulList<tree::Row>,
+    /// Subtests collapsed or expanded by the user, by hierarchical number
+    collapsed: HashMap<String, bool>,
+}
+
+/// Pane receiving the navigation keys, cycled with `Tab`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Focus {
+    Failures,
+    Preview,
+    Skipped,
+    Todo,
+    Build,
+}
+
+impl Focus {
+    const CYCLE: [Focus; 5] = [
+        Self::Failures,
+        Self::Preview,
+        Self::Skipped,
+        Self::Todo,
+        Self::Build,
+    ];
+}
+
+/// Row of the skipped pane
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SkippedRow {
+    /// Index of a suite skipped entirely in the skipped suites
+    Suite(usize),
+    /// Index of a skipped test in the tests
+    Test(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RerunTarget {
+    /// Index of the test in the failure list
+    Failure(usize),
+    /// All the failing tests, at once
+    Failures,
+    /// Index of the test ending the subtree in the flattened tests
+    Subtree(usize),
 }
 
+const PREVIEW_CACHE: &str = "previews";
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
 struct App {
+    storage: Storage,
+    /// Last saved session
+    session: Session,
+    config: Config,
+    config_modified: Vec<Option<SystemTime>>,
+    /// Settings given on the command line, taking precedence over all the other layers
+    cli: Settings,
+    profile: Option<String>,
+    profile_picker: Option<StatefulList<String>>,
+    /// Tasks of the Justfile or Makefile, offered when no run command nor profile is configured
+    task_picker: Option<StatefulList<tasks::Task>>,
+
     test_command: String,
     test_args: Vec<String>,
-    build_command: Option<String>,
-    build_args: Vec<String>,
+    /// Where the TAP documents are read from
+    /// Shared with the worker thread reading the document of a run
+    source: Arc<Mutex<Box<dyn TapSource>>>,
+    /// `--source` or `source` setting, `None` for the run command
+    source_spec: Option<String>,
+    /// Interpreters the tests are run under, each one being a subtest of the runs
+    interpreters: Vec<String>,
+    /// Number of shards run in parallel, each one being a subtest of the runs
+    shards: Option<usize>,
+    /// Seed given to every run, a new one being drawn for each run if `None`
+    fixed_seed: Option<u64>,
+    /// Seed of the run shown
+    seed: Option<u64>,
+    /// Interpreters under which the tests fail, for the tests which pass under other ones
+    partial_failures: HashMap<usize, Vec<String>>,
+    /// Pre-build commands followed by the build command, run one after the other
+    build: Vec<Vec<String>>,
+    rerun_test_command: Option<Vec<String>>,
+    rerun_subtest_command: Option<Vec<String>>,
+    /// The `rerun_command` setting, running all the failing tests
+    rerun_failures_command: Option<Vec<String>>,
+    /// Shell command explaining the selected failure, run with `i`
+    explain_command: Option<String>,
+    /// Globs of the files the build depends on
+    sources: Vec<String>,
+    env: BTreeMap<String, String>,
+    /// Command the other commands are run with, loading the environment of the project
+    wrapper: Vec<String>,
+    hooks: Hooks,
+    plugins: Vec<Plugin>,
+    script: Option<Script>,
+    /// Golden TAP file the output of the runs is compared with
+    golden: Option<PathBuf>,
+    /// Failures accepted with `Y`, which do not fail the run
+    expectations: Expectations,
+    /// Notes attached to the tests by the plugins, by hierarchical number
+    annotations: HashMap<String, Vec<String>>,
+    /// Colored diagnostics of the last run in a pseudo-terminal, by hierarchical number
+    colored: HashMap<String, Vec<String>>,
 
     preview: bool,
     root: Option<PathBuf>,
+    /// Directory the test and rerun commands are run in, the current one if unset
+    run_dir: Option<PathBuf>,
+    /// Directory the build commands are run in
+    build_dir: Option<PathBuf>,
+    /// Run the test command in a pseudo-terminal
+    pty: bool,
+    /// Prefix of the lines of the logs the documents are extracted from
+    extract: Option<extract::Prefix>,
+    layout: LayoutSettings,
+    templates: Templates,
+    /// Preview ratio chosen with `<` and `>`, overriding the configured one
+    preview_ratio: Option<u16>,
+    /// Stack the preview below the failures instead of beside them
+    vertical_preview: bool,
+    /// Show the failures on one line each, the selected one being detailed below them
+    compact: bool,
+    /// Give the whole screen to the failures, the tree or the build log
+    zoomed: bool,
+    macros: Macros,
+    focus: Focus,
+    /// Lines the preview is scrolled by from the failing line
+    preview_scroll: i32,
+    /// Session restored at launch, whose tree selection and scroll positions are restored again
+    /// once the first run finishes
+    restored_session: Option<Session>,
+    /// Skipped suites then skipped tests, browsed when the skipped pane is focused
+    skipped_list: StatefulList<SkippedRow>,
+    /// Indices of the failing and passing `TODO` tests, browsed when the TODO pane is focused
+    todo_list: StatefulList<usize>,
+    /// Lines the build log is scrolled up by from its end
+    build_scroll: usize,
+    /// List the failures which did not fail in the previous run first, toggled with `s`
+    new_first: bool,
+    /// Select the first new failure after a run instead of the first failure
+    select_new_failure: bool,
+    /// The selection of the failures and of the tree wraps around their ends
+    wrap_selection: bool,
+    keymap: Keymap,
+    theme: Theme,
+    /// Lines the help overlay opened with `?` is scrolled by
+    help: Option<u16>,
 
     location_filter: Option<Filter>,
+    /// Source of the location filter, edited with `L`
+    location_filter_text: Option<String>,
+    filter_edit: Option<FilterEdit>,
+    tag_filter: Option<Filter>,
+    /// Only the failures and skipped tests with this tag are listed, selected with `#`
+    tag: Option<String>,
+    tag_picker: Option<StatefulList<TagEntry>>,
 
-    err: Option<ErrorTracker>,
+    toasts: Toasts,
 
+    /// Tests of the last run, flattened with the subtests after their children
+    tests: Vec<Test>,
+    /// Differences between the plans of the last run and the number of tests it ran
+    plan_mismatches: Vec<String>,
+    /// Exit status of the test command of the last run, if it failed
+    failed_status: Option<ExitStatus>,
+    /// Document and subtests of the last run skipped entirely by their plan
+    skipped_suites: Vec<SkippedSuite>,
+    /// File the results of each run are written to as JSON, with `--report-json`
+    report_json: Option<PathBuf>,
+    /// Viewers of the session attached with `--attach`
+    mirror: Option<mirror::Mirror>,
+    /// The results shown are the ones of a past run, which can't be rerun
+    past_run: Option<PastRun>,
     statuses: Vec<TestResult>,
-    skipped: Vec<(String, Option<String>, Option<String>)>,
-    failure: StatefulList<(String, Option<String>, String, Option<Location>)>,
+    /// Indices of the skipped tests
+    skipped: Vec<usize>,
+    failure: StatefulList<Failure>,
+    /// Duration of the last run of the test command, `None` for the results of a previous session
+    run_duration: Option<Duration>,
+    /// Start of the first of the consecutive runs with failing tests up to the last one
+    failing_since: Option<Instant>,
+    /// Identities of the tests failing in the previous run, see [`failure_key`]
+    known_failures: Option<HashSet<String>>,
     could_run: bool,
+    /// The results are the ones of a previous session, the tests are being run again
+    stale: bool,
+    /// Run of the test command in progress
+    test_run: Option<TestRun>,
+    /// The tests need to be run once the current results are drawn
+    pending_run: bool,
+    /// Build started with `b` or before a run, along with the fingerprint of the sources it was
+    /// started on
+    build_job: Option<(Job, Option<String>)>,
+    /// Run of the tests started once the build job succeeds
+    pending_tests: Option<PendingTests>,
+    /// Output of the last build and whether it succeeded, shown again with `O`
+    last_build: Option<(Vec<u8>, bool)>,
+    /// Rerun of a failure or of a subtree started with `Enter` or `x`
+    rerun: Option<(RerunTarget, Job)>,
+    /// Reruns to start once the current one finishes
+    rerun_queue: VecDeque<RerunTarget>,
+    /// Actions on the marked failures, opened with `A`
+    bulk_menu: Option<StatefulList<BulkAction>>,
+    /// Artifacts of the selected failure, opened with `a`
+    artifact_picker: Option<StatefulList<String>>,
+    artifact_view: Option<ArtifactView>,
+    /// Output of the explain command, shown in a popup
+    explanation: Option<Explanation>,
+    /// Results frozen with `F`, shown next to the current ones
+    snapshot: Option<Snapshot>,
+    /// Graphics protocol of the terminal, used to draw images
+    graphics: Option<image::Protocol>,
+    /// Draw with colors, unless disabled by `NO_COLOR`
+    color: bool,
+    /// Image to draw over the current frame, and the one currently drawn
+    image: Option<image::Placement>,
+    shown_image: Option<image::Placement>,
+    /// Shown instead of the failure list when toggled with `T`
+    tree: Option<TreeView>,
+    search: Option<Search>,
+    bench: Option<BenchView>,
+    clusters: Option<ClusterView>,
+    timeline: Option<TimelineView>,
+    /// Times the tests of the last run were read at
+    timings: Timeline,
+    stderr_view: Option<StderrView>,
+    /// Output of the test command on stderr during the last run
+    stderr: StderrLog,
+    /// Shown instead of the failure list when toggled with `D`
+    diff: Option<DiffView>,
+    /// Raw TAP document of the results shown, instead of the failures
+    raw: Option<RawView>,
+    /// Raw TAP output of the current and previous runs
+    tap: Option<String>,
+    previous_tap: Option<String>,
+    /// Commits the current and previous runs were done on
+    commit: Option<Commit>,
+    previous_commit: Option<Commit>,
+    /// Last title given to the terminal, `None` outside of the TUI
+    title: Option<String>,
+    /// Print the tests to stdout as they complete
+    stream: bool,
+    transcript: Transcript,
 }
 
-enum Either3<T, U, V> {
-    One(T),
-    Two(U),
-    Three(V),
+/// Editor command of the user, `$VISUAL` or `$EDITOR`, `vi` if neither is set
+fn editor() -> String {
+    env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".into())
 }
 
-impl<T, U, V, X> Iterator for Either3<T, U, V>
-where
-    T: Iterator<Item = X>,
-    U: Iterator<Item = X>,
-    V: Iterator<Item = X>,
-{
-    type Item = X;
+/// Runs `editor` with `args` in the terminal, suspending the TUI until it exits
+fn run_editor<B: Backend>(
+    terminal: &mut Terminal<B>,
+    editor: &str,
+    args: Vec<OsString>,
+) -> anyhow::Result<()> {
+    crossterm::terminal::disable_raw_mode()?;
+    crossterm::execute!(std::io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$@\""))
+        .arg("sh")
+        .args(args)
+        .status();
+    crossterm::terminal::enable_raw_mode()?;
+    crossterm::execute!(std::io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    terminal.clear()?;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        match self {
-            Either3::One(v) => v.next(),
-            Either3::Two(v) => v.next(),
-            Either3::Three(v) => v.next(),
-        }
+    if !status?.success() {
+        anyhow::bail!("The editor exited with an error");
     }
+    Ok(())
 }
 
 impl App {
     fn new(
-        test: Vec<String>,
-        build: Option<Vec<String>>,
-        location_filter: Option<String>,
-        preview: bool,
-        root: Option<PathBuf>,
+        config: Config,
+        cli: Settings,
+        profile: Option<String>,
+        start: Start,
+        stream: bool,
     ) -> anyhow::Result<Self> {
-        if preview {
-            match which::which("bat") {
-                Ok(_) => (),
-                Err(which::Error::CannotFindBinaryPath) => {
-                    anyhow::bail!("Can't find executable `bat`, could not enable --preview");
-                }
-                Err(e) => {
-                    anyhow::bail!("Error in checking for conditions of preview: {e}")
-                }
-            }
-        };
-
-        let mut test = test.into_iter();
-        let test_command = test.next().unwrap();
-        let (build_command, build_args) = match build {
-            None => (None, Vec::new()),
-            Some(b) => {
-                let mut build = b.into_iter();
-                (build.next(), build.collect())
-            }
-        };
+        let root = Config::project_root(config.explicit.as_deref())?;
+        let storage = Storage::for_project(&root)?;
+        storage.prune_cache(PREVIEW_CACHE, Duration::from_secs(7 * 24 * 60 * 60));
 
         let mut this = Self {
-            test_command,
-            test_args: test.collect(),
-            build_command,
-            build_args,
-            err: None,
-            could_run: true,
-            preview,
-            root,
+            storage,
+            session: Session::default(),
+            config_modified: config.modified(),
+            config,
+            cli,
+            profile: None,
+            profile_picker: None,
+            task_picker: None,
+            test_command: String::new(),
+            test_args: Vec::new(),
+            source: Arc::new(Mutex::new(source::from_spec("command")?)),
+            source_spec: None,
+            interpreters: Vec::new(),
+            shards: None,
+            fixed_seed: None,
+            seed: None,
+            partial_failures: HashMap::new(),
+            build: Vec::new(),
+            rerun_test_command: None,
+            rerun_subtest_command: None,
+            rerun_failures_command: None,
+            explain_command: None,
+            sources: Vec::new(),
+            env: BTreeMap::new(),
+            wrapper: Vec::new(),
+            hooks: Hooks::default(),
+            plugins: Vec::new(),
+            script: None,
+            golden: None,
+            expectations: Expectations::default(),
+            annotations: HashMap::new(),
+            colored: HashMap::new(),
+            toasts: Toasts::default(),
+            could_run: false,
+            preview: false,
+            root: None,
+            run_dir: None,
+            build_dir: None,
+            pty: false,
+            extract: None,
+            layout: LayoutSettings::default(),
+            templates: Templates::default(),
+            preview_ratio: None,
+            vertical_preview: false,
+            compact: false,
+            zoomed: false,
+            macros: Macros::default(),
+            focus: Focus::Failures,
+            preview_scroll: 0,
+            restored_session: None,
+            skipped_list: StatefulList::empty(),
+            todo_list: StatefulList::empty(),
+            build_scroll: 0,
+            new_first: false,
+            select_new_failure: false,
+            wrap_selection: true,
+            keymap: Keymap::default(),
+            theme: Theme::default(),
+            help: None,
+            tests: Vec::new(),
+            plan_mismatches: Vec::new(),
+            failed_status: None,
+            skipped_suites: Vec::new(),
+            report_json: None,
+            mirror: None,
+            past_run: None,
             statuses: Vec::new(),
             skipped: Vec::new(),
             failure: StatefulList::empty(),
-            location_filter: location_filter
-                .map(|f| -> anyhow::Result<_> {
-                    let defs = Definitions::core();
-
-                    let (f, errs) = jaq_core::parse::parse(&f, jaq_core::parse::main());
-                    let f = match f {
-                        None => {
-                            anyhow::bail!("Errors parsing the filter: {}", errs.iter().join("\n"))
-                        }
-                        Some(f) => f,
-                    };
-                    let mut errs = Vec::new();
-                    let f = defs.finish(f, Vec::new(), &mut errs);
-                    if !errs.is_empty() {
-                        anyhow::bail!("Errors finishing the filter: {}", errs.iter().join("\n"))
-                    }
+            known_failures: None,
+            run_duration: None,
+            failing_since: None,
+            location_filter: None,
+            location_filter_text: None,
+            filter_edit: None,
+            tag_filter: None,
+            tag: None,
+            tag_picker: None,
+            stale: false,
+            test_run: None,
+            pending_run: false,
+            build_job: None,
+            pending_tests: None,
+            last_build: None,
+            rerun: None,
+            rerun_queue: VecDeque::new(),
+            bulk_menu: None,
+            artifact_picker: None,
+            artifact_view: None,
+            explanation: None,
+            snapshot: None,
+            graphics: image::Protocol::detect(),
+            color: color::stdout(),
+            image: None,
+            shown_image: None,
+            tree: None,
+            search: None,
+            bench: None,
+            clusters: None,
+            timeline: None,
+            timings: Timeline::default(),
+            stderr_view: None,
+            stderr: StderrLog::default(),
+            diff: None,
+            raw: None,
+            tap: None,
+            previous_tap: None,
+            commit: None,
+            previous_commit: None,
+            title: None,
+            stream,
+            transcript: Transcript::default(),
+        };
+        this.transcript
+            .record(format!("Session started in {}", root.display()));
 
-                    Ok(f)
-                })
-                .transpose()?,
+        let session = match start {
+            Start::Restore => Session::load(&this.storage),
+            Start::Run | Start::Idle => Session::default(),
         };
+        this.preview_ratio = session.preview_ratio;
+        let restored_profile = session
+            .profile
+            .clone()
+            .filter(|p| this.config.profile.contains_key(p));
+
+        let resolved = this.config.resolve(None, &this.cli)?;
+        let has_run_command = resolved.run_command.is_some() || resolved.source.is_some();
+        match profile {
+            Some(name) => this.select_profile(Some(name))?,
+            None if has_run_command => this.select_profile(None)?,
+            None if restored_profile.is_some() => this.select_profile(restored_profile)?,
+            None => match this.config.profile.len() {
+                0 => {
+                    let tasks = tasks::discover(&root);
+                    if tasks.is_empty() {
+                        anyhow::bail!("No run command given and no profile in the config file");
+                    }
+                    let mut picker = StatefulList::with_items(tasks);
+                    picker.select(Some(0));
+                    this.task_picker = Some(picker);
+                    return Ok(this);
+                }
+                1 => this.select_profile(this.config.profile.keys().next().cloned())?,
+                _ => {
+                    this.open_profile_picker();
+                    return Ok(this);
+                }
+            },
+        }
 
-        if let Err(e) = this.run_tests() {
-            this.err = Some(ErrorTracker::new(e));
+        if start == Start::Idle {
+            return Ok(this);
+        }
+        if start == Start::Restore && this.load_last_run() {
+            this.pending_run = true;
+        } else if let Err(e) = this.run_tests() {
+            this.toasts.push(Toast::error(e));
         };
+        this.restore_session(session);
+        this.session = this.session();
 
         Ok(this)
     }
 
-    fn run_tests(&mut self) -> anyhow::Result<()> {
-        self.could_run = false;
-        self.statuses.clear();
-        self.skipped.clear();
-        self.failure = StatefulList::empty();
+    /// Identity of the selected failure, to select it again once the tests are run again
+    fn selected_key(&self) -> Option<String> {
+        self.failure
+            .selected()
+            .map(|f| failure_key(&self.tests, f.index))
+    }
 
-        if let Some(build) = &self.build_command {
-            let result = duct::cmd(build, &self.build_args)
-                .stderr_to_stdout()
-                .stdout_capture()
-                .unchecked()
-                .run()?;
-            if !result.status.success() {
-                anyhow::bail!(
-                    "Build command failed: {}",
-                    String::from_utf8_lossy(&result.stdout)
-                )
-            }
+    /// Selects the failure with the identity `key`, if it still fails
+    fn select_by_key(&mut self, key: &str) {
+        let position = self
+            .failure
+            .items()
+            .iter()
+            .position(|f| failure_key(&self.tests, f.index) == key);
+        if position.is_some() {
+            self.failure.select(position);
         }
-        self.could_run = true;
-
-        let mut command = Command::new(&self.test_command);
-        command.args(&self.test_args);
-        let output = command.output()?;
+    }
 
-        let tap = String::from_utf8(output.stdout)?;
-        let mut parser = TapParser::new();
-        let document = parser.parse(&tap)?;
+    /// Selects the failure with the hierarchical `number`, if it still fails
+    fn select_failure(&mut self, number: &str) {
+        if let Some(index) = self.failure.items().iter().position(|f| f.number == number) {
+            self.failure.select(Some(index));
+        }
+    }
 
-        fn handle_body<'a, 'f: 'a>(
-            body: Vec<TapStatement<'a>>,
-            parents: Vec<usize>,
-            filter: &'f Option<Filter>,
-        ) -> impl Iterator<Item = (Test, Option<ErrorTracker>)> + 'a {
-            body.into_iter()
-                .enumerate()
-                .flat_map(move |(i, st)| handle_statement(st, i, parents.clone(), filter))
-        }
-
-        fn handle_statement<'a, 'f: 'a>(
-            statement: TapStatement<'a>,
-            number: usize,
-            parents: Vec<usize>,
-            filter: &'f Option<Filter>,
-        ) -> impl Iterator<Item = (Test, Option<ErrorTracker>)> + 'a {
-            fn handle_test_point(
-                test: TapTest,
-                parents: Vec<usize>,
-                number: usize,
-                filter: &Option<Filter>,
-            ) -> (Test, Option<ErrorTracker>) {
-                let mut err = None;
-                let yaml = test.yaml.join("\n");
-                let location = match filter {
-                    Some(f) if !yaml.is_empty() => {
-                        match serde_yaml::from_str::<serde_yaml::Value>(&yaml) {
-                            Ok(v) => {
-                                let json = serde_json::to_value(&v)
-                                    .expect("Could not parse back YAML into JSON");
-                                let inputs = jaq_core::RcIter::new(core::iter::empty());
-                                let mut out = f.run(
-                                    jaq_core::Ctx::new([], &inputs),
-                                    jaq_core::Val::from(json),
-                                );
-                                match out.next().map(|v| v.map(|r| r.to_str().map(|s| s.parse()))) {
-                                    None => None,
-                                    Some(Err(e)) | Some(Ok(Err(e))) => {
-                                        err = Some(ErrorTracker::new(e));
-                                        None
-                                    }
-                                    Some(Ok(Ok(Err(e)))) => {
-                                        err = Some(ErrorTracker::new(e));
-                                        None
-                                    }
-                                    Some(Ok(Ok(Ok(v)))) => Some(v),
-                                }
-                            }
-                            Err(e) => {
-                                err = Some(ErrorTracker::new(e));
-                                None
-                            }
-                        }
-                    }
-                    _ => None,
-                };
-                (
-                    Test {
-                        result: test.result,
-                        number: test.number.unwrap_or(number),
-                        desc: test.desc.map(ToString::to_string),
-                        directive: test.directive.as_ref().map(|d| Directive {
-                            key: match &d.kind {
-                                DirectiveKind::Skip => DirectiveKind::Skip,
-                                DirectiveKind::Todo => DirectiveKind::Todo,
-                            },
-                            reason: d.reason.map(ToString::to_string),
-                        }),
-                        yaml,
-                        location,
-                        parents: parents.to_vec(),
-                    },
-                    err,
-                )
-            }
+    fn session(&self) -> Session {
+        Session {
+            profile: self.profile.clone(),
+            selected: self.failure.selected().map(|f| f.number.clone()),
+            preview_ratio: self.preview_ratio,
+            tag: self.tag.clone(),
+            search: self.search.as_ref().map(|s| (s.query.clone(), s.all)),
+            tree: self.tree.as_ref().map(|tree| TreeSession {
+                collapsed: tree.collapsed.clone().into_iter().collect(),
+                selected: self.tree_selection(),
+            }),
+            scroll: Scroll {
+                failures: self.failure.offset(),
+                tree: self.tree.as_ref().map_or(0, |tree| tree.rows.offset()),
+                preview: self.preview_scroll,
+            },
+        }
+    }
 
-            match statement {
-                TapStatement::Subtest(s) => {
-                    let mut child_lineage = parents.to_vec();
-                    child_lineage.push(number);
-                    let b: Box<dyn Iterator<Item = _>> =
-                        Box::new(handle_body(s.statements, child_lineage, filter));
-                    Either3::One(b.chain(std::iter::once(handle_test_point(
-                        s.ending, parents, number, filter,
-                    ))))
-                }
-                TapStatement::TestPoint(t) => Either3::Two(std::iter::once(handle_test_point(
-                    t, parents, number, filter,
-                ))),
-                _ => Either3::Three(std::iter::empty()),
-            }
+    /// Puts the filters, the views and the selection back as they were in the last session
+    fn restore_session(&mut self, session: Session) {
+        if session.tag.is_some() {
+            self.select_tag(session.tag.clone());
+        }
+        self.search = session.search.clone().map(|(query, all)| Search {
+            query,
+            editing: false,
+            all,
+        });
+        if let Some(tree) = &session.tree {
+            self.tree = Some(TreeView {
+                rows: StatefulList::empty(),
+                collapsed: tree.collapsed.clone().into_iter().collect(),
+            });
+        }
+        if let Some(selected) = &session.selected {
+            self.select_failure(selected);
         }
+        self.restore_view(&session);
+        // The lists are emptied by the run started at launch
+        self.restored_session = Some(session);
+    }
 
-        self.statuses.clear();
-        self.skipped.clear();
-        let mut failure = Vec::new();
-        for (test, err) in handle_body(document, Vec::new(), &self.location_filter) {
-            let number = test
-                .parents
-                .iter()
-                .chain(std::iter::once(&test.number))
-                .join(".");
-            if !test.result {
-                failure.push((number, test.desc, test.yaml, test.location));
-                self.statuses.push(TestResult::Fail);
-            } else {
-                match test.directive {
-                    Some(d) if d.key == tap_parser::DirectiveKind::Skip => {
-                        self.skipped.push((number, test.desc, d.reason));
-                        self.statuses.push(TestResult::Skip);
-                    }
-                    _ => self.statuses.push(TestResult::Success),
-                };
-            }
-            self.err = self.err.take().or(err);
+    /// Restores the selection in the tree view and the scroll positions of a `session`
+    fn restore_view(&mut self, session: &Session) {
+        if let Some(tree) = &session.tree {
+            self.refresh_tree(tree.selected.clone());
+        }
+        self.failure.set_offset(session.scroll.failures);
+        if let Some(tree) = &mut self.tree {
+            tree.rows.set_offset(session.scroll.tree);
         }
-        self.failure = StatefulList::with_items(failure);
+        self.preview_scroll = session.scroll.preview;
+    }
+
+    fn preview_ratio(&self) -> u16 {
+        self.preview_ratio
+            .unwrap_or_else(|| self.layout.preview_ratio())
+    }
+
+    /// Gives `delta` percents of the width more to the preview
+    fn resize_preview(&mut self, delta: i16) {
+        let ratio = self.preview_ratio() as i16 + delta;
+        self.preview_ratio = Some(ratio.clamp(10, 90) as u16);
+    }
 
+    /// Saves the session if it changed since it was last saved
+    fn save_session(&mut self) -> anyhow::Result<()> {
+        let session = self.session();
+        if session != self.session {
+            session.save(&self.storage)?;
+            self.session = session;
+        }
         Ok(())
     }
 
-    fn run<B: Backend>(
-        mut self,
-        terminal: &mut Terminal<B>,
-        tick_rate: Duration,
-    ) -> anyhow::Result<()> {
-        let mut last_tick = Instant::now();
-        loop {
-            terminal.draw(|f| self.draw(f))?;
+    /// Resolves the commands to run from the configuration layers and the profile `name`
+    fn select_profile(&mut self, name: Option<String>) -> anyhow::Result<()> {
+        let settings = self.config.resolve(name.as_deref(), &self.cli)?;
 
-            let timeout = tick_rate
-                .checked_sub(last_tick.elapsed())
-                .unwrap_or(Duration::from_secs(0));
-            if crossterm::event::poll(timeout)? {
-                if let Event::Key(key) = crossterm::event::read()? {
-                    match key.code {
-                        KeyCode::Char('q') => return Ok(()),
-                        KeyCode::Char('r') => {
-                            if let Err(e) = self.run_tests() {
-                                self.err = Some(ErrorTracker::new(e));
-                            }
-                        }
-                        KeyCode::Up => self.failure.previous(),
-                        KeyCode::Down => self.failure.next(),
-                        KeyCode::Esc => self.failure.unselect(),
-                        _ => (),
-                    }
+        let tap_source = source::from_spec(settings.source.as_deref().unwrap_or("command"))?;
+        let run_command = settings.run_command.filter(|c| !c.is_empty());
+        if tap_source.needs_command() && run_command.is_none() {
+            anyhow::bail!("No run command given on the command line or in the configuration");
+        }
+        let interpreters = settings.interpreters.unwrap_or_default();
+        if !interpreters.is_empty() && !tap_source.needs_command() {
+            anyhow::bail!("The interpreters only apply to the sources running the run command");
+        }
+        let shard = settings
+            .shard
+            .as_deref()
+            .map(str::parse::<shard::Shard>)
+            .transpose()?;
+        let shards = settings.shards;
+        if (shard.is_some() || shards.is_some()) && !tap_source.needs_command() {
+            anyhow::bail!("The shards only apply to the sources running the run command");
+        }
+        if shard.is_some() && shards.is_some() {
+            anyhow::bail!("--shard runs a single shard, it can't be used with --shards");
+        }
+        if shards == Some(0) {
+            anyhow::bail!("--shards needs at least one shard");
+        }
+        let location_filter = settings
+            .location_filter
+            .as_deref()
+            .map(parse_filter)
+            .transpose()?;
+        let tag_filter = settings
+            .tag_filter
+            .as_deref()
+            .map(parse_filter)
+            .transpose()
+            .context("Invalid tag filter")?;
+        let preview = settings.preview.unwrap_or(false);
+        if location_filter.is_none() && (preview || settings.root.is_some()) {
+            anyhow::bail!("--preview and --root require a location filter");
+        }
+        if preview {
+            doctor::check_preview()?;
+        }
+        settings.layout.validate()?;
+        let templates = Templates::parse(&settings.templates)?;
+        let theme = Theme::parse(&settings.theme)?;
+        let wrapper = match (settings.nix, settings.direnv) {
+            (Some(flake), _) => vec!["nix".into(), "develop".into(), flake, "-c".into()],
+            (None, Some(false)) => Vec::new(),
+            (None, direnv) => {
+                let root = Config::project_root(self.config.explicit.as_deref())?;
+                let enabled = match direnv {
+                    Some(enabled) => enabled,
+                    None => root.join(".envrc").is_file() && which::which("direnv").is_ok(),
+                };
+                match enabled {
+                    true => vec!["direnv".into(), "exec".into(), root.display().to_string()],
+                    false => Vec::new(),
                 }
             }
+        };
+        let script = settings.script.as_deref().map(Script::load).transpose()?;
+        let expectations = Expectations::load(
+            Config::project_root(self.config.explicit.as_deref())?.join(
+                settings
+                    .expectations
+                    .unwrap_or_else(|| PathBuf::from(expectations::FILE)),
+            ),
+        )?;
+        let directory = |dir: Option<PathBuf>| -> anyhow::Result<Option<PathBuf>> {
+            let Some(dir) = dir else {
+                return Ok(None);
+            };
+            let dir = Config::project_root(self.config.explicit.as_deref())?.join(dir);
+            anyhow::ensure!(
+                dir.is_dir(),
+                "The working directory {} does not exist",
+                dir.display()
+            );
+            Ok(Some(dir))
+        };
+        let run_dir = directory(settings.cwd)?;
+        let build_dir = directory(settings.build_cwd)?.or_else(|| run_dir.clone());
+        // Giving a prefix enables the extraction, unless it is disabled
+        let extract = match (settings.extract, settings.log_prefix) {
+            (Some(false), _) | (None, None) => None,
+            (_, Some(prefix)) => Some(extract::Prefix::Regex(
+                regex::Regex::new(&prefix)
+                    .with_context(|| format!("Invalid log prefix `{prefix}`"))?,
+            )),
+            (Some(true), None) => Some(extract::Prefix::Detected),
+        };
+        let keymap = Keymap::new(&settings.keys, settings.vim_keys.unwrap_or(false))
+            .context("Invalid key bindings")?;
 
-            if last_tick.elapsed() >= tick_rate {
-                last_tick = Instant::now();
+        let mut run_command = run_command.unwrap_or_default();
+        let mut env = settings.env;
+        if let Some(shard) = shard {
+            run_command = shard.command(&run_command);
+            shard.env(&mut env);
+        }
+        let mut test = run_command.into_iter();
+        self.test_command = test.next().unwrap_or_default();
+        self.test_args = test.collect();
+        // Keeps the document already read from stdin when the settings are reloaded
+        if settings.source != self.source_spec
+            || interpreters != self.interpreters
+            || shards != self.shards
+        {
+            let spec = settings.source.as_deref().unwrap_or("command");
+            let tap_source = shard::wrap(spec, tap_source, shards)?;
+            self.source = Arc::new(Mutex::new(interp::wrap(tap_source, interpreters.clone())));
+            self.source_spec = settings.source;
+            self.interpreters = interpreters;
+            self.shards = shards;
+        }
+        self.build = settings
+            .pre_build
+            .unwrap_or_default()
+            .into_iter()
+            .chain(settings.build_command)
+            .filter(|command| !command.is_empty())
+            .collect();
+        self.fixed_seed = settings.seed;
+        self.rerun_test_command = settings.rerun_test_command;
+        self.rerun_subtest_command = settings.rerun_subtest_command;
+        self.rerun_failures_command = settings.rerun_command;
+        self.explain_command = settings.explain;
+        self.sources = settings.sources.unwrap_or_default();
+        self.location_filter = location_filter;
+        self.location_filter_text = settings.location_filter;
+        self.tag_filter = tag_filter;
+        self.preview = preview;
+        self.root = settings.root;
+        self.run_dir = run_dir;
+        self.build_dir = build_dir;
+        self.pty = settings.pty.unwrap_or(false);
+        self.extract = extract;
+        self.layout = settings.layout;
+        self.templates = templates;
+        self.select_new_failure = settings.select_new_failure.unwrap_or(false);
+        self.wrap_selection = settings.wrap_selection.unwrap_or(true);
+        self.keymap = keymap;
+        self.theme = theme;
+        self.env = env;
+        self.wrapper = wrapper;
+        self.hooks = settings.hooks;
+        self.script = script;
+        self.golden = settings.golden;
+        self.expectations = expectations;
+        self.start_plugins(settings.plugins.unwrap_or_default());
+        if let Some(name) = &name {
+            self.transcript.record(format!("Using the profile {name}"));
+        }
+        self.profile = name;
 
-                if let Some(true) = self
-                    .err
-                    .as_ref()
-                    .map(|err| err.created_at.elapsed() > Duration::from_secs(8))
-                {
-                    self.err = None;
-                }
+        // Reported now rather than when the command is first run
+        for (setting, program) in self.programs() {
+            if let Err(e) = doctor::find_program(&program) {
+                self.toasts.push(Toast::warning(format!(
+                    "{e}, needed by the {setting} (see `tapr doctor`)"
+                )));
             }
         }
-    }
 
-    fn draw<B: Backend>(&mut self, f: &mut Frame<B>) {
-        let size = f.size();
-        let outer = Block::default()
-            .borders(Borders::ALL)
-            .title("TAP Runner")
-            .title_alignment(Alignment::Center)
-            .border_type(BorderType::Rounded);
-        let inner = outer.inner(size);
-        f.render_widget(outer, size);
+        Ok(())
+    }
 
-        let skipped_constraint = if self.skipped.is_empty() {
-            Constraint::Max(0)
-        } else if self.skipped.len() <= 10 {
-            Constraint::Max((2 + self.skipped.len()) as u16)
-        } else {
-            Constraint::Max(12)
+    /// Programs run by the configured commands, with the setting of each one. Only the wrapper is
+    /// given when there is one, the other programs being found in the environment it loads. The
+    /// paths are relative to the directory the commands run in.
+    fn programs(&self) -> Vec<(&'static str, String)> {
+        if let Some(wrapper) = self.wrapper.first() {
+            return vec![("environment", wrapper.clone())];
+        }
+        let within = |dir: &Option<PathBuf>, program: &String| match dir {
+            Some(dir) if program.contains('/') => dir.join(program).display().to_string(),
+            _ => program.clone(),
+        };
+        let first = |command: &Option<Vec<String>>| {
+            command
+                .as_ref()
+                .and_then(|c| c.first())
+                .map(|program| within(&self.run_dir, program))
         };
+        let mut programs = vec![(
+            "run command",
+            Some(within(&self.run_dir, &self.test_command)),
+        )];
+        programs.extend(self.build.iter().map(|command| {
+            let program = command.first().map(|p| within(&self.build_dir, p));
+            ("build commands", program)
+        }));
+        programs.extend([
+            ("rerun test command", first(&self.rerun_test_command)),
+            ("rerun subtest command", first(&self.rerun_subtest_command)),
+            ("rerun command", first(&self.rerun_failures_command)),
+        ]);
+        if !self.test_args.iter().any(|arg| arg.contains("{interp}")) {
+            for interpreter in &self.interpreters {
+                let program = interpreter.split_whitespace().next().map(str::to_owned);
+                programs.push(("interpreters", program));
+            }
+        }
+        let hooks = &self.hooks;
+        let shell = [
+            &hooks.pre_run,
+            &hooks.post_run,
+            &hooks.on_fail,
+            &hooks.on_green,
+        ]
+        .iter()
+        .any(|hook| hook.is_some());
+        if shell || self.explain_command.is_some() || !self.plugins.is_empty() {
+            programs.push(("hooks, plugins and explain command", Some("sh".to_owned())));
+        }
+        // The placeholders are only known when running the command
+        programs
+            .into_iter()
+            .filter_map(|(setting, program)| Some((setting, program?)))
+            .filter(|(_, program)| !program.is_empty() && !program.contains('{'))
+            .collect()
+    }
 
-        let error_constraint = if self.err.is_none() {
-            Constraint::Max(0)
-        } else if self.could_run {
-            Constraint::Max(4)
-        } else {
-            Constraint::Min(0)
+    /// Reloads the config files if they were modified since they were last read
+    fn reload_config(&mut self) -> anyhow::Result<()> {
+        let modified = self.config.modified();
+        if modified == self.config_modified {
+            return Ok(());
+        }
+        self.config_modified = modified;
+
+        let config = Config::discover(self.config.explicit.as_deref())?;
+        let changes = config.changes_from(&self.config, self.profile.as_deref());
+        let active = self.profile.as_deref();
+        let (new, old) = (
+            config.layered(active).ok(),
+            self.config.layered(active).ok(),
+        );
+        let needs_run = match (&new, &old) {
+            (Some(new), Some(old)) => new.needs_run(old),
+            _ => true,
         };
 
-        let body_constraint = if self.could_run {
-            Constraint::Min(0)
+        self.config = config;
+        self.config_modified = self.config.modified();
+        if new != old {
+            self.select_profile(self.profile.clone())?;
+            match needs_run {
+                true => self.run_tests()?,
+                // The run in progress is processed with the new settings once it finishes
+                false if self.test_run.is_some() => (),
+                false => self.reapply()?,
+            }
+        }
+
+        if changes.is_empty() {
+            self.toasts
+                .push(Toast::info("Config reloaded, nothing changed"));
         } else {
-            Constraint::Max(0)
-        };
+            self.toasts.push(Toast::info(format!(
+                "Config reloaded, {}",
+                changes.join(", ")
+            )));
+        }
 
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                error_constraint,
-                Constraint::Max(5),
-                skipped_constraint,
-                body_constraint,
-            ])
-            .split(inner);
+        Ok(())
+    }
+
+    fn open_profile_picker(&mut self) {
+        let profiles = self.config.profile.keys().cloned().collect_vec();
+        let active = self
+            .profile
+            .as_ref()
+            .and_then(|p| profiles.iter().position(|name| name == p));
+
+        let mut picker = StatefulList::with_items(profiles);
+        picker.select(Some(active.unwrap_or(0)));
+        self.profile_picker = Some(picker);
+    }
 
-        if let Some(e) = &self.err {
-            let p = Paragraph::new(e.error.clone())
-                .block(Block::default().title("Error").borders(Borders::ALL))
-                .wrap(Wrap { trim: true });
-            f.render_widget(p, chunks[0]);
+    fn run_tests(&mut self) -> anyhow::Result<()> {
+        if self.test_run.is_some() {
+            anyhow::bail!("The tests are already running");
         }
+        let selected = self.selected_key();
+        self.stale = false;
+        self.could_run = false;
+        self.statuses.clear();
+        self.skipped.clear();
+        self.skipped_suites.clear();
+        self.skipped_list = StatefulList::empty();
+        self.todo_list = StatefulList::empty();
+        self.failure = StatefulList::empty();
 
-        let status = ColoredList::new(
-            self.statuses
-                .iter()
-                .map(|s| match s {
-                    TestResult::Skip => Color::Yellow,
-                    TestResult::Success => Color::Blue,
-                    TestResult::Fail => Color::Rgb(255, 0, 0),
-                })
-                .collect(),
-        )
-        .block(Block::default().title("Status").borders(Borders::ALL));
-        f.render_widget(status, chunks[1]);
+        // Picks up the expectations removed from the file since the last run
+        self.expectations = Expectations::load(self.expectations.path().to_owned())?;
+        self.pending_tests = None;
 
-        if !self.skipped.is_empty() {
-            let p = Paragraph::new(
-                self.skipped
-                    .iter()
-                    .map(|(parents, desc, reason)| {
-                        Spans::from(
-                            parents.clone()
-                                + &match desc {
-                                    None => "".into(),
-                                    Some(d) => format!(" - {d}"),
-                                }
-                                + &match reason {
-                                    None => "".into(),
-                                    Some(r) => format!(" ({r})"),
-                                },
-                        )
-                    })
-                    .collect::<Vec<_>>(),
-            )
-            .block(Block::default().title("Skipped").borders(Borders::ALL));
-            f.render_widget(p, chunks[2])
+        // The hook and the build run in the build job, the tests are started once it succeeds
+        let mut commands = Vec::new();
+        if let Some(pre_run) = &self.hooks.pre_run {
+            commands.push((String::new(), self.hook_expression("pre_run", pre_run)));
         }
+        let hook = !commands.is_empty();
+        let mut fingerprint = None;
+        if !self.build.is_empty() {
+            fingerprint = self.sources_fingerprint()?;
+            let up_to_date = fingerprint.is_some()
+                && std::fs::read_to_string(self.build_fingerprint_path()).ok() == fingerprint;
 
-        let mut failure_location = chunks[3];
-        if self.preview {
-            if let Some((_, _, _, Some(location))) = self.failure.selected() {
-                let preview_chunks = Layout::default()
-                    .direction(Direction::Horizontal)
-                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-                    .split(chunks[3]);
-
-                match generate_failure_preview(self.root.as_deref(), location, preview_chunks[1]) {
-                    Ok(p) => {
-                        f.render_widget(
-                            Paragraph::new(p).block(Block::default().borders(Borders::all())),
-                            preview_chunks[1],
-                        );
-                        failure_location = preview_chunks[0];
-                    }
-                    Err(e) => {
-                        self.err = Some(ErrorTracker::new(e));
-                    }
-                };
+            if up_to_date {
+                fingerprint = None;
+                self.transcript
+                    .record("Build skipped, the sources did not change");
+            } else {
+                for index in 0..self.build.len() {
+                    let (line, build) = self.build_command(index);
+                    self.record_command("Building", &line);
+                    commands.push((self.build_header(index), build));
+                }
             }
         }
+        let built = commands.len() > usize::from(hook);
 
-        self.failure
-            .render(f, failure_location, |(num, desc, yaml, location)| {
-                let mut lines = Vec::new();
-                lines.push(
-                    Span::raw(
-                        num.clone()
-                            + &match desc {
-                                None => "".into(),
-                                Some(d) => format!(" - {d}"),
-                            },
-                    )
-                    .into(),
-                );
-                lines.push("----------".into());
-                if let Some(location) = location {
-                    lines.push(
-                        format!("Failure in '{}' at line {}", location.file, location.line).into(),
-                    );
-                };
-                lines.extend(
-                    yaml.split('\n')
-                        .filter(|s| !s.is_empty())
-                        .map(|t| Spans::from(t.to_owned())),
-                );
-                lines.push("----------".into());
-                ListItem::new(lines)
-            });
+        if commands.is_empty() {
+            self.could_run = true;
+            return self.start_test_command(selected);
+        }
+        let job = Job::sequence(commands).context("The build could not be started")?;
+        self.build_scroll = 0;
+        self.build_job = Some((job, fingerprint));
+        self.pending_tests = Some(PendingTests {
+            selected,
+            hook,
+            built,
+        });
+        Ok(())
     }
-}
 
-fn generate_failure_preview(
-    root: Option<&Path>,
-    location: &Location,
-    area: Rect,
-) -> anyhow::Result<Text<'static>> {
-    let file = root
-        .map(|r| r.join(&location.file))
-        .unwrap_or_else(|| Path::new(&location.file).to_path_buf());
-    if !file.exists() {
-        anyhow::bail!("File {} does not exist", location.file)
+    /// Runs the test command without building
+    fn run_test_command(&mut self) -> anyhow::Result<()> {
+        if self.test_run.is_some() {
+            anyhow::bail!("The tests are already running");
+        }
+        let selected = self.selected_key();
+        self.start_test_command(selected)
     }
 
-    let shell = env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
-    let mut preview = Command::new(shell)
-        .arg("-c")
-        .arg(format!(
-            "bat --force-colorization --terminal-width {} {} --highlight-line {}",
-            area.width - 2,
-            file.to_str().context("file path is not UTF-8")?,
-            location.line
-        ))
-        .output()?
-        .stdout
-        .into_text()?;
+    /// Starts the test command on a worker thread, the results being loaded by `poll_test_run`
+    fn start_test_command(&mut self, selected: Option<String>) -> anyhow::Result<()> {
+        self.stale = false;
+        self.could_run = true;
+        self.annotations.clear();
+        self.colored.clear();
+        self.stderr = StderrLog::default();
+        if !self.tests.is_empty() {
+            self.known_failures = Some(
+                (0..self.tests.len())
+                    .filter(|&i| self.tests[i].status() == TestResult::Fail)
+                    .map(|i| failure_key(&self.tests, i))
+                    .collect(),
+            );
+        }
+        self.past_run = None;
+        let waiting = self.source.lock().unwrap().continuous();
+        if !waiting {
+            self.clear_tests();
+        }
+        let started = plugin::Event::RunStarted {
+            profile: self.profile.as_deref(),
+        };
+        if let Some(e) = plugin::broadcast(&mut self.plugins, &[started]).pop() {
+            self.toasts.push(Toast::error(e));
+        }
+
+        let seed = match self.source.lock().unwrap().needs_command() {
+            true => Some(self.fixed_seed.unwrap_or_else(new_seed)),
+            false => None,
+        };
+        let command = std::iter::once(&self.test_command)
+            .chain(&self.test_args)
+            .map(|arg| seeded(arg, seed))
+            .collect_vec();
+        let (wrapper, mut env, stream) = (self.wrapper.clone(), self.env.clone(), self.stream);
+        let (dir, pty, extract) = (self.run_dir.clone(), self.pty, self.extract.clone());
+        if let Some(seed) = seed {
+            env.insert("TAP_SEED".to_owned(), seed.to_string());
+        }
+        let context = source::Context {
+            command: &command,
+            wrapper: &wrapper,
+            env: &env,
+            dir: dir.as_deref(),
+            pty,
+            extract: extract.as_ref(),
+            stream,
+            lines: None,
+            stderr: None,
+            status: None,
+        };
+        let description = self.source.lock().unwrap().describe(&context);
+        self.transcript.record(format!("Running: {description}"));
+
+        let source = self.source.clone();
+        let (tx, lines) = mpsc::channel();
+        let (stderr_tx, stderr) = mpsc::channel();
+        let (status_tx, statuses) = mpsc::channel();
+        let worker = Worker::spawn(move || {
+            let context = source::Context {
+                command: &command,
+                wrapper: &wrapper,
+                env: &env,
+                dir: dir.as_deref(),
+                pty,
+                extract: extract.as_ref(),
+                stream,
+                lines: Some(&tx),
+                stderr: Some(&stderr_tx),
+                status: Some(&status_tx),
+            };
+            source.lock().unwrap().read(&context)
+        });
+        self.test_run = Some(TestRun {
+            worker,
+            lines,
+            live: stream::LiveDocument::default(),
+            selected,
+            waiting,
+            seed,
+            point_at: Duration::ZERO,
+            colored: Vec::new(),
+            stderr,
+            statuses,
+        });
+        Ok(())
+    }
+
+    /// Clears the results, for the tests to be shown again as they complete
+    fn clear_tests(&mut self) {
+        let tree_selection = self.tree_selection();
+        self.tests.clear();
+        self.timings = Timeline::default();
+        self.rerun = None;
+        // The queued reruns are positions in the failures being replaced
+        self.rerun_queue.clear();
+        self.rebuild_results();
+        self.refresh_tree(tree_selection);
+    }
+
+    /// Shows the tests completed since the last call, and loads the results of the test command
+    /// once it finishes
+    fn poll_test_run(&mut self) -> anyhow::Result<()> {
+        let Some(run) = &mut self.test_run else {
+            return Ok(());
+        };
+
+        // Polled first, so that no line is missed once the worker has returned
+        let result = run.worker.poll();
+        let lines = run.lines.try_iter().collect_vec();
+        if run.waiting && !lines.is_empty() {
+            // The run starts with its first line
+            run.waiting = false;
+            run.worker.started_at = Instant::now();
+            self.clear_tests();
+        }
+        let run = self.test_run.as_mut().expect("the run is in progress");
+        let at = run.worker.started_at.elapsed();
+        let mut completed = Vec::new();
+        for line in &lines {
+            let line = match self.pty {
+                true => {
+                    run.colored.push(line.clone());
+                    ansi::strip(line)
+                }
+                false => line.into(),
+            };
+            // A test is only complete once the next line is read, but finished with its own line
+            if let Some(test) = run.live.push(&line) {
+                self.timings
+                    .record(test.parents.len(), test.hierarchical_number(), run.point_at);
+                self.stderr.complete(test.hierarchical_number());
+                completed.push(test);
+            }
+            let content = line.trim_start();
+            if content.starts_with("ok") || content.starts_with("not ok") {
+                run.point_at = at;
+            }
+        }
+        if let Some(test) = result.as_ref().and_then(|_| run.live.finish()) {
+            self.timings
+                .record(test.parents.len(), test.hierarchical_number(), run.point_at);
+        }
+        for event in run.stderr.try_iter() {
+            self.stderr.push(event);
+        }
+        if !completed.is_empty() && result.is_none() {
+            let tree_selection = self.tree_selection();
+            self.tests.extend(completed);
+            self.rebuild_results();
+            self.refresh_tree(tree_selection);
+        }
+
+        let Some(result) = result else {
+            return Ok(());
+        };
+        let run = self.test_run.take().unwrap();
+        self.failed_status = run.statuses.try_iter().find(|status| !status.success());
+        self.colored = match &result {
+            Some(Ok(tap)) => ansi::diagnostics(tap, &run.colored),
+            _ => HashMap::new(),
+        };
+        if let Some(Ok(tap)) = &result {
+            self.stderr.number(tap);
+        }
+        // The selection made while the tests were running takes precedence
+        let selected = self.selected_key().or(run.selected);
+        self.finish_test_run(run.worker.started_at, selected, run.seed, result)?;
+        // Reads the next document of the sources that keep producing them
+        let continuous = self.source.lock().unwrap().continuous();
+        match continuous {
+            true => self.run_test_command(),
+            false => Ok(()),
+        }
+    }
+
+    /// Waits for the test command to finish and loads its results, outside of the TUI
+    fn wait_test_run(&mut self) -> anyhow::Result<()> {
+        let Some(run) = self.test_run.take() else {
+            return Ok(());
+        };
+        let started_at = run.worker.started_at;
+        let result = run.worker.wait();
+        self.failed_status = run.statuses.try_iter().find(|status| !status.success());
+        self.finish_test_run(started_at, run.selected, run.seed, result)
+    }
+
+    fn finish_test_run(
+        &mut self,
+        started_at: Instant,
+        selected: Option<String>,
+        seed: Option<u64>,
+        result: Option<anyhow::Result<String>>,
+    ) -> anyhow::Result<()> {
+        self.run_duration = Some(started_at.elapsed());
+        let tap = result.context("The test command could not be read")??;
+
+        if let Err(e) = Storage::write(&self.last_run_path(), &tap) {
+            self.toasts
+                .push(Toast::warning(format!("Could not save the run: {e:#}")));
+        }
+        let commit = Config::project_root(self.config.explicit.as_deref())
+            .ok()
+            .and_then(|root| Commit::head(&root));
+        if let Err(e) = self.history().record(&tap, commit.as_ref(), seed) {
+            self.toasts
+                .push(Toast::warning(format!("Could not archive the run: {e:#}")));
+        }
+
+        self.previous_commit = std::mem::replace(&mut self.commit, commit);
+        self.seed = seed;
+        if let Err(e) = self.load_tap(&tap) {
+            self.transcript
+                .record(format!("Could not parse the output: {e:#}"));
+            return Err(e);
+        }
+        // The last tests are only complete once the document ends
+        if !self.timings.is_empty() {
+            let end = started_at.elapsed();
+            for test in &self.tests {
+                let number = test.hierarchical_number();
+                if self.timings.get(&number).is_none() {
+                    self.timings.record(test.parents.len(), number, end);
+                }
+            }
+        }
+        if let Some(key) = selected {
+            self.select_by_key(&key);
+        }
+        self.select_first_failure();
+        if let Some(session) = self.restored_session.take() {
+            self.restore_view(&session);
+        }
+        if let Some(path) = &self.report_json {
+            if let Err(e) = report::write(path, &self.tests, self.summary(), &self.plan_mismatches)
+            {
+                self.toasts.push(Toast::warning(format!("{e:#}")));
+            }
+        }
+        let mut summary = Vec::new();
+        self.print_summary(&mut summary, OutputLevel::Verbose)?;
+        self.transcript.record("Run finished");
+        self.transcript.attach(&String::from_utf8_lossy(&summary));
+        self.broadcast_results();
+
+        let failing = self.statuses.contains(&TestResult::Fail);
+        let green_again = match (failing, self.failing_since) {
+            (true, None) => {
+                self.failing_since = Some(started_at);
+                false
+            }
+            (false, Some(since)) => {
+                self.failing_since = None;
+                let message = format!(
+                    "The suite is green again after {}",
+                    describe_duration(since.elapsed())
+                );
+                self.transcript.record(&message);
+                self.toasts.push(Toast::success(message));
+                true
+            }
+            _ => false,
+        };
+
+        let hooks = [
+            ("post_run", &self.hooks.post_run, true),
+            ("on_fail", &self.hooks.on_fail, failing),
+            ("on_green", &self.hooks.on_green, green_again),
+        ];
+        for (name, command, applies) in hooks {
+            let Some(command) = command.as_ref().filter(|_| applies) else {
+                continue;
+            };
+            if let Err(e) = self.run_hook(name, command) {
+                self.toasts.push(Toast::warning(e));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Restarts the plugins if their commands changed
+    fn start_plugins(&mut self, commands: Vec<String>) {
+        if self.plugins.iter().map(|p| &p.command).eq(&commands) {
+            return;
+        }
+
+        self.plugins.clear();
+        for command in commands {
+            match Plugin::spawn(&command, &self.env) {
+                Ok(plugin) => self.plugins.push(plugin),
+                Err(e) => self.toasts.push(Toast::error(format!(
+                    "Could not start plugin `{command}`: {e}"
+                ))),
+            }
+        }
+    }
+
+    /// Sends the results of the last run to the plugins
+    fn broadcast_results(&mut self) {
+        if self.plugins.is_empty() {
+            return;
+        }
+
+        let numbers = self
+            .tests
+            .iter()
+            .map(Test::hierarchical_number)
+            .collect_vec();
+        let mut events = self
+            .tests
+            .iter()
+            .zip(&numbers)
+            .map(|(test, number)| plugin::Event::Test {
+                number,
+                desc: test.desc.as_deref(),
+                result: test.status().name(),
+                yaml: &test.yaml,
+            })
+            .collect_vec();
+        let summary = self.summary();
+        events.push(plugin::Event::RunFinished {
+            total: summary.total,
+            passed: summary.passed,
+            failed: summary.failed,
+            skipped: summary.skipped,
+            todo: summary.todo + summary.bonus,
+        });
+
+        if let Some(e) = plugin::broadcast(&mut self.plugins, &events).pop() {
+            self.toasts.push(Toast::error(e));
+        }
+    }
+
+    /// Applies the commands emitted by the plugins
+    fn poll_plugins(&mut self) {
+        for command in self.plugins.iter().flat_map(Plugin::poll).collect_vec() {
+            match command {
+                Ok(plugin::Command::Toast { message }) => self.toasts.push(Toast::info(message)),
+                Ok(plugin::Command::Annotate { test, text }) => {
+                    self.annotations.entry(test).or_default().push(text)
+                }
+                Err(e) => self.toasts.push(Toast::warning(e)),
+            }
+        }
+    }
+
+    /// Runs the `name` hook, with environment variables describing the last run
+    fn run_hook(&self, name: &str, command: &str) -> anyhow::Result<()> {
+        let output = self
+            .hook_expression(name, command)
+            .stderr_to_stdout()
+            .stdout_capture()
+            .unchecked()
+            .run()?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "The {name} hook failed ({}): {}",
+                output.status,
+                String::from_utf8_lossy(&output.stdout)
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Command of the hook `name`, given the results of the last run in its environment
+    fn hook_expression(&self, name: &str, command: &str) -> duct::Expression {
+        let summary = self.summary();
+        let failures = self
+            .failure
+            .items()
+            .iter()
+            .map(|f| describe(&f.number, f.desc.as_deref()))
+            .join("\n");
+
+        self.expression("sh", &["-c".into(), command.into()])
+            .env("TAP_RUNNER_HOOK", name)
+            .env("TAP_RUNNER_PROFILE", self.profile.as_deref().unwrap_or(""))
+            .env("TAP_RUNNER_TOTAL", summary.total.to_string())
+            .env("TAP_RUNNER_PASSED", summary.passed.to_string())
+            .env("TAP_RUNNER_FAILED", summary.failed.to_string())
+            .env("TAP_RUNNER_SKIPPED", summary.skipped.to_string())
+            .env("TAP_RUNNER_TODO", summary.todo.to_string())
+            .env("TAP_RUNNER_BONUS", summary.bonus.to_string())
+            .env("TAP_RUNNER_FAILURES", failures)
+            .env("TAP_RUNNER_REPORT", self.last_run_path())
+            .stdin_null()
+    }
+
+    /// Prefixes a command with the wrapper loading the environment of the project, if any
+    fn wrap(&self, command: &str, args: &[String]) -> Vec<String> {
+        self.wrapper
+            .iter()
+            .map(String::as_str)
+            .chain(std::iter::once(command))
+            .chain(args.iter().map(String::as_str))
+            .map(ToOwned::to_owned)
+            .collect()
+    }
+
+    /// Command run with the environment of the configuration, in the directory of the tests
+    fn expression(&self, command: &str, args: &[String]) -> duct::Expression {
+        self.expression_in(self.run_dir.as_deref(), command, args)
+    }
+
+    /// Command run with the environment of the configuration, in `dir` if given
+    fn expression_in(
+        &self,
+        dir: Option<&Path>,
+        command: &str,
+        args: &[String],
+    ) -> duct::Expression {
+        let command = self.wrap(command, args);
+        let expression = self
+            .env
+            .iter()
+            .fold(duct::cmd(&command[0], &command[1..]), |cmd, (k, v)| {
+                cmd.env(k, v)
+            });
+        match dir {
+            Some(dir) => expression.dir(dir),
+            None => expression,
+        }
+    }
+
+    /// Rerun command, with the seed of the run shown so that the tests are run the same way
+    fn rerun_expression(&self, command: &[String]) -> duct::Expression {
+        let command = command
+            .iter()
+            .map(|arg| seeded(arg, self.seed))
+            .collect_vec();
+        let expression = self.expression(&command[0], &command[1..]);
+        match self.seed {
+            Some(seed) => expression.env("TAP_SEED", seed.to_string()),
+            None => expression,
+        }
+    }
+
+    /// Name of the build command at `index`, to report which one failed
+    fn build_step(&self, index: usize) -> String {
+        match self.build.len() {
+            1 => "Build command".to_owned(),
+            count => format!(
+                "Build step {}/{count} (`{}`)",
+                index + 1,
+                transcript::command_line(&self.build[index])
+            ),
+        }
+    }
+
+    /// Line written to the build output before the output of the command at `index`, telling
+    /// where it starts when there are several
+    fn build_header(&self, index: usize) -> String {
+        match self.build.len() {
+            1 => String::new(),
+            _ => format!("$ {}\n", transcript::command_line(&self.build[index])),
+        }
+    }
+
+    fn record_command(&mut self, action: &str, line: &[String]) {
+        self.transcript
+            .record(format!("{action}: {}", transcript::command_line(line)));
+    }
+
+    /// Build command at `index`, as run, with its expression
+    fn build_command(&self, index: usize) -> (Vec<String>, duct::Expression) {
+        let command = &self.build[index];
+        (
+            self.wrap(&command[0], &command[1..]),
+            self.expression_in(self.build_dir.as_deref(), &command[0], &command[1..]),
+        )
+    }
+
+    fn sources_fingerprint(&self) -> anyhow::Result<Option<String>> {
+        Ok(match self.sources.is_empty() {
+            true => None,
+            false => Some(format!("{:016x}", fingerprint::sources(&self.sources)?)),
+        })
+    }
+
+    /// Starts the build in the background, displaying its output as it runs
+    fn start_build(&mut self) -> anyhow::Result<()> {
+        if self.build.is_empty() {
+            anyhow::bail!("No build command is configured");
+        }
+        let fingerprint = self.sources_fingerprint()?;
+        let commands = (0..self.build.len())
+            .map(|index| {
+                let (line, build) = self.build_command(index);
+                self.record_command("Building", &line);
+                (self.build_header(index), build)
+            })
+            .collect();
+        self.build_scroll = 0;
+        self.build_job = Some((Job::sequence(commands)?, fingerprint));
+        Ok(())
+    }
+
+    /// Shows the output of the last build in the build pane again
+    fn show_last_build(&mut self) {
+        match &self.last_build {
+            _ if self.build_running() => (),
+            Some((output, success)) => {
+                self.build_scroll = 0;
+                self.build_job = Some((Job::finished(output.clone(), *success), None));
+            }
+            None => self.toasts.push(Toast::error("No build has run yet")),
+        }
+    }
+
+    fn poll_build(&mut self) -> anyhow::Result<()> {
+        let Some((job, _)) = &mut self.build_job else {
+            return Ok(());
+        };
+        match job.poll() {
+            Some(result) => self.finish_build(result),
+            None => Ok(()),
+        }
+    }
+
+    /// Waits for the build job and starts the tests which were waiting for it, outside of the TUI
+    fn wait_build(&mut self) -> anyhow::Result<()> {
+        let Some((job, _)) = &mut self.build_job else {
+            return Ok(());
+        };
+        match job.wait() {
+            Some(result) => self.finish_build(result),
+            None => Ok(()),
+        }
+    }
+
+    fn finish_build(&mut self, result: std::io::Result<bool>) -> anyhow::Result<()> {
+        let pending = self.pending_tests.take();
+        let Some((job, fingerprint)) = &mut self.build_job else {
+            return Ok(());
+        };
+        let fingerprint = fingerprint.take();
+        let (output, stage) = (job.output.clone(), job.stage);
+        if pending.as_ref().is_none_or(|p| p.built) {
+            self.last_build = Some((output.clone(), result.as_ref().is_ok_and(|&s| s)));
+        }
+        let step = match &pending {
+            Some(pending) if pending.hook && stage == 0 => "The pre_run hook".to_owned(),
+            Some(pending) if pending.hook => self.build_step(stage - 1),
+            _ => self.build_step(stage),
+        };
+
+        match result {
+            Ok(true) => {
+                if let Some(fingerprint) = fingerprint {
+                    Storage::write(&self.build_fingerprint_path(), fingerprint)?;
+                }
+                let Some(pending) = pending else {
+                    self.toasts.push(Toast::info("Build succeeded"));
+                    self.transcript.record("Build succeeded");
+                    return Ok(());
+                };
+                self.build_job = None;
+                if pending.built && !String::from_utf8_lossy(&output).trim().is_empty() {
+                    self.toasts.push(Toast::warning(
+                        "The build printed some output, `O` shows it",
+                    ));
+                }
+                self.could_run = true;
+                self.start_test_command(pending.selected)
+            }
+            Ok(false) => {
+                self.transcript.record(format!("{step} failed"));
+                self.transcript.attach(&String::from_utf8_lossy(&output));
+                anyhow::bail!("{step} failed")
+            }
+            Err(e) => {
+                self.transcript.record(format!("{step} failed: {e}"));
+                anyhow::bail!("{step} failed: {e}")
+            }
+        }
+    }
+
+    /// Command of a rerun `template`, with the placeholders replaced by the values of the test at
+    /// `index`
+    fn rerun_command(&self, template: &[String], index: usize) -> Vec<String> {
+        let test = &self.tests[index];
+        let path = tree::description_path(&self.tests, index);
+        template
+            .iter()
+            .map(|arg| {
+                arg.replace("{number}", &test.hierarchical_number())
+                    .replace("{desc}", test.desc.as_deref().unwrap_or(""))
+                    .replace("{path}", &path)
+            })
+            .collect_vec()
+    }
+
+    /// Indices in the failure list of the tests which still fail, not having passed on a rerun
+    fn still_failing(&self) -> Vec<usize> {
+        (0..self.failure.items().len())
+            .filter(|&i| {
+                self.failure.items()[i]
+                    .rerun
+                    .is_none_or(|r| r == TestResult::Fail)
+            })
+            .collect()
+    }
+
+    /// Command of the rerun `template` of the tests still failing. An argument which is only a
+    /// placeholder becomes one argument per test, the values being joined with `|` in the others.
+    fn rerun_failures_command(&self, template: &[String]) -> Vec<String> {
+        let indices = self
+            .still_failing()
+            .into_iter()
+            .map(|i| self.failure.items()[i].index)
+            .collect_vec();
+        let values = |placeholder: &str| {
+            indices
+                .iter()
+                .map(|&i| match placeholder {
+                    "{numbers}" => self.tests[i].hierarchical_number(),
+                    "{descriptions}" => self.tests[i].desc.clone().unwrap_or_default(),
+                    _ => tree::description_path(&self.tests, i),
+                })
+                .collect_vec()
+        };
+        const PLACEHOLDERS: [&str; 3] = ["{numbers}", "{descriptions}", "{paths}"];
+        template
+            .iter()
+            .flat_map(|arg| match PLACEHOLDERS.contains(&arg.as_str()) {
+                true => values(arg),
+                false => vec![PLACEHOLDERS.iter().fold(arg.clone(), |arg, placeholder| {
+                    arg.replace(placeholder, &values(placeholder).join("|"))
+                })],
+            })
+            .collect()
+    }
+
+    fn start_rerun(&mut self, target: RerunTarget) -> anyhow::Result<()> {
+        if self.rerun.as_ref().is_some_and(|(_, job)| job.running()) {
+            anyhow::bail!("A rerun is already running");
+        }
+        if self.test_run.is_some() {
+            anyhow::bail!("The tests are running");
+        }
+
+        if target == RerunTarget::Failures {
+            let template = self
+                .rerun_failures_command
+                .as_ref()
+                .context("No rerun command is configured")?;
+            if template.is_empty() {
+                anyhow::bail!("The rerun command is empty");
+            }
+            let failing = self.still_failing().len();
+            if failing == 0 {
+                anyhow::bail!("No test is failing");
+            }
+            let command = self.rerun_failures_command(template);
+            let expression = self.rerun_expression(&command);
+            self.rerun = Some((target, Job::separate(expression)?));
+            let action = format!("Rerunning the {failing} failing tests");
+            self.record_command(&action, &self.wrap(&command[0], &command[1..]));
+            return Ok(());
+        }
+
+        let (template, index) = match target {
+            RerunTarget::Failures => unreachable!("handled above"),
+            RerunTarget::Failure(i) => (
+                self.rerun_test_command
+                    .as_ref()
+                    .context("No rerun test command is configured")?,
+                self.failure.items()[i].index,
+            ),
+            RerunTarget::Subtree(i) if tree::subtree_start(&self.tests, i) < i => (
+                self.rerun_subtest_command
+                    .as_ref()
+                    .context("No rerun subtest command is configured")?,
+                i,
+            ),
+            RerunTarget::Subtree(i) => (
+                self.rerun_test_command
+                    .as_ref()
+                    .context("No rerun test command is configured")?,
+                i,
+            ),
+        };
+        if template.is_empty() {
+            anyhow::bail!("The rerun command is empty");
+        }
+
+        let command = self.rerun_command(template, index);
+        let expression = self.rerun_expression(&command);
+        self.rerun = Some((target, Job::separate(expression)?));
+        let test = &self.tests[index];
+        let action = format!(
+            "Rerunning {}",
+            describe(&test.hierarchical_number(), test.desc.as_deref())
+        );
+        self.record_command(&action, &self.wrap(&command[0], &command[1..]));
+
+        Ok(())
+    }
+
+    /// Runs the selected failure on its own in the background, with the rerun test command
+    fn rerun_selected(&mut self) -> anyhow::Result<()> {
+        match self.failure.selected_index() {
+            None => Ok(()),
+            Some(index) => self.start_rerun(RerunTarget::Failure(index)),
+        }
+    }
+
+    /// Runs the subtree selected in the tree view in the background, with the rerun subtest
+    /// command (or the rerun test command for a single test)
+    fn rerun_subtree(&mut self) -> anyhow::Result<()> {
+        let selected = self
+            .tree
+            .as_ref()
+            .and_then(|t| t.rows.selected())
+            .map(|r| r.index);
+        match selected {
+            None => Ok(()),
+            Some(index) => self.start_rerun(RerunTarget::Subtree(index)),
+        }
+    }
+
+    /// Merges the results of the rerun once it finishes
+    fn poll_rerun(&mut self) -> anyhow::Result<()> {
+        let Some((_, job)) = &mut self.rerun else {
+            return Ok(());
+        };
+        if let Some(Err(e)) = job.poll() {
+            anyhow::bail!("Could not rerun the test: {e}");
+        }
+        if job.running() {
+            return Ok(());
+        }
+
+        let (target, job) = self.rerun.take().unwrap();
+        if let Some(next) = self.rerun_queue.pop_front() {
+            self.start_rerun(next)?;
+        }
+        let output = String::from_utf8_lossy(&job.output);
+        let tests = self
+            .parse(&output)
+            .context("Could not parse the output of the rerun")?
+            .tests;
+        let errors = String::from_utf8_lossy(&job.errors)
+            .lines()
+            .map(|line| ansi::strip(line).into_owned())
+            .collect_vec();
+        self.toasts.extend(warnings_toast(&tests));
+        let failed = tests.iter().filter(|t| t.status() == TestResult::Fail);
+        self.transcript.record(format!(
+            "Rerun finished: {} tests, {} failed",
+            tests.len(),
+            failed.count()
+        ));
+
+        match target {
+            RerunTarget::Failure(i) => self.merge_failure(i, tests, errors),
+            RerunTarget::Failures => self.merge_failures(tests),
+            RerunTarget::Subtree(i) => self.merge_subtree(i, tests),
+        }
+    }
+
+    /// Replaces the result of the failure at `index` in the failure list, keeping it in the list,
+    /// with the output of the rerun on stderr
+    fn merge_failure(
+        &mut self,
+        index: usize,
+        tests: Vec<Test>,
+        stderr: Vec<String>,
+    ) -> anyhow::Result<()> {
+        let failure = &self.failure.items()[index];
+        let position = tests
+            .iter()
+            .position(|t| t.desc.is_some() && t.desc == failure.desc)
+            .or((tests.len() == 1).then_some(0))
+            .with_context(|| format!("Test {} is not in the rerun output", failure.number))?;
+        let test = tests.into_iter().nth(position).unwrap();
+
+        let status = self.replace_failure(index, test);
+        self.failure.items_mut()[index].stderr = stderr;
+        let number = &self.failure.items()[index].number;
+        self.toasts.push(Toast::info(match status {
+            TestResult::Fail => format!("Test {number} still fails"),
+            TestResult::Success => format!("Test {number} passed on rerun"),
+            TestResult::Skip => format!("Test {number} was skipped on rerun"),
+            TestResult::Todo => format!("Test {number} is marked TODO on rerun"),
+            TestResult::Bonus => format!("Test {number} passed on rerun, as TODO"),
+        }));
+
+        Ok(())
+    }
+
+    /// Replaces the results of the failures still failing with the ones of the rerun of them all,
+    /// matching the tests by description
+    fn merge_failures(&mut self, tests: Vec<Test>) -> anyhow::Result<()> {
+        let mut tests = tests.into_iter().map(Some).collect_vec();
+        let (mut fixed, mut failing, mut missing) = (0, 0, Vec::new());
+        for index in self.still_failing() {
+            let failure = &self.failure.items()[index];
+            let found = tests.iter().position(|t| {
+                t.as_ref()
+                    .is_some_and(|t| t.desc.is_some() && t.desc == failure.desc)
+            });
+            let Some(test) = found.and_then(|position| tests[position].take()) else {
+                missing.push(failure.number.clone());
+                continue;
+            };
+            match self.replace_failure(index, test) {
+                TestResult::Fail => failing += 1,
+                _ => fixed += 1,
+            }
+        }
+
+        let mut message =
+            format!("Rerun of the failing tests: {fixed} passed, {failing} still fail");
+        if !missing.is_empty() {
+            message.push_str(&format!(", not in the output: {}", missing.join(", ")));
+        }
+        self.toasts.push(match missing.is_empty() {
+            true => Toast::info(message),
+            false => Toast::warning(message),
+        });
+        Ok(())
+    }
+
+    /// Replaces the result of the failure at `index` in the failure list with the rerun `test`,
+    /// keeping it in the list, and returns its new status
+    fn replace_failure(&mut self, index: usize, mut test: Test) -> TestResult {
+        let failure = &mut self.failure.items_mut()[index];
+        let status = test.status();
+        self.statuses[failure.index] = status;
+        failure.yaml = test.yaml.clone();
+        failure.location = test.location.clone();
+        failure.warnings = test.warnings.clone();
+        failure.artifacts = test.artifacts();
+        failure.tags = test.tags.clone();
+        failure.rerun = Some(status);
+
+        let old = &self.tests[failure.index];
+        test.number = old.number;
+        test.parents = old.parents.clone();
+        self.tests[failure.index] = test;
+
+        status
+    }
+
+    /// Replaces the subtree ending at `index` with the results of its rerun. The output of the
+    /// rerun may either contain the subtest itself, or only its children.
+    fn merge_subtree(&mut self, index: usize, mut tests: Vec<Test>) -> anyhow::Result<()> {
+        let start = tree::subtree_start(&self.tests, index);
+        let node = &self.tests[index];
+        let depth = node.parents.len();
+        let number = node.hierarchical_number();
+
+        let found = tests
+            .iter()
+            .rposition(|t| t.desc.is_some() && t.desc == node.desc);
+        let range = match found {
+            Some(i) => {
+                let base = tests[i].parents.len();
+                let mut subtree = tests
+                    .drain(tree::subtree_start(&tests, i)..=i)
+                    .collect_vec();
+                // The children keep the index of the subtest in the full run in their lineage,
+                // rather than its index in the rerun output
+                let parent = match start < index {
+                    true => self.tests[start].parents[depth],
+                    false => node.number,
+                };
+                for test in &mut subtree {
+                    test.parents = node
+                        .parents
+                        .iter()
+                        .chain(&test.parents[base..])
+                        .copied()
+                        .collect();
+                    if let Some(lineage) = test.parents.get_mut(depth) {
+                        *lineage = parent;
+                    }
+                }
+                subtree.last_mut().unwrap().number = node.number;
+                tests = subtree;
+                start..index + 1
+            }
+            None if start < index => {
+                let lineage = self.tests[start].parents[..=depth].to_vec();
+                for test in &mut tests {
+                    test.parents = lineage.iter().chain(&test.parents).copied().collect();
+                }
+                self.tests[index].result = tests
+                    .iter()
+                    .filter(|t| t.parents.len() == depth + 1)
+                    .all(|t| t.result);
+                start..index
+            }
+            None if tests.len() == 1 => {
+                tests[0].number = node.number;
+                tests[0].parents = node.parents.clone();
+                index..index + 1
+            }
+            None => anyhow::bail!(
+                "Test {} is not in the rerun output",
+                node.hierarchical_number()
+            ),
+        };
+
+        let selected = self.tree_selection();
+        self.tests.splice(range, tests);
+        self.rebuild_results();
+        self.refresh_tree(selected);
+
+        let children = self.tests[start..]
+            .iter()
+            .take_while(|t| t.parents.len() > depth)
+            .count();
+        self.toasts
+            .push(Toast::info(match self.tests[start + children].result {
+                true => format!("Test {number} passed on rerun"),
+                false => format!("Test {number} still fails"),
+            }));
+
+        Ok(())
+    }
+
+    fn build_running(&self) -> bool {
+        self.build_job
+            .as_ref()
+            .is_some_and(|(job, _)| job.running())
+    }
+
+    /// File where the fingerprint of the sources at the last successful build is saved
+    fn build_fingerprint_path(&self) -> PathBuf {
+        let command = self.build.iter().map(|c| c.join("\0")).join("\n");
+        self.storage.state_path(&format!(
+            "build-{:016x}.fingerprint",
+            storage::stable_hash(command.as_bytes())
+        ))
+    }
+
+    /// Runs the build even if the sources did not change, then runs the tests
+    fn rebuild_and_run_tests(&mut self) -> anyhow::Result<()> {
+        match std::fs::remove_file(self.build_fingerprint_path()) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => self.run_tests(),
+        }
+    }
+
+    /// Hash identifying the current test command in the state file names
+    fn command_hash(&self) -> u64 {
+        let command = self
+            .source_spec
+            .iter()
+            .chain(std::iter::once(&self.test_command))
+            .chain(&self.test_args)
+            .join("\0");
+        storage::stable_hash(command.as_bytes())
+    }
+
+    /// File where the output of the last run of the current commands is saved
+    fn last_run_path(&self) -> PathBuf {
+        self.storage
+            .state_path(&format!("last-run-{:016x}.tap", self.command_hash()))
+    }
+
+    /// Past runs of the current commands
+    fn history(&self) -> History {
+        History::new(
+            self.storage
+                .state_path(&format!("runs/{:016x}", self.command_hash())),
+        )
+    }
+
+    /// Shows the run before (`older`) or after the one shown, going back to the last run after
+    /// the newest past run
+    fn step_history(&mut self, older: bool) -> anyhow::Result<()> {
+        if self.test_run.is_some() {
+            anyhow::bail!("The tests are running");
+        }
+        let past_run = match self.past_run.take() {
+            Some(past_run) => past_run,
+            None => {
+                let runs = self.history().runs();
+                let newest = runs.last().and_then(|r| r.read().ok());
+                let last = match newest.is_some() && newest == self.tap {
+                    true => runs.len() - 1,
+                    false => runs.len(),
+                };
+                PastRun {
+                    runs,
+                    position: last,
+                    last,
+                    stale: self.stale,
+                }
+            }
+        };
+        let position = match older {
+            true => past_run.position.checked_sub(1),
+            false => Some(past_run.position + 1).filter(|&p| p <= past_run.last),
+        };
+        let Some(position) = position else {
+            let at_last = past_run.position == past_run.last;
+            if !at_last {
+                self.past_run = Some(past_run);
+            }
+            anyhow::bail!(match older {
+                true => "No older run",
+                false => "This is the last run",
+            });
+        };
+
+        if position == past_run.last {
+            let tap = self.tap.clone().unwrap_or_default();
+            self.show_tap(&tap)?;
+            self.stale = past_run.stale;
+            return Ok(());
+        }
+        let tap = past_run.runs[position].read()?;
+        self.show_tap(&tap)?;
+        self.select_first_failure();
+        self.past_run = Some(PastRun {
+            position,
+            ..past_run
+        });
+        Ok(())
+    }
+
+    /// File where the baseline of the benchmarks of the current commands is saved
+    fn bench_baseline_path(&self) -> PathBuf {
+        self.storage
+            .state_path(&format!("bench-baseline-{:016x}.json", self.command_hash()))
+    }
+
+    fn toggle_bench(&mut self) {
+        self.bench = match self.bench {
+            Some(_) => None,
+            None => {
+                self.tree = None;
+                self.diff = None;
+                self.raw = None;
+                self.clusters = None;
+                self.timeline = None;
+                self.stderr_view = None;
+                Some(BenchView {
+                    baseline: bench::load_baseline(&self.bench_baseline_path()),
+                    state: TableState::default(),
+                })
+            }
+        };
+    }
+
+    /// Handles the keys specific to the benchmark view, returns whether the key was handled
+    fn handle_bench_key(&mut self, code: KeyCode) -> anyhow::Result<bool> {
+        let baseline_path = self.bench_baseline_path();
+        let Some(view) = &mut self.bench else {
+            return Ok(false);
+        };
+
+        let count = bench::compare(&bench::collect(&self.tests), &view.baseline).len();
+        let selected = view.state.selected();
+        match code {
+            KeyCode::Up | KeyCode::Down if count == 0 => (),
+            KeyCode::Up => view.state.select(Some(
+                selected.map_or(count - 1, |i| (i + count - 1) % count),
+            )),
+            KeyCode::Down => view
+                .state
+                .select(Some(selected.map_or(0, |i| (i + 1) % count))),
+            KeyCode::Esc => view.state.select(None),
+            KeyCode::Char('s') => {
+                let current = bench::collect(&self.tests);
+                bench::save_baseline(&baseline_path, &current)?;
+                view.baseline = current;
+                self.toasts
+                    .push(Toast::info("Saved the measurements as the baseline"));
+            }
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    fn toggle_clusters(&mut self) {
+        self.clusters = match self.clusters {
+            Some(_) => None,
+            None => {
+                self.tree = None;
+                self.bench = None;
+                self.diff = None;
+                self.raw = None;
+                self.timeline = None;
+                self.stderr_view = None;
+                Some(ClusterView {
+                    clusters: StatefulList::empty().wrapping(self.wrap_selection),
+                })
+            }
+        };
+        self.refresh_clusters();
+    }
+
+    /// Groups the failures shown by the similarity of their messages, if the clusters are shown
+    fn refresh_clusters(&mut self) {
+        let Some(view) = &mut self.clusters else {
+            return;
+        };
+        let failures = self.failure.items();
+        let messages = failures.iter().map(Failure::message).collect_vec();
+        let clusters = cluster::group(&messages)
+            .into_iter()
+            .map(|members| {
+                members
+                    .into_iter()
+                    .map(|i| failures[i].number.clone())
+                    .collect_vec()
+            })
+            .collect_vec();
+        view.clusters.set_items(clusters);
+        if view.clusters.selected().is_none() {
+            view.clusters.next();
+        }
+    }
+
+    /// Handles the keys specific to the cluster view, returns whether the key was handled
+    fn handle_cluster_key(&mut self, code: KeyCode) -> bool {
+        let Some(view) = &mut self.clusters else {
+            return false;
+        };
+
+        match code {
+            KeyCode::Up => view.clusters.previous(),
+            KeyCode::Down => view.clusters.next(),
+            KeyCode::Enter => {
+                if let Some(number) = view.clusters.selected().map(|c| c[0].clone()) {
+                    self.clusters = None;
+                    self.select_failure(&number);
+                    self.preview_scroll = 0;
+                }
+            }
+            KeyCode::Char('m') => {
+                let Some(cluster) = view.clusters.selected() else {
+                    return true;
+                };
+                let members = self
+                    .failure
+                    .items_mut()
+                    .iter_mut()
+                    .filter(|f| cluster.contains(&f.number))
+                    .collect_vec();
+                // Marks the whole cluster, or unmarks it when it already is
+                let marked = !members.iter().all(|f| f.marked);
+                for failure in members {
+                    failure.marked = marked;
+                }
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn toggle_timeline(&mut self) {
+        self.timeline = match self.timeline {
+            Some(_) => None,
+            None => {
+                self.tree = None;
+                self.bench = None;
+                self.diff = None;
+                self.raw = None;
+                self.clusters = None;
+                self.stderr_view = None;
+                Some(TimelineView { scroll: 0 })
+            }
+        };
+    }
+
+    fn toggle_stderr(&mut self) {
+        self.stderr_view = match self.stderr_view {
+            Some(_) => None,
+            None => {
+                self.tree = None;
+                self.bench = None;
+                self.diff = None;
+                self.raw = None;
+                self.clusters = None;
+                self.timeline = None;
+                Some(StderrView { scroll: 0 })
+            }
+        };
+    }
+
+    /// Handles the keys specific to the stderr view, returns whether the key was handled
+    fn handle_stderr_key(&mut self, code: KeyCode) -> bool {
+        let Some(view) = &mut self.stderr_view else {
+            return false;
+        };
+
+        // Bounded when drawn, to the rows that fit
+        view.scroll = match code {
+            KeyCode::Up => view.scroll.saturating_sub(1),
+            KeyCode::Down => view.scroll + 1,
+            KeyCode::PageUp => view.scroll.saturating_sub(20),
+            KeyCode::PageDown => view.scroll + 20,
+            KeyCode::Home => 0,
+            KeyCode::End => usize::MAX,
+            _ => return false,
+        };
+
+        true
+    }
+
+    /// Handles the keys specific to the timeline view, returns whether the key was handled
+    fn handle_timeline_key(&mut self, code: KeyCode) -> bool {
+        let Some(view) = &mut self.timeline else {
+            return false;
+        };
+
+        // Bounded when drawn, to the rows that fit
+        view.scroll = match code {
+            KeyCode::Up => view.scroll.saturating_sub(1),
+            KeyCode::Down => view.scroll + 1,
+            KeyCode::PageUp => view.scroll.saturating_sub(20),
+            KeyCode::PageDown => view.scroll + 20,
+            KeyCode::Home => 0,
+            KeyCode::End => usize::MAX,
+            _ => return false,
+        };
+
+        true
+    }
+
+    fn toggle_diff(&mut self) {
+        self.diff = match self.diff {
+            Some(_) => None,
+            None => {
+                self.tree = None;
+                self.bench = None;
+                self.raw = None;
+                self.clusters = None;
+                self.timeline = None;
+                self.stderr_view = None;
+                Some(DiffView {
+                    lines: Vec::new(),
+                    scroll: 0,
+                })
+            }
+        };
+        self.refresh_diff();
+    }
+
+    /// Compares the output of the current run to the previous one, if the diff is shown
+    fn refresh_diff(&mut self) {
+        let Some(view) = &mut self.diff else {
+            return;
+        };
+        view.lines = match (&self.previous_tap, &self.tap) {
+            (Some(previous), Some(current)) => diff::unified(previous, current, 3),
+            _ => Vec::new(),
+        };
+        view.scroll = view.scroll.min(view.lines.len().saturating_sub(1));
+    }
+
+    /// Shows the raw TAP document of the results instead of the failures, at the line of the
+    /// selected failure, or goes back to the failures
+    fn toggle_raw(&mut self) -> anyhow::Result<()> {
+        if self.raw.take().is_some() {
+            return Ok(());
+        }
+        let Some(tap) = self.shown_tap()? else {
+            anyhow::bail!("No output to show");
+        };
+        let mut view = RawView::new(&tap);
+        if let Some(line) = self
+            .failure
+            .selected()
+            .and_then(|f| view.tests.get(&f.number))
+        {
+            // Leaves some context above the line of the test
+            view.scroll = line.saturating_sub(3);
+        }
+        self.tree = None;
+        self.bench = None;
+        self.diff = None;
+        self.clusters = None;
+        self.timeline = None;
+        self.stderr_view = None;
+        self.raw = Some(view);
+        Ok(())
+    }
+
+    /// Handles the keys specific to the raw view, returns whether the key was handled
+    fn handle_raw_key(&mut self, code: KeyCode) -> bool {
+        let Some(view) = &mut self.raw else {
+            return false;
+        };
+
+        let max = view.lines.len().saturating_sub(1);
+        view.scroll = match code {
+            KeyCode::Up => view.scroll.saturating_sub(1),
+            KeyCode::Down => (view.scroll + 1).min(max),
+            KeyCode::PageUp => view.scroll.saturating_sub(20),
+            KeyCode::PageDown => (view.scroll + 20).min(max),
+            KeyCode::Home => 0,
+            KeyCode::End => max,
+            _ => return false,
+        };
+
+        true
+    }
+
+    /// Handles the keys specific to the diff view, returns whether the key was handled
+    fn handle_diff_key(&mut self, code: KeyCode) -> bool {
+        let Some(view) = &mut self.diff else {
+            return false;
+        };
+
+        let max = view.lines.len().saturating_sub(1);
+        view.scroll = match code {
+            KeyCode::Up => view.scroll.saturating_sub(1),
+            KeyCode::Down => (view.scroll + 1).min(max),
+            KeyCode::PageUp => view.scroll.saturating_sub(20),
+            KeyCode::PageDown => (view.scroll + 20).min(max),
+            KeyCode::Home => 0,
+            KeyCode::End => max,
+            _ => return false,
+        };
+
+        true
+    }
+
+    /// Shows the results of the last run of the current commands, until they are run again
+    fn load_last_run(&mut self) -> bool {
+        let Ok(tap) = std::fs::read_to_string(self.last_run_path()) else {
+            return false;
+        };
+
+        self.could_run = true;
+        let run = self
+            .history()
+            .runs()
+            .pop()
+            .filter(|run| run.read().ok().as_ref() == Some(&tap));
+        self.seed = run.as_ref().and_then(|run| run.seed);
+        self.commit = run.and_then(|run| run.commit);
+        if let Err(e) = self.load_tap(&tap) {
+            self.toasts.push(Toast::error(e));
+        }
+        self.stale = true;
+        true
+    }
+
+    /// Parses a TAP document, processing the tests with the script
+    fn parse(&self, tap: &str) -> anyhow::Result<Document> {
+        let mut document = parse_tap(tap, &self.location_filter)?;
+        if let Some(filter) = &self.tag_filter {
+            for test in document.tests.iter_mut().filter(|t| !t.yaml.is_empty()) {
+                match filter_strings(filter, &test.yaml) {
+                    Ok(tags) => test.tags = tags,
+                    Err(e) => test.warnings.push(format!("Tag filter: {e:#}")),
+                }
+            }
+        }
+        if let Some(script) = &self.script {
+            for test in &mut document.tests {
+                if let Err(e) = script.apply(test) {
+                    test.warnings.push(format!("{e:#}"));
+                }
+            }
+        }
+        if let Some(path) = &self.golden {
+            let golden = std::fs::read_to_string(path)
+                .with_context(|| format!("Could not read the golden file {}", path.display()))?;
+            let first = document
+                .tests
+                .iter()
+                .filter(|t| t.parents.is_empty())
+                .map(|t| t.number)
+                .max()
+                .unwrap_or(0)
+                + 1;
+            document
+                .tests
+                .extend(golden::divergences(path, &golden, tap, first));
+        }
+        Ok(document)
+    }
+
+    /// Parses a TAP document, replacing the current results
+    fn load_tap(&mut self, tap: &str) -> anyhow::Result<()> {
+        self.past_run = None;
+        self.previous_tap = self.tap.replace(tap.to_owned());
+        self.refresh_diff();
+        self.show_tap(tap)
+    }
+
+    /// Shows the results of a TAP document, without making it the last run
+    fn show_tap(&mut self, tap: &str) -> anyhow::Result<()> {
+        self.show_results(tap)?;
+        self.toasts.extend(warnings_toast(&self.tests));
+        if let Some(mismatch) = self.plan_mismatches.first() {
+            self.toasts.push(Toast::warning(mismatch));
+        }
+        Ok(())
+    }
+
+    /// Shows the results of a TAP document, without notifying its warnings
+    fn show_results(&mut self, tap: &str) -> anyhow::Result<()> {
+        self.stale = false;
+        let selected = self.tree_selection();
+        self.tests.clear();
+        self.rebuild_results();
+        self.refresh_tree(selected.clone());
+        self.rerun = None;
+        self.rerun_queue.clear();
+
+        let document = self.parse(tap)?;
+        self.tests = document.tests;
+        self.plan_mismatches = document.plan_mismatches;
+        self.skipped_suites = document.skipped_suites;
+        self.rebuild_results();
+        self.refresh_tree(selected);
+        if let Some(view) = &mut self.raw {
+            view.replace(tap);
+        }
+
+        Ok(())
+    }
+
+    /// Computes the statuses, the skipped tests and the failures from the tests
+    fn rebuild_results(&mut self) {
+        let selected = self.failure.selected().map(|f| f.number.clone());
+        let flagged = |flag: fn(&Failure) -> bool| -> HashSet<_> {
+            self.failure
+                .items()
+                .iter()
+                .filter(|f| flag(f))
+                .map(|f| f.number.clone())
+                .collect()
+        };
+        let marked = flagged(|f| f.marked);
+        let wrapped = flagged(|f| f.wrapped);
+
+        self.partial_failures = match self.interpreters.len() > 1 {
+            true => interp::partial_failures(&self.tests),
+            false => HashMap::new(),
+        };
+        self.statuses.clear();
+        self.skipped.clear();
+        let mut failure = Vec::new();
+        let mut todo = Vec::new();
+        // Only the last run was read with its stderr and colors
+        let mut stderr = match self.past_run {
+            Some(_) => HashMap::new(),
+            None => self.stderr.by_test(),
+        };
+        for (index, test) in self.tests.iter().enumerate() {
+            let number = test.hierarchical_number();
+            let status = test.status();
+            self.statuses.push(status);
+            if !has_tag(test, self.tag.as_deref()) {
+                continue;
+            }
+            match status {
+                TestResult::Fail => failure.push(Failure {
+                    expected: false,
+                    marked: marked.contains(&number),
+                    wrapped: wrapped.contains(&number),
+                    stderr: stderr.remove(&number).unwrap_or_default(),
+                    colored: match self.past_run {
+                        Some(_) => None,
+                        None => self.colored.get(&number).cloned(),
+                    },
+                    number,
+                    desc: test.desc.clone(),
+                    yaml: test.yaml.clone(),
+                    location: test.location.clone(),
+                    category: test.category.clone(),
+                    warnings: test.warnings.clone(),
+                    artifacts: test.artifacts(),
+                    tags: test.tags.clone(),
+                    only_under: self
+                        .partial_failures
+                        .get(&index)
+                        .cloned()
+                        .unwrap_or_default(),
+                    new: self
+                        .known_failures
+                        .as_ref()
+                        .map(|known| !known.contains(&failure_key(&self.tests, index))),
+                    index,
+                    rerun: None,
+                }),
+                TestResult::Skip => self.skipped.push(index),
+                TestResult::Todo | TestResult::Bonus => todo.push(index),
+                TestResult::Success => (),
+            }
+        }
+        for failure in &mut failure {
+            let key = failure_key(&self.tests, failure.index);
+            failure.expected = self.expectations.matches(&key, failure.message());
+        }
+        self.failure = StatefulList::with_items(failure).wrapping(self.wrap_selection);
+        self.sort_failures();
+        if let Some(selected) = selected {
+            self.select_failure(&selected);
+        }
+
+        // The skipped suites are listed before the skipped tests
+        let skipped = (0..self.skipped_suites.len())
+            .map(SkippedRow::Suite)
+            .chain(self.skipped.iter().map(|&index| SkippedRow::Test(index)))
+            .collect_vec();
+        self.skipped_list.set_items(skipped);
+        self.skipped_list.set_wrapping(self.wrap_selection);
+        self.todo_list.set_items(todo);
+        self.todo_list.set_wrapping(self.wrap_selection);
+        self.refresh_clusters();
+    }
+
+    /// Selects the first failure, or the first new one if configured so, when none is selected
+    fn select_first_failure(&mut self) {
+        if self.failure.selected().is_some() || self.failure.items().is_empty() {
+            return;
+        }
+        let new = self
+            .failure
+            .items()
+            .iter()
+            .position(|f| f.new == Some(true))
+            .filter(|_| self.select_new_failure);
+        self.failure.select(Some(new.unwrap_or(0)));
+    }
+
+    /// Orders the failures as in the document, or with the new ones first if `new_first`
+    fn sort_failures(&mut self) {
+        let selected = self.failure.selected().map(|f| f.number.clone());
+        let new_first = self.new_first;
+        self.failure
+            .items_mut()
+            .sort_by_key(|f| (new_first && f.new != Some(true), f.index));
+        self.failure.unselect();
+        if let Some(selected) = selected {
+            self.select_failure(&selected);
+        }
+    }
+
+    fn toggle_new_first(&mut self) -> anyhow::Result<()> {
+        // Reruns refer to the failures by their position in the list
+        if self.rerun.is_some() || !self.rerun_queue.is_empty() {
+            anyhow::bail!("The failures can't be reordered while they are rerun");
+        }
+        self.new_first = !self.new_first;
+        self.sort_failures();
+        Ok(())
+    }
+
+    fn toggle_mark(&mut self) {
+        if let Some(index) = self.failure.selected_index() {
+            let failure = &mut self.failure.items_mut()[index];
+            failure.marked = !failure.marked;
+        }
+    }
+
+    fn toggle_wrap(&mut self) {
+        if let Some(index) = self.failure.selected_index() {
+            let failure = &mut self.failure.items_mut()[index];
+            failure.wrapped = !failure.wrapped;
+        }
+    }
+
+    /// Accepts the failures at `indices` in the expectations file, or stops expecting them if
+    /// they all were
+    fn accept(&mut self, indices: &[usize]) -> anyhow::Result<()> {
+        if indices.is_empty() {
+            anyhow::bail!("No failure is selected");
+        }
+        let failures = indices.iter().map(|&i| &self.failure.items()[i]);
+        let remove = failures.clone().all(|f| f.expected);
+        for failure in failures {
+            let key = failure_key(&self.tests, failure.index);
+            match remove {
+                true => self.expectations.remove(&key),
+                false => self.expectations.accept(key, failure.message()),
+            }
+        }
+        self.expectations.save()?;
+        for &i in indices {
+            self.failure.items_mut()[i].expected = !remove;
+        }
+        let path = self.expectations.path().display();
+        self.toasts.push(Toast::info(match (remove, indices.len()) {
+            (true, 1) => format!("The failure is no longer expected, removed from {path}"),
+            (true, n) => format!("{n} failures are no longer expected, removed from {path}"),
+            (false, 1) => format!("Accepted the failure as expected in {path}"),
+            (false, n) => format!("Accepted {n} failures as expected in {path}"),
+        }));
+        Ok(())
+    }
+
+    fn marked(&self) -> impl Iterator<Item = (usize, &Failure)> {
+        self.failure
+            .items()
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| f.marked)
+    }
+
+    fn open_bulk_menu(&mut self) -> anyhow::Result<()> {
+        if self.marked().next().is_none() {
+            anyhow::bail!("No failure is marked, mark them with `m`");
+        }
+        let mut menu = StatefulList::with_items(BulkAction::ALL.to_vec());
+        menu.select(Some(0));
+        self.bulk_menu = Some(menu);
+        Ok(())
+    }
+
+    fn open_tag_picker(&mut self) -> anyhow::Result<()> {
+        let mut counts: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        for test in &self.tests {
+            for tag in &test.tags {
+                let (tests, failures) = counts.entry(tag).or_default();
+                *tests += 1;
+                *failures += (test.status() == TestResult::Fail) as usize;
+            }
+        }
+        if counts.is_empty() {
+            anyhow::bail!("No test has tags, list them under the `tags` key of the diagnostics");
+        }
+
+        let all = TagEntry {
+            tag: None,
+            tests: self.tests.len(),
+            failures: self.summary().failed,
+        };
+        let entries = std::iter::once(all)
+            .chain(counts.into_iter().map(|(tag, (tests, failures))| TagEntry {
+                tag: Some(tag.to_owned()),
+                tests,
+                failures,
+            }))
+            .collect_vec();
+        let active = entries.iter().position(|e| e.tag == self.tag);
+        let mut picker = StatefulList::with_items(entries);
+        picker.select(Some(active.unwrap_or(0)));
+        self.tag_picker = Some(picker);
+        Ok(())
+    }
+
+    /// Lists only the failures and skipped tests with the `tag`, or all of them
+    fn select_tag(&mut self, tag: Option<String>) {
+        let tree_selection = self.tree_selection();
+        self.tag = tag;
+        self.rebuild_results();
+        self.select_first_failure();
+        self.refresh_tree(tree_selection);
+    }
+
+    fn open_artifact_picker(&mut self) -> anyhow::Result<()> {
+        let Some(failure) = self.failure.selected() else {
+            anyhow::bail!("No failure is selected");
+        };
+        if failure.artifacts.is_empty() {
+            anyhow::bail!("The selected failure has no artifacts");
+        }
+        let mut picker = StatefulList::with_items(failure.artifacts.clone());
+        picker.select(Some(0));
+        self.artifact_picker = Some(picker);
+        Ok(())
+    }
+
+    /// Path of a local artifact, relative paths being relative to the root of the project
+    fn artifact_path(&self, artifact: &str) -> PathBuf {
+        match self.files_root() {
+            Some(root) => root.join(artifact),
+            None => PathBuf::from(artifact),
+        }
+    }
+
+    /// Directory the relative paths of the results are relative to: the root given, or else the
+    /// one the tests run in
+    fn files_root(&self) -> Option<&Path> {
+        self.root.as_deref().or(self.run_dir.as_deref())
+    }
+
+    /// Previews a local artifact, or opens an URL in the browser
+    fn show_artifact(&mut self, artifact: &str) -> anyhow::Result<()> {
+        match artifact::is_url(artifact) {
+            true => artifact::open(artifact),
+            false => {
+                let view = ArtifactView::load(artifact, &self.artifact_path(artifact))?;
+                self.artifact_view = Some(view);
+                Ok(())
+            }
+        }
+    }
+
+    fn open_artifact(&self, artifact: &str) -> anyhow::Result<()> {
+        match artifact::is_url(artifact) {
+            true => artifact::open(artifact),
+            false => artifact::open(&self.artifact_path(artifact).to_string_lossy()),
+        }
+    }
+
+    fn apply_bulk_action<B: Backend>(
+        &mut self,
+        action: BulkAction,
+        terminal: &mut Terminal<B>,
+    ) -> anyhow::Result<()> {
+        match action {
+            BulkAction::Rerun => {
+                if self.rerun.as_ref().is_some_and(|(_, job)| job.running()) {
+                    anyhow::bail!("A rerun is already running");
+                }
+                let mut targets = self
+                    .marked()
+                    .map(|(i, _)| RerunTarget::Failure(i))
+                    .collect::<VecDeque<_>>();
+                if let Some(first) = targets.pop_front() {
+                    self.start_rerun(first)?;
+                }
+                self.rerun_queue = targets;
+            }
+            BulkAction::Edit => self.edit_marked(terminal)?,
+            BulkAction::Copy => {
+                let mut text = Vec::new();
+                for (_, failure) in self.marked() {
+                    self.tests[failure.index].write(&mut text, "", false)?;
+                }
+                clipboard::copy(&String::from_utf8_lossy(&text))?;
+                self.toasts.push(Toast::info("Copied the marked failures"));
+            }
+            BulkAction::Export => {
+                let created = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+                let path = self
+                    .storage
+                    .state_path(&format!("reports/report-{}.txt", created.as_secs()));
+                let mut report = Vec::new();
+                for (_, failure) in self.marked() {
+                    self.tests[failure.index].write(&mut report, "", true)?;
+                }
+                Storage::write(&path, report)?;
+                self.toasts.push(Toast::info(format!(
+                    "Exported the marked failures to {}",
+                    path.display()
+                )));
+            }
+            BulkAction::Accept => {
+                let marked = self.marked().map(|(i, _)| i).collect_vec();
+                self.accept(&marked)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Saves the results of the last run as JSON in the state directory
+    fn export_report(&mut self) -> anyhow::Result<()> {
+        if self.tests.is_empty() {
+            anyhow::bail!("No results to export");
+        }
+        let created = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+        let path = self
+            .storage
+            .state_path(&format!("reports/results-{}.json", created.as_secs()));
+        report::write(&path, &self.tests, self.summary(), &self.plan_mismatches)?;
+        self.toasts.push(Toast::info(format!(
+            "Exported the results to {}",
+            path.display()
+        )));
+        Ok(())
+    }
+
+    /// Saves the transcript of the session in the state directory
+    fn export_transcript(&mut self) -> anyhow::Result<()> {
+        let created = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+        let path = self
+            .storage
+            .state_path(&format!("transcripts/transcript-{}.txt", created.as_secs()));
+        self.transcript.save(&path)?;
+        self.toasts.push(Toast::info(format!(
+            "Exported the transcript to {}",
+            path.display()
+        )));
+        Ok(())
+    }
+
+    /// Path of the file of a location, relative to the root of the project
+    fn location_path(&self, location: &Location) -> PathBuf {
+        match self.files_root() {
+            Some(root) => root.join(&location.file),
+            None => PathBuf::from(&location.file),
+        }
+    }
+
+    /// Opens the location of the selected failure in `$VISUAL` or `$EDITOR`, at its line
+    fn edit_selected<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> anyhow::Result<()> {
+        let Some(failure) = self.failure.selected() else {
+            anyhow::bail!("No failure is selected");
+        };
+        let Some(location) = &failure.location else {
+            anyhow::bail!("The selected failure has no location");
+        };
+        let file = self.location_path(location);
+        run_editor(
+            terminal,
+            &editor(),
+            vec![format!("+{}", location.line).into(), file.into_os_string()],
+        )
+    }
+
+    /// Copies the selected failure, with its location and YAML diagnostics, to the clipboard
+    fn copy_selected(&mut self) -> anyhow::Result<()> {
+        let Some(failure) = self.failure.selected() else {
+            anyhow::bail!("No failure is selected");
+        };
+        let mut text = Vec::new();
+        self.tests[failure.index].write(&mut text, "", true)?;
+        clipboard::copy(&String::from_utf8_lossy(&text))?;
+        self.toasts.push(Toast::info(format!(
+            "Copied {}",
+            describe(&failure.number, failure.desc.as_deref())
+        )));
+        Ok(())
+    }
+
+    /// Runs the explain command for the selected failure, its output being shown in a popup
+    fn explain_selected(&mut self) -> anyhow::Result<()> {
+        let Some(template) = &self.explain_command else {
+            anyhow::bail!("No explain command given on the command line or in the configuration");
+        };
+        let Some(failure) = self.failure.selected() else {
+            anyhow::bail!("No failure is selected");
+        };
+        let file = failure.location.as_ref().map(|l| self.location_path(l));
+        let location = file
+            .as_deref()
+            .zip(failure.location.as_ref().map(|l| l.line));
+        if location.is_none() && explain::needs_location(template) {
+            anyhow::bail!("The selected failure has no location");
+        }
+
+        let command = explain::command(template);
+        let variables = explain::variables(&failure.number, failure.desc.as_deref(), location);
+        let expression = variables.into_iter().fold(
+            self.expression("sh", &["-c".into(), command]).stdin_null(),
+            |expression, (var, value)| expression.env(var, value),
+        );
+        self.explanation = Some(Explanation {
+            test: describe(&failure.number, failure.desc.as_deref()),
+            job: Job::spawn(expression)?,
+            scroll: 0,
+        });
+        Ok(())
+    }
+
+    /// Opens the locations of the marked failures in `$VISUAL` or `$EDITOR`, as a quickfix list
+    /// for the vi-like editors
+    fn edit_marked<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> anyhow::Result<()> {
+        let locations = self
+            .marked()
+            .filter_map(|(_, f)| f.location.as_ref().map(|l| (f, l)))
+            .map(|(f, l)| (self.location_path(l), l.line, f))
+            .collect_vec();
+        if locations.is_empty() {
+            anyhow::bail!("None of the marked failures has a location");
+        }
+
+        let editor = editor();
+        let program = editor.split_whitespace().next().unwrap_or_default();
+        let args = if program.ends_with("vi") || program.ends_with("vim") {
+            let mut quickfix = String::new();
+            for (file, line, failure) in &locations {
+                quickfix += &format!("{}:{line}: {}", file.display(), failure.number);
+                if let Some(desc) = &failure.desc {
+                    quickfix += &format!(" - {desc}");
+                }
+                quickfix += "\n";
+            }
+            let path = self.storage.cache_path("marked.quickfix");
+            Storage::write(&path, quickfix)?;
+            vec!["-q".into(), path.into_os_string()]
+        } else {
+            locations
+                .into_iter()
+                .map(|(file, _, _)| file.into_os_string())
+                .unique()
+                .collect()
+        };
+        run_editor(terminal, &editor, args)
+    }
+
+    fn toggle_tree(&mut self) {
+        self.bench = None;
+        self.diff = None;
+        self.raw = None;
+        self.clusters = None;
+        self.timeline = None;
+        self.stderr_view = None;
+        self.tree = match self.tree {
+            Some(_) => None,
+            None => Some(TreeView {
+                rows: StatefulList::empty(),
+                collapsed: HashMap::new(),
+            }),
+        };
+        self.refresh_tree(None);
+    }
+
+    /// Handles the keys typed in the search bar
+    fn handle_search_key(&mut self, code: KeyCode) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+
+        match code {
+            KeyCode::Esc => self.search = None,
+            KeyCode::Enter if search.query.is_empty() => self.search = None,
+            KeyCode::Enter => {
+                search.editing = false;
+                self.jump_to_match(true);
+            }
+            KeyCode::Tab => search.all = !search.all,
+            KeyCode::Backspace => {
+                search.query.pop();
+            }
+            KeyCode::Char(c) => search.query.push(c),
+            _ => (),
+        }
+    }
+
+    /// Handles the keys typed in the editor of the location filter
+    fn handle_filter_edit_key(&mut self, code: KeyCode) {
+        let Some(edit) = &mut self.filter_edit else {
+            return;
+        };
+        match code {
+            KeyCode::Esc => {
+                let original = edit.original.clone();
+                self.filter_edit = None;
+                if let Err(e) = self.set_location_filter(original.as_deref()) {
+                    self.toasts.push(Toast::error(format!("{e:#}")));
+                }
+                return;
+            }
+            KeyCode::Enter if edit.error.is_some() => {
+                self.toasts.push(Toast::error(
+                    "The filter is invalid, <esc> restores the previous one",
+                ));
+                return;
+            }
+            KeyCode::Enter => {
+                self.filter_edit = None;
+                return;
+            }
+            KeyCode::Backspace => {
+                edit.text.pop();
+            }
+            KeyCode::Char(c) => edit.text.push(c),
+            _ => return,
+        }
+        let filter = edit.filter().map(str::to_owned);
+        let result = self.set_location_filter(filter.as_deref());
+        let warning = warnings_toast(&self.tests).map(|t| t.message);
+        if let Some(edit) = &mut self.filter_edit {
+            edit.error = result.err().map(|e| format!("{e:#}"));
+            edit.warning = warning;
+        }
+    }
+
+    /// Replaces the location filter, showing the results again with it
+    fn set_location_filter(&mut self, filter: Option<&str>) -> anyhow::Result<()> {
+        let location_filter = filter.map(parse_filter).transpose()?;
+        if location_filter.is_none() && (self.preview || self.root.is_some()) {
+            anyhow::bail!("--preview and --root require a location filter");
+        }
+        self.location_filter = location_filter;
+        self.location_filter_text = filter.map(str::to_owned);
+        self.reparse()
+    }
+
+    /// Applies the filters, script and templates to the results shown again, without running the
+    /// tests
+    fn reapply(&mut self) -> anyhow::Result<()> {
+        if self.test_run.is_some() {
+            anyhow::bail!("The tests are running, their results will be processed with them");
+        }
+        self.reparse()?;
+        self.toasts.extend(warnings_toast(&self.tests));
+        Ok(())
+    }
+
+    /// TAP document of the results shown, the one of the past run browsed if any
+    fn shown_tap(&self) -> anyhow::Result<Option<String>> {
+        Ok(match &self.past_run {
+            Some(past_run) => Some(past_run.runs[past_run.position].read()?),
+            None => self.tap.clone(),
+        })
+    }
+
+    /// Parses the results shown again, for the filters to apply to them without running the
+    /// tests. Their warnings are not notified.
+    fn reparse(&mut self) -> anyhow::Result<()> {
+        // The run in progress is parsed with the new filters once it finishes
+        if self.test_run.is_some() {
+            return Ok(());
+        }
+        let Some(tap) = self.shown_tap()? else {
+            return Ok(());
+        };
+        let stale = self.stale;
+        self.show_results(&tap)?;
+        self.stale = stale;
+        Ok(())
+    }
+
+    /// Indices of the tests matching the search
+    fn search_matches(&self) -> Vec<usize> {
+        let Some(search) = &self.search else {
+            return Vec::new();
+        };
+        (0..self.tests.len())
+            .filter(|&i| search.matches(&self.tests[i]))
+            .collect()
+    }
+
+    /// Selects the next (or previous) test matching the search. Matches that are not failing are
+    /// selected in the tree view, opening it if needed.
+    fn jump_to_match(&mut self, forward: bool) {
+        let matches = self.search_matches();
+        if matches.is_empty() {
+            self.toasts.push(Toast::info("No test matches the search"));
+            return;
+        }
+
+        if self.tree.is_none() && !self.search.as_ref().is_some_and(|s| s.all) {
+            let indices = self.failure.items().iter().map(|f| f.index).collect_vec();
+            let current = self.failure.selected_index();
+            if let Some(position) = next_match(&indices, current, forward, |i| {
+                matches.contains(&indices[i])
+            }) {
+                self.failure.select(Some(position));
+            }
+            return;
+        }
+
+        if self.tree.is_none() {
+            self.toggle_tree();
+        }
+        // Matches are looked for in the order of the fully expanded tree
+        let expanded = (0..self.tests.len())
+            .filter(|&i| tree::is_subtest(&self.tests, i))
+            .map(|i| (self.tests[i].hierarchical_number(), false))
+            .collect();
+        let order = tree::rows(&self.tests, &expanded)
+            .into_iter()
+            .map(|r| r.index)
+            .collect_vec();
+        let current = self
+            .tree
+            .as_ref()
+            .and_then(|t| t.rows.selected())
+            .and_then(|r| order.iter().position(|&i| i == r.index));
+        let Some(position) = next_match(&order, current, forward, |i| matches.contains(&order[i]))
+        else {
+            return;
+        };
+
+        let index = order[position];
+        if let Some(tree) = &mut self.tree {
+            for ancestor in tree::ancestors(&self.tests, index) {
+                tree.collapsed
+                    .insert(self.tests[ancestor].hierarchical_number(), false);
+            }
+        }
+        self.refresh_tree(Some(self.tests[index].hierarchical_number()));
+    }
+
+    /// Hierarchical number of the test selected in the tree view
+    fn tree_selection(&self) -> Option<String> {
+        let row = self.tree.as_ref()?.rows.selected()?;
+        Some(self.tests[row.index].hierarchical_number())
+    }
+
+    /// Recomputes the rows of the tree view, selecting the test with the hierarchical number
+    /// `selected`
+    fn refresh_tree(&mut self, selected: Option<String>) {
+        let Some(tree) = &mut self.tree else {
+            return;
+        };
+
+        let tests = &self.tests;
+        let tag = self.tag.as_deref();
+        let mut rows = tree::rows(tests, &tree.collapsed);
+        if tag.is_some() {
+            // The subtests are kept if any of their tests has the tag
+            rows.retain(|row| {
+                let start = match row.subtest {
+                    true => tree::subtree_start(tests, row.index),
+                    false => row.index,
+                };
+                tests[start..=row.index].iter().any(|t| has_tag(t, tag))
+            });
+        }
+        tree.rows = StatefulList::with_items(rows).wrapping(self.wrap_selection);
+        let position = selected.and_then(|number| {
+            tree.rows
+                .items()
+                .iter()
+                .position(|r| self.tests[r.index].hierarchical_number() == number)
+        });
+        tree.rows.select(position);
+    }
+
+    /// Collapses (or expands) the subtest selected in the tree view
+    fn collapse_selected(&mut self, collapse: bool) {
+        let Some(tree) = &mut self.tree else {
+            return;
+        };
+        let Some(row) = tree.rows.selected().filter(|r| r.subtest) else {
+            return;
+        };
+
+        let number = self.tests[row.index].hierarchical_number();
+        tree.collapsed.insert(number.clone(), collapse);
+        self.refresh_tree(Some(number));
+    }
+
+    /// Expands the subtests up to `depth` levels deep and collapses the deeper ones
+    fn expand_to_depth(&mut self, depth: usize) {
+        let Some(tree) = &mut self.tree else {
+            return;
+        };
+
+        for (index, test) in self.tests.iter().enumerate() {
+            if tree::is_subtest(&self.tests, index) {
+                tree.collapsed
+                    .insert(test.hierarchical_number(), test.parents.len() >= depth);
+            }
+        }
+        let selected = self.tree_selection();
+        self.refresh_tree(selected);
+    }
+
+    /// Handles the keys specific to the tree view, returns whether the key was handled
+    fn handle_tree_key(&mut self, code: KeyCode) -> anyhow::Result<bool> {
+        let Some(tree) = &mut self.tree else {
+            return Ok(false);
+        };
+
+        match code {
+            KeyCode::Up | KeyCode::Down if tree.rows.items().is_empty() => (),
+            KeyCode::Up => tree.rows.previous(),
+            KeyCode::Down => tree.rows.next(),
+            KeyCode::Left => self.collapse_selected(true),
+            KeyCode::Right => self.collapse_selected(false),
+            KeyCode::Char('E') => self.expand_to_depth(usize::MAX),
+            KeyCode::Char('C') => self.expand_to_depth(0),
+            KeyCode::Char(c @ '1'..='9') => self.expand_to_depth(c as usize - '0' as usize),
+            KeyCode::Esc => tree.rows.unselect(),
+            KeyCode::Enter | KeyCode::Char('x') => self.rerun_subtree()?,
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Sets the title of the terminal, when in the TUI
+    fn set_title(&mut self, title: String) {
+        if self.title.as_ref().is_some_and(|t| *t != title) {
+            // The title is purely informative, failing to set it is not an issue
+            let _ = crossterm::execute!(std::io::stdout(), SetTitle(&title));
+            self.title = Some(title);
+        }
+    }
+
+    /// Sets the title of the terminal to the status of the last run
+    fn update_title(&mut self) {
+        let status = if self.build_running() || self.rerun.is_some() || self.test_run.is_some() {
+            "running…".to_string()
+        } else if !self.could_run {
+            "error".to_string()
+        } else if self.failure.items().is_empty() {
+            "all passing".to_string()
+        } else {
+            format!("{} failing", self.failure.items().len())
+        };
+        self.set_title(format!("tap_runner — {status}"));
+    }
+
+    /// Draws the image requested by the last frame, removing the previous one
+    fn update_image<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> anyhow::Result<()> {
+        let Some(protocol) = self.graphics else {
+            return Ok(());
+        };
+        let mut out = std::io::stdout();
+        if self.shown_image.take().is_some() {
+            image::clear(&mut out, protocol)?;
+            // Redraws the cells the previous image was drawn over
+            terminal.clear()?;
+            terminal.draw(|f| self.draw(f))?;
+        }
+        if let Some(placement) = &self.image {
+            if let Err(e) = image::show(&mut out, protocol, placement) {
+                self.toasts.push(Toast::warning(format!(
+                    "Could not display {}: {e:#}",
+                    placement.path.display()
+                )));
+            }
+            // Not drawn again on each frame, even if it failed
+            self.shown_image = self.image.clone();
+        }
+        Ok(())
+    }
+
+    /// Handles a key press typed by the user, recording or replaying the macros, and returns
+    /// whether tap_runner should exit
+    fn press<B: Backend>(
+        &mut self,
+        code: KeyCode,
+        terminal: &mut Terminal<B>,
+    ) -> anyhow::Result<bool> {
+        let typing = self.search.as_ref().is_some_and(|s| s.editing) || self.filter_edit.is_some();
+        // The text fields and the registers of the macros read the keys as typed
+        let code = match typing || self.macros.pending() {
+            true => code,
+            false => self.keymap.translate(code),
+        };
+        // The tree view uses the digits to expand the subtests
+        match self.macros.press(code, typing, self.tree.is_none()) {
+            macros::Press::Key => return self.handle_key(code, terminal),
+            macros::Press::Pending => (),
+            macros::Press::Recording(register) => self
+                .toasts
+                .push(Toast::info(format!("Recording macro @{register}"))),
+            macros::Press::Recorded(register, len) => self.toasts.push(Toast::info(format!(
+                "Recorded {len} keys in macro @{register}"
+            ))),
+            macros::Press::Empty(register) => self
+                .toasts
+                .push(Toast::error(format!("Macro @{register} is empty"))),
+            macros::Press::Replay {
+                register,
+                keys,
+                count,
+            } => {
+                for _ in 0..count {
+                    for &key in &keys {
+                        if self.handle_key(key, terminal)? {
+                            return Ok(true);
+                        }
+                    }
+                }
+                self.toasts.push(Toast::info(match count {
+                    1 => format!("Replayed macro @{register}"),
+                    _ => format!("Replayed macro @{register} {count} times"),
+                }));
+            }
+        }
+        Ok(false)
+    }
+
+    /// Handles a key press, returning whether tap_runner should exit
+    fn handle_key<B: Backend>(
+        &mut self,
+        code: KeyCode,
+        terminal: &mut Terminal<B>,
+    ) -> anyhow::Result<bool> {
+        if let Some(scroll) = &mut self.help {
+            match code {
+                KeyCode::Up => *scroll = scroll.saturating_sub(1),
+                KeyCode::Down => *scroll = scroll.saturating_add(1),
+                KeyCode::PageUp => *scroll = scroll.saturating_sub(20),
+                KeyCode::PageDown => *scroll = scroll.saturating_add(20),
+                KeyCode::Esc | KeyCode::Char('q' | '?') => self.help = None,
+                _ => (),
+            }
+            return Ok(false);
+        }
+
+        if let Some(picker) = &mut self.profile_picker {
+            match code {
+                KeyCode::Char('q') => return Ok(true),
+                KeyCode::Up => picker.previous(),
+                KeyCode::Down => picker.next(),
+                KeyCode::Esc if self.profile.is_none() => return Ok(true),
+                KeyCode::Esc => self.profile_picker = None,
+                KeyCode::Enter => {
+                    let name = picker.selected().cloned();
+                    self.profile_picker = None;
+                    match self.select_profile(name) {
+                        Ok(()) => {
+                            if let Err(e) = self.run_tests() {
+                                self.toasts.push(Toast::error(e));
+                            }
+                        }
+                        Err(e) => {
+                            self.toasts.push(Toast::error(e));
+                            self.open_profile_picker();
+                        }
+                    }
+                }
+                _ => (),
+            }
+            return Ok(false);
+        }
+
+        if let Some(picker) = &mut self.task_picker {
+            match code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(true),
+                KeyCode::Up => picker.previous(),
+                KeyCode::Down => picker.next(),
+                KeyCode::Char('b') => {
+                    let build = picker.selected().map(|t| t.command());
+                    self.cli.build_command = match self.cli.build_command == build {
+                        true => None,
+                        false => build,
+                    };
+                }
+                KeyCode::Enter => {
+                    self.cli.run_command = picker.selected().map(|t| t.command());
+                    match self.select_profile(None) {
+                        Ok(()) => {
+                            self.task_picker = None;
+                            if let Err(e) = self.run_tests() {
+                                self.toasts.push(Toast::error(e));
+                            }
+                        }
+                        Err(e) => self.toasts.push(Toast::error(e)),
+                    }
+                }
+                _ => (),
+            }
+            return Ok(false);
+        }
+
+        if let Some(menu) = &mut self.bulk_menu {
+            match code {
+                KeyCode::Up => menu.previous(),
+                KeyCode::Down => menu.next(),
+                KeyCode::Esc | KeyCode::Char('q') => self.bulk_menu = None,
+                KeyCode::Enter => {
+                    let action = menu.selected().copied();
+                    self.bulk_menu = None;
+                    if let Some(action) = action {
+                        if let Err(e) = self.apply_bulk_action(action, terminal) {
+                            self.toasts.push(Toast::error(format!("{e:#}")));
+                        }
+                    }
+                }
+                _ => (),
+            }
+            return Ok(false);
+        }
+
+        if let Some(explanation) = &mut self.explanation {
+            match code {
+                KeyCode::Up => explanation.scroll = explanation.scroll.saturating_sub(1),
+                KeyCode::Down => explanation.scroll = explanation.scroll.saturating_add(1),
+                KeyCode::PageUp => explanation.scroll = explanation.scroll.saturating_sub(20),
+                KeyCode::PageDown => explanation.scroll = explanation.scroll.saturating_add(20),
+                KeyCode::Esc | KeyCode::Char('q') => self.explanation = None,
+                _ => (),
+            }
+            return Ok(false);
+        }
+
+        if let Some(view) = &mut self.artifact_view {
+            match code {
+                KeyCode::Up => view.scroll = view.scroll.saturating_sub(1),
+                KeyCode::Down => view.scroll = view.scroll.saturating_add(1),
+                KeyCode::PageUp => view.scroll = view.scroll.saturating_sub(20),
+                KeyCode::PageDown => view.scroll = view.scroll.saturating_add(20),
+                KeyCode::Char('o') => {
+                    let name = view.name.clone();
+                    if let Err(e) = self.open_artifact(&name) {
+                        self.toasts.push(Toast::error(e));
+                    }
+                }
+                KeyCode::Esc | KeyCode::Char('q') => self.artifact_view = None,
+                _ => (),
+            }
+            return Ok(false);
+        }
+
+        if let Some(picker) = &mut self.tag_picker {
+            match code {
+                KeyCode::Up => picker.previous(),
+                KeyCode::Down => picker.next(),
+                KeyCode::Esc | KeyCode::Char('q') => self.tag_picker = None,
+                KeyCode::Enter => {
+                    let tag = picker.selected().and_then(|e| e.tag.clone());
+                    self.tag_picker = None;
+                    self.select_tag(tag);
+                }
+                _ => (),
+            }
+            return Ok(false);
+        }
+
+        if let Some(picker) = &mut self.artifact_picker {
+            match code {
+                KeyCode::Up => picker.previous(),
+                KeyCode::Down => picker.next(),
+                KeyCode::Esc | KeyCode::Char('q') => self.artifact_picker = None,
+                KeyCode::Enter | KeyCode::Char('o') => {
+                    if let Some(artifact) = picker.selected().cloned() {
+                        let result = match code {
+                            KeyCode::Enter => self.show_artifact(&artifact),
+                            _ => self.open_artifact(&artifact),
+                        };
+                        if let Err(e) = result {
+                            self.toasts.push(Toast::error(format!("{e:#}")));
+                        }
+                    }
+                }
+                _ => (),
+            }
+            return Ok(false);
+        }
+
+        if self.search.as_ref().is_some_and(|s| s.editing) {
+            self.handle_search_key(code);
+            return Ok(false);
+        }
+
+        if self.filter_edit.is_some() {
+            self.handle_filter_edit_key(code);
+            return Ok(false);
+        }
+
+        if self.past_run.is_some()
+            && matches!(code, KeyCode::Enter | KeyCode::Char('x' | 'f' | 'A'))
+        {
+            self.toasts.push(Toast::error(
+                "Past runs can't be rerun, `]` goes back to the last run",
+            ));
+            return Ok(false);
+        }
+
+        if self.build_job.is_none() {
+            match self.handle_bench_key(code) {
+                Ok(true) => return Ok(false),
+                Ok(false) => (),
+                Err(e) => {
+                    self.toasts.push(Toast::error(format!("{e:#}")));
+                    return Ok(false);
+                }
+            }
+            if self.handle_diff_key(code)
+                || self.handle_raw_key(code)
+                || self.handle_cluster_key(code)
+                || self.handle_timeline_key(code)
+                || self.handle_stderr_key(code)
+            {
+                return Ok(false);
+            }
+            match self.handle_tree_key(code) {
+                Ok(true) => return Ok(false),
+                Ok(false) => (),
+                Err(e) => {
+                    self.toasts.push(Toast::error(e));
+                    return Ok(false);
+                }
+            }
+        }
+
+        match code {
+            KeyCode::Char('q') => return Ok(true),
+            KeyCode::Char('T') => self.toggle_tree(),
+            KeyCode::Char('M') => self.toggle_bench(),
+            KeyCode::Char('K') => self.toggle_clusters(),
+            KeyCode::Char('I') => self.toggle_timeline(),
+            KeyCode::Char('S') => self.toggle_stderr(),
+            KeyCode::Char('D') => self.toggle_diff(),
+            KeyCode::Char('V') => {
+                if let Err(e) = self.toggle_raw() {
+                    self.toasts.push(Toast::error(e));
+                }
+            }
+            KeyCode::Char('m') => self.toggle_mark(),
+            KeyCode::Char('Y') => {
+                let selected = self.failure.selected_index();
+                if let Err(e) = self.accept(selected.as_slice()) {
+                    self.toasts.push(Toast::error(e));
+                }
+            }
+            KeyCode::Char('w') => self.toggle_wrap(),
+            KeyCode::Char('s') => {
+                if let Err(e) = self.toggle_new_first() {
+                    self.toasts.push(Toast::error(e));
+                }
+            }
+            KeyCode::Char('X') => {
+                if let Err(e) = self.export_transcript() {
+                    self.toasts.push(Toast::error(e));
+                }
+            }
+            KeyCode::Char(c @ ('[' | ']')) => {
+                if let Err(e) = self.step_history(c == '[') {
+                    self.toasts.push(Toast::error(e));
+                }
+            }
+            KeyCode::Char('J') => {
+                if let Err(e) = self.export_report() {
+                    self.toasts.push(Toast::error(e));
+                }
+            }
+            KeyCode::Char('R') => {
+                let reapplied = self
+                    .select_profile(self.profile.clone())
+                    .and_then(|()| self.reapply());
+                match reapplied {
+                    Ok(()) => self.toasts.push(Toast::info(
+                        "The results were processed again with the current settings",
+                    )),
+                    Err(e) => self.toasts.push(Toast::error(format!("{e:#}"))),
+                }
+            }
+            KeyCode::Char('L') => {
+                self.filter_edit = Some(FilterEdit::new(self.location_filter_text.clone()));
+            }
+            KeyCode::Char('/') => {
+                let all = self.search.as_ref().is_some_and(|s| s.all);
+                self.search = Some(Search::new(all));
+            }
+            KeyCode::Char('n') if self.search.is_some() => self.jump_to_match(true),
+            KeyCode::Char('N') if self.search.is_some() => self.jump_to_match(false),
+            KeyCode::Char('c') => self.compact = !self.compact,
+            KeyCode::Char('?') => self.help = Some(0),
+            KeyCode::Char('o') => self.vertical_preview = !self.vertical_preview,
+            KeyCode::Char('z') => self.zoomed = !self.zoomed,
+            KeyCode::Char('F') => {
+                if let Err(e) = self.toggle_snapshot() {
+                    self.toasts.push(Toast::error(e));
+                }
+            }
+            KeyCode::Char('<') => self.resize_preview(5),
+            KeyCode::Char('>') => self.resize_preview(-5),
+            KeyCode::Char('e') => {
+                if let Err(e) = self.edit_selected(terminal) {
+                    self.toasts.push(Toast::error(e));
+                }
+            }
+            KeyCode::Char('y') => {
+                if let Err(e) = self.copy_selected() {
+                    self.toasts.push(Toast::error(e));
+                }
+            }
+            KeyCode::Char('i') => {
+                if let Err(e) = self.explain_selected() {
+                    self.toasts.push(Toast::error(e));
+                }
+            }
+            KeyCode::Char('a') => {
+                if let Err(e) = self.open_artifact_picker() {
+                    self.toasts.push(Toast::error(e));
+                }
+            }
+            KeyCode::Char('#') => {
+                if let Err(e) = self.open_tag_picker() {
+                    self.toasts.push(Toast::error(e));
+                }
+            }
+            KeyCode::Char('A') => {
+                if let Err(e) = self.open_bulk_menu() {
+                    self.toasts.push(Toast::error(e));
+                }
+            }
+            KeyCode::Char('P') if !self.config.profile.is_empty() => self.open_profile_picker(),
+            KeyCode::Char('r' | 't' | 'b' | 'B') if self.build_running() => {
+                self.toasts.push(Toast::error("A build is already running"));
+            }
+            KeyCode::Char('r' | 't' | 'B') if self.test_run.is_some() => {
+                self.toasts
+                    .push(Toast::error("The tests are already running"));
+            }
+            KeyCode::Char('r') => {
+                self.build_job = None;
+                if let Err(e) = self.run_tests() {
+                    self.toasts.push(Toast::error(e));
+                }
+            }
+            KeyCode::Char('t') => {
+                self.build_job = None;
+                if let Err(e) = self.run_test_command() {
+                    self.toasts.push(Toast::error(e));
+                }
+            }
+            KeyCode::Char('b') => {
+                if let Err(e) = self.start_build() {
+                    self.toasts.push(Toast::error(e));
+                }
+            }
+            KeyCode::Char('O') => self.show_last_build(),
+            KeyCode::Char('B') => {
+                self.build_job = None;
+                if let Err(e) = self.rebuild_and_run_tests() {
+                    self.toasts.push(Toast::error(e));
+                }
+            }
+            KeyCode::Enter | KeyCode::Char('x') => {
+                if let Err(e) = self.rerun_selected() {
+                    self.toasts.push(Toast::error(e));
+                }
+            }
+            KeyCode::Char('f') => {
+                if let Err(e) = self.start_rerun(RerunTarget::Failures) {
+                    self.toasts.push(Toast::error(e));
+                }
+            }
+            KeyCode::Tab => self.cycle_focus(true),
+            KeyCode::BackTab => self.cycle_focus(false),
+            KeyCode::Up if self.focus() != Focus::Failures => self.scroll(-1),
+            KeyCode::Down if self.focus() != Focus::Failures => self.scroll(1),
+            KeyCode::Up => {
+                self.failure.previous();
+                self.preview_scroll = 0;
+            }
+            KeyCode::Down => {
+                self.failure.next();
+                self.preview_scroll = 0;
+            }
+            // The tests waiting for the build would never start
+            KeyCode::Esc if self.build_job.is_some() && self.pending_tests.is_none() => {
+                self.build_job = None
+            }
+            KeyCode::Esc => self.failure.unselect(),
+            _ => (),
+        }
+
+        Ok(false)
+    }
+
+    /// Selects the item clicked in the list shown on top, or moves its selection with the wheel
+    fn handle_mouse(&mut self, event: MouseEvent) {
+        // The popups without lists take all the events, like the keys
+        if self.help.is_some() || self.explanation.is_some() || self.artifact_view.is_some() {
+            return;
+        }
+        if let Some(picker) = &mut self.profile_picker {
+            picker.mouse(event);
+        } else if let Some(picker) = &mut self.task_picker {
+            picker.mouse(event);
+        } else if let Some(menu) = &mut self.bulk_menu {
+            menu.mouse(event);
+        } else if let Some(picker) = &mut self.tag_picker {
+            picker.mouse(event);
+        } else if let Some(picker) = &mut self.artifact_picker {
+            picker.mouse(event);
+        } else if let Some(tree) = self.tree.as_mut().filter(|_| self.build_job.is_none()) {
+            tree.rows.mouse(event);
+        } else if let Some(view) = self.clusters.as_mut().filter(|_| self.build_job.is_none()) {
+            view.clusters.mouse(event);
+        } else if self.focusable(Focus::Skipped) && self.skipped_list.mouse(event) {
+            self.focus = Focus::Skipped;
+        } else if self.focusable(Focus::Todo) && self.todo_list.mouse(event) {
+            self.focus = Focus::Todo;
+        } else if self.failure.mouse(event) {
+            self.focus = Focus::Failures;
+            self.preview_scroll = 0;
+        }
+    }
+
+    fn run<B: Backend>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+        tick_rate: Duration,
+    ) -> anyhow::Result<()> {
+        self.title = Some(String::new());
+        let mut last_tick = Instant::now();
+        loop {
+            self.update_title();
+            let frame = terminal.draw(|f| self.draw(f))?;
+            if let Some(mirror) = &mut self.mirror {
+                mirror.send(frame.buffer);
+            }
+            if self.image != self.shown_image {
+                self.update_image(terminal)?;
+            }
+
+            if self.pending_run {
+                self.pending_run = false;
+                if let Err(e) = self.run_tests() {
+                    self.toasts.push(Toast::error(e));
+                }
+                continue;
+            }
+
+            let timeout = tick_rate
+                .checked_sub(last_tick.elapsed())
+                .unwrap_or(Duration::from_secs(0));
+            if crossterm::event::poll(timeout)? {
+                match crossterm::event::read()? {
+                    Event::Key(key) if self.press(key.code, terminal)? => return Ok(()),
+                    Event::Mouse(event) => self.handle_mouse(event),
+                    _ => (),
+                }
+            }
+
+            if let Err(e) = self.poll_build() {
+                self.toasts.push(Toast::error(e));
+            }
+            if let Err(e) = self.poll_test_run() {
+                self.toasts.push(Toast::error(format!("{e:#}")));
+            }
+            if let Err(e) = self.poll_rerun() {
+                self.toasts.push(Toast::error(format!("{e:#}")));
+            }
+            if let Some(Err(e)) = self.explanation.as_mut().and_then(|e| e.job.poll()) {
+                self.toasts.push(Toast::error(format!(
+                    "Could not run the explain command: {e}"
+                )));
+            }
+            self.poll_plugins();
+
+            if last_tick.elapsed() >= tick_rate {
+                last_tick = Instant::now();
+
+                if let Err(e) = self.save_session() {
+                    self.toasts
+                        .push(Toast::warning(format!("Could not save session: {e:#}")));
+                }
+
+                if let Err(e) = self.reload_config() {
+                    self.toasts.push(Toast::error(format!("{e:#}")));
+                }
+
+                self.toasts.expire();
+            }
+        }
+    }
+
+    /// Describes which of the `conditions` were met by the last run
+    fn failure_reasons(&self, conditions: &[FailOn]) -> Vec<(FailOn, String)> {
+        let count = |f: fn(&Test) -> bool| self.tests.iter().filter(|t| f(t)).count();
+        let counted = |count: usize, what: &str| (count != 0).then(|| format!("{count} {what}"));
+
+        conditions
+            .iter()
+            .filter_map(|&condition| {
+                let reason = match condition {
+                    FailOn::Fail => counted(
+                        self.failure.items().iter().filter(|f| !f.expected).count(),
+                        "failed tests",
+                    ),
+                    FailOn::ParseError => {
+                        counted(count(|t| !t.warnings.is_empty()), "tests with warnings")
+                    }
+                    FailOn::TodoPass => counted(
+                        count(|t| t.status() == TestResult::Bonus),
+                        "passing TODO tests",
+                    ),
+                    FailOn::Skip => counted(self.skipped.len(), "skipped tests"),
+                    FailOn::PlanMismatch => self.plan_mismatches.first().cloned(),
+                    FailOn::ExitStatus => self
+                        .failed_status
+                        .map(|status| format!("the test command exited with {status}")),
+                    FailOn::SkipAll => counted(self.skipped_suites.len(), "skipped suites"),
+                };
+                reason.map(|r| (condition, r))
+            })
+            .collect()
+    }
+
+    fn print_summary(&self, out: &mut impl Write, level: OutputLevel) -> std::io::Result<()> {
+        if level == OutputLevel::Quiet {
+            return Ok(());
+        }
+
+        writeln!(out, "{}", self.summary())?;
+        if let Some(commit) = &self.commit {
+            writeln!(out, "Commit: {commit}")?;
+        }
+        if let Some(seed) = self.seed {
+            writeln!(out, "Seed: {seed}")?;
+        }
+
+        if level == OutputLevel::Summary || !self.statuses.contains(&TestResult::Fail) {
+            return Ok(());
+        }
+
+        let (expected, failures): (Vec<_>, Vec<_>) =
+            self.failure.items().iter().partition(|f| f.expected);
+        if !failures.is_empty() {
+            writeln!(out, "Failures:")?;
+        }
+        for failure in failures {
+            if failure.rerun.is_some_and(|r| r != TestResult::Fail) {
+                continue;
+            }
+            self.tests[failure.index].write(out, "  ", level == OutputLevel::Verbose)?;
+        }
+        if !expected.is_empty() {
+            writeln!(out, "Expected failures:")?;
+            for failure in expected {
+                writeln!(
+                    out,
+                    "  {}",
+                    describe(&failure.number, failure.desc.as_deref())
+                )?;
+            }
+        }
+        let partial = self
+            .failure
+            .items()
+            .iter()
+            .filter(|f| !f.only_under.is_empty())
+            .collect_vec();
+        if !partial.is_empty() {
+            writeln!(out, "Failing under only some interpreters:")?;
+            for failure in partial {
+                writeln!(
+                    out,
+                    "  {} (under {})",
+                    describe(&failure.number, failure.desc.as_deref()),
+                    failure.only_under.join(", ")
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn draw<B: Backend>(&mut self, f: &mut Frame<B>) {
+        self.image = None;
+        self.draw_results(f);
+        self.draw_profile_picker(f);
+        self.draw_task_picker(f);
+        if let Some(menu) = &mut self.bulk_menu {
+            draw_popup(f, &self.theme, "Marked failures", menu, |action| {
+                ListItem::new(action.label())
+            });
+        }
+        if let Some(picker) = &mut self.tag_picker {
+            draw_popup(f, &self.theme, "Tags", picker, |entry| {
+                ListItem::new(entry.label())
+            });
+        }
+        if let Some(picker) = &mut self.artifact_picker {
+            draw_popup(
+                f,
+                &self.theme,
+                "Artifacts (enter: preview, o: open)",
+                picker,
+                |artifact| ListItem::new(artifact.as_str()),
+            );
+        }
+        if let Some(view) = &self.artifact_view {
+            let area = centered_rect(80, 80, f.size());
+            f.render_widget(Clear, area);
+            let block = Block::default()
+                .title(format!("{} (o: open, esc: close)", view.name))
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded);
+            let inner = block.inner(area);
+            let image = view
+                .image
+                .clone()
+                .filter(|path| self.graphics.is_some_and(|g| g.supports(path)));
+            match image {
+                Some(path) => {
+                    f.render_widget(block, area);
+                    self.image = Some(image::Placement { path, area: inner });
+                }
+                None => {
+                    let p = Paragraph::new(view.text.clone())
+                        .scroll((view.scroll, 0))
+                        .wrap(Wrap { trim: false })
+                        .block(block);
+                    f.render_widget(p, area);
+                }
+            }
+        }
+        if let Some(explanation) = &self.explanation {
+            let state = match explanation.job.success {
+                None => " (running)",
+                Some(true) => "",
+                Some(false) => " (failed)",
+            };
+            let text =
+                explanation.job.output.into_text().unwrap_or_else(|_| {
+                    Text::raw(String::from_utf8_lossy(&explanation.job.output))
+                });
+            let area = centered_rect(80, 80, f.size());
+            f.render_widget(Clear, area);
+            let p = Paragraph::new(text)
+                .scroll((explanation.scroll, 0))
+                .wrap(Wrap { trim: false })
+                .block(
+                    Block::default()
+                        .title(format!("{}{state} (esc: close)", explanation.test))
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded),
+                );
+            f.render_widget(p, area);
+        }
+        if let Some(scroll) = self.help {
+            let area = centered_rect(80, 80, f.size());
+            f.render_widget(Clear, area);
+            let p = Paragraph::new(help::lines(&self.keymap))
+                .scroll((scroll, 0))
+                .block(
+                    Block::default()
+                        .title("Help (esc: close)")
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded),
+                );
+            f.render_widget(p, area);
+        }
+        if self.could_run || self.build_job.is_some() {
+            let area = f.size().inner(&Margin {
+                vertical: 1,
+                horizontal: 1,
+            });
+            self.toasts.render(f, area);
+        }
+        if !self.color {
+            f.render_widget(widgets::Monochrome, f.size());
+        }
+    }
+
+    fn draw_results<B: Backend>(&mut self, f: &mut Frame<B>) {
+        let size = f.size();
+        let mut title = vec![Span::raw("TAP Runner")];
+        if self.stale {
+            title.push(Span::styled(
+                " [stale]",
+                Style::default()
+                    .fg(Color::DarkGray)
+                    .add_modifier(Modifier::ITALIC),
+            ));
+        }
+        if self.zoomed {
+            title.push(Span::styled(
+                " [zoomed]",
+                Style::default()
+                    .fg(Color::DarkGray)
+                    .add_modifier(Modifier::ITALIC),
+            ));
+        }
+        if self.test_run.as_ref().is_some_and(|run| run.waiting) {
+            title.push(Span::styled(
+                " waiting for the next document",
+                Style::default()
+                    .fg(Color::DarkGray)
+                    .add_modifier(Modifier::ITALIC),
+            ));
+        } else if let Some(run) = &self.test_run {
+            let elapsed = run.worker.started_at.elapsed();
+            let frame = SPINNER[(elapsed.as_millis() / 100) as usize % SPINNER.len()];
+            title.push(Span::styled(
+                format!(" {frame} running ({}s)", elapsed.as_secs()),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        if let Some(tag) = &self.tag {
+            title.push(Span::styled(
+                format!(" [#{tag}]"),
+                Style::default()
+                    .fg(Color::Blue)
+                    .add_modifier(Modifier::ITALIC),
+            ));
+        }
+        if let Some(past_run) = &self.past_run {
+            let run = &past_run.runs[past_run.position];
+            let commit = match &run.commit {
+                Some(commit) if run.commit != self.commit => format!(", ⚠ commit {commit}"),
+                Some(commit) => format!(", commit {commit}"),
+                None => String::new(),
+            };
+            let seed = match run.seed {
+                Some(seed) => format!(", seed {seed}"),
+                None => String::new(),
+            };
+            title.push(Span::styled(
+                format!(
+                    " [past run {}/{}, {}{commit}{seed}]",
+                    past_run.position + 1,
+                    past_run.runs.len(),
+                    transcript::timestamp(run.time)
+                ),
+                Style::default()
+                    .fg(Color::DarkGray)
+                    .add_modifier(Modifier::ITALIC),
+            ));
+        }
+        if let Some(register) = self.macros.recording() {
+            title.push(Span::styled(
+                format!(" [recording @{register}]"),
+                Style::default()
+                    .fg(Color::Red)
+                    .add_modifier(Modifier::ITALIC),
+            ));
+        }
+        let outer = Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded);
+        let mut inner = outer.inner(size);
+        f.render_widget(outer, size);
+
+        if (self.could_run || self.build_job.is_some()) && self.layout.hints() {
+            let areas = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(1)])
+                .split(inner);
+            inner = areas[0];
+            f.render_widget(
+                Paragraph::new(hints::line(self.hints(), &self.keymap)),
+                areas[1],
+            );
+        }
+
+        let order = self.layout.order();
+        let constraints = order
+            .iter()
+            .map(|pane| match pane {
+                Pane::Status | Pane::Skipped | Pane::Todo if self.zoomed => Constraint::Max(0),
+                Pane::Status => Constraint::Max(self.layout.status_height()),
+                Pane::Skipped if self.skipped_rows() == 0 => Constraint::Max(0),
+                Pane::Skipped => Constraint::Max(
+                    (2 + self.skipped_rows()).min(self.layout.skipped_height() as usize) as u16,
+                ),
+                Pane::Todo if self.todo_list.items().is_empty() => Constraint::Max(0),
+                Pane::Todo => Constraint::Max(
+                    (2 + self.todo_list.items().len()).min(self.layout.todo_height() as usize)
+                        as u16,
+                ),
+                Pane::Body => Constraint::Min(0),
+            })
+            .collect_vec();
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(constraints)
+            .split(inner);
+        let area = |pane| areas[order.iter().position(|p| *p == pane).unwrap()];
+        let chunks = [
+            area(Pane::Status),
+            area(Pane::Skipped),
+            area(Pane::Body),
+            area(Pane::Todo),
+        ];
+
+        if !self.could_run && self.build_job.is_none() {
+            if let Some(e) = self.toasts.last_error() {
+                let p = Paragraph::new(e.message.as_str())
+                    .block(Block::default().title("Error").borders(Borders::ALL))
+                    .wrap(Wrap { trim: true });
+                f.render_widget(p, inner);
+            }
+            return;
+        }
+
+        let matches = self.search_matches();
+        let mut chunks = chunks;
+        if let Some(edit) = &self.filter_edit {
+            let areas = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(2)])
+                .split(chunks[2]);
+            chunks[2] = areas[0];
+            let status = match (&edit.error, &edit.warning) {
+                (Some(_), _) => Style::default().fg(Color::Red),
+                (None, Some(_)) => Style::default().fg(Color::Yellow),
+                (None, None) => Style::default().fg(Color::DarkGray),
+            };
+            f.render_widget(
+                Paragraph::new(vec![
+                    Spans::from(Span::styled(
+                        edit.prompt(),
+                        Style::default().fg(Color::Yellow),
+                    )),
+                    Spans::from(Span::styled(edit.status(), status)),
+                ]),
+                areas[1],
+            );
+        } else if let Some(search) = &self.search {
+            let areas = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(1)])
+                .split(chunks[2]);
+            chunks[2] = areas[0];
+            f.render_widget(
+                Paragraph::new(search.prompt(matches.len()))
+                    .style(Style::default().fg(Color::Yellow)),
+                areas[1],
+            );
+        }
+
+        let status = ColoredList::new(
+            self.statuses
+                .iter()
+                .map(|&status| self.theme.result(status))
+                .collect(),
+        )
+        .highlight(
+            (0..self.statuses.len())
+                .map(|i| matches.contains(&i))
+                .collect(),
+        )
+        .cells(self.layout.cell_width(), self.layout.cell_gap())
+        .glyph(self.layout.cell_glyph)
+        .symbols(match self.color {
+            true => Vec::new(),
+            false => self.statuses.iter().map(|&s| result_symbol(s)).collect(),
+        })
+        .per_row(self.layout.cells_per_row)
+        .block(
+            Block::default()
+                .title(match self.seed.filter(|_| self.past_run.is_none()) {
+                    Some(seed) => format!("Status (seed {seed})"),
+                    None => "Status".to_owned(),
+                })
+                .borders(Borders::ALL),
+        );
+        if !self.zoomed {
+            f.render_widget(status, chunks[0]);
+        }
+
+        if !self.zoomed && self.skipped_rows() != 0 {
+            // The position tells how many rows the pane hides
+            let title = match self.skipped_list.selected_index() {
+                Some(i) => format!("Skipped ({}/{})", i + 1, self.skipped_rows()),
+                None => format!("Skipped ({})", self.skipped_rows()),
+            };
+            let block = self.pane_block(Focus::Skipped, title);
+            let area = block.inner(chunks[1]);
+            f.render_widget(block, chunks[1]);
+            self.skipped_list
+                .set_focused(self.focus() == Focus::Skipped);
+            let (tests, suites, template) =
+                (&self.tests, &self.skipped_suites, &self.templates.skipped);
+            let (width, truncation) = (area.width as usize, self.layout.truncation());
+            let fit =
+                |row: Spans<'static>| ListItem::new(widgets::truncate(row, width, truncation));
+            self.skipped_list
+                .render(f, area, &self.theme, |&row| match row {
+                    SkippedRow::Suite(index) => {
+                        let suite = &suites[index];
+                        let name = match &suite.name {
+                            Some(name) => format!("Subtest `{name}` skipped entirely"),
+                            None => "All the tests skipped".to_owned(),
+                        };
+                        let row = match &suite.reason {
+                            Some(reason) => format!("{name} ({reason})"),
+                            None => name,
+                        };
+                        ListItem::new(Span::styled(
+                            row,
+                            Style::default().add_modifier(Modifier::ITALIC),
+                        ))
+                    }
+                    SkippedRow::Test(index) => {
+                        let test = &tests[index];
+                        if let Some(template) = template {
+                            return fit(Spans::from(template.render(test)));
+                        }
+                        let row = describe(&test.hierarchical_number(), test.desc.as_deref());
+                        match test.directive.as_ref().and_then(|d| d.reason.as_ref()) {
+                            None => fit(Spans::from(row)),
+                            Some(r) => fit(Spans::from(format!("{row} ({r})"))),
+                        }
+                    }
+                });
+        }
+
+        if !self.zoomed && !self.todo_list.items().is_empty() {
+            let count = self.todo_list.items().len();
+            let title = match self.todo_list.selected_index() {
+                Some(i) => format!("TODO ({}/{count})", i + 1),
+                None => format!("TODO ({count})"),
+            };
+            let block = self.pane_block(Focus::Todo, title);
+            let area = block.inner(chunks[3]);
+            f.render_widget(block, chunks[3]);
+            self.todo_list.set_focused(self.focus() == Focus::Todo);
+            let (tests, theme) = (&self.tests, &self.theme);
+            let (width, truncation) = (area.width as usize, self.layout.truncation());
+            self.todo_list.render(f, area, theme, |&index| {
+                let test = &tests[index];
+                // Passing `TODO` tests may be ready to lose their directive
+                let (mark, status) = match test.status() {
+                    TestResult::Bonus => ("★ ", " passing"),
+                    _ => ("◌ ", ""),
+                };
+                let mut row = describe(&test.hierarchical_number(), test.desc.as_deref());
+                if let Some(reason) = test.directive.as_ref().and_then(|d| d.reason.as_ref()) {
+                    row.push_str(&format!(" ({reason})"));
+                }
+                let row = Spans::from(vec![
+                    Span::styled(mark, Style::default().fg(theme.result(test.status()))),
+                    Span::raw(row),
+                    Span::styled(status, Style::default().fg(Color::DarkGray)),
+                ]);
+                ListItem::new(widgets::truncate(row, width, truncation))
+            });
+        }
+
+        if let Some((job, _)) = &self.build_job {
+            let title = match job.success {
+                None => "Build (running)",
+                Some(true) => "Build (succeeded)",
+                Some(false) => "Build (failed)",
+            };
+            let block = self.pane_block(Focus::Build, title);
+            let height = block.inner(chunks[2]).height as usize;
+            // The compilers color their diagnostics when they are forced to
+            let mut lines = match job.output.into_text() {
+                Ok(text) => text.lines,
+                Err(_) => Text::raw(String::from_utf8_lossy(&job.output).into_owned()).lines,
+            };
+            let end = lines
+                .len()
+                .saturating_sub(self.build_scroll)
+                .max(height.min(lines.len()));
+            lines.truncate(end);
+            let p = Paragraph::new(lines.split_off(end.saturating_sub(height))).block(block);
+            f.render_widget(p, chunks[2]);
+            return;
+        }
+
+        if let Some(snapshot) = &self.snapshot {
+            let areas = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .split(chunks[2]);
+            chunks[2] = areas[0];
+            self.draw_snapshot(f, snapshot, areas[1]);
+        }
+
+        let spinner = self.rerun.as_ref().map(|(target, job)| {
+            let frame = job.started_at.elapsed().as_millis() / 100;
+            (*target, SPINNER[frame as usize % SPINNER.len()])
+        });
+        let match_style = Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD);
+
+        if let Some(view) = &self.diff {
+            let hint = match (&self.previous_tap, view.lines.is_empty()) {
+                (None, _) => Some("No previous run to compare to"),
+                (Some(_), true) => Some("The output did not change since the previous run"),
+                (Some(_), false) => None,
+            };
+            let lines = view.lines[view.scroll.min(view.lines.len())..]
+                .iter()
+                .map(|line| {
+                    let style = match line.kind {
+                        diff::Kind::Hunk => Style::default().fg(Color::Cyan),
+                        diff::Kind::Context => Style::default(),
+                        diff::Kind::Removed => Style::default().fg(Color::Red),
+                        diff::Kind::Added => Style::default().fg(Color::Green),
+                    };
+                    // Changed test points are highlighted, as they show which tests changed
+                    let test = line.text[1..].trim_start();
+                    let changed = matches!(line.kind, diff::Kind::Removed | diff::Kind::Added);
+                    let style =
+                        match changed && (test.starts_with("ok") || test.starts_with("not ok")) {
+                            true => style.add_modifier(Modifier::BOLD),
+                            false => style,
+                        };
+                    Spans::from(Span::styled(line.text.clone(), style))
+                })
+                .collect_vec();
+            let mut title = vec![Span::raw("Raw output changes since the previous run")];
+            if let (Some(previous), Some(current)) = (&self.previous_commit, &self.commit) {
+                if previous != current {
+                    title.push(Span::styled(
+                        format!(" ⚠ run on another commit: {previous} → {current}"),
+                        Style::default().fg(Color::Yellow),
+                    ));
+                }
+            }
+            let p = Paragraph::new(lines).block(
+                Block::default()
+                    .title(Spans::from(title))
+                    .borders(Borders::ALL),
+            );
+            f.render_widget(p, chunks[2]);
+            if let Some(hint) = hint {
+                f.render_widget(
+                    Paragraph::new(hint),
+                    chunks[2].inner(&Margin {
+                        vertical: 1,
+                        horizontal: 1,
+                    }),
+                );
+            }
+            return;
+        }
+
+        if let Some(view) = &self.raw {
+            let selected = self
+                .failure
+                .selected()
+                .and_then(|f| view.tests.get(&f.number))
+                .copied();
+            let width = view.lines.len().to_string().len();
+            let lines = view.lines[view.scroll.min(view.lines.len())..]
+                .iter()
+                .enumerate()
+                .map(|(i, line)| {
+                    let number = view.scroll + i;
+                    let style = match Some(number) == selected {
+                        true => Style::default().bg(self.theme.highlight),
+                        false => Style::default(),
+                    };
+                    Spans::from(vec![
+                        Span::styled(
+                            format!("{:>width$} ", number + 1),
+                            Style::default().fg(Color::DarkGray),
+                        ),
+                        Span::styled(line.clone(), style),
+                    ])
+                })
+                .collect_vec();
+            let title = match selected {
+                Some(line) => format!("Raw output, selected test at line {}", line + 1),
+                None => "Raw output".to_owned(),
+            };
+            let p =
+                Paragraph::new(lines).block(Block::default().title(title).borders(Borders::ALL));
+            f.render_widget(p, chunks[2]);
+            return;
+        }
+
+        if let Some(BenchView { baseline, state }) = &mut self.bench {
+            let descriptions: HashMap<_, _> = self
+                .tests
+                .iter()
+                .map(|t| (t.hierarchical_number(), t.desc.as_deref()))
+                .collect();
+            let format = |v: f64| format!("{v:.3}");
+            let rows = bench::compare(&bench::collect(&self.tests), baseline)
+                .into_iter()
+                .map(|c| {
+                    let (delta, color) = match (c.delta(), c.improved()) {
+                        (None, _) => ("new".to_string(), Color::Reset),
+                        (Some(d), Some(true)) => (format!("{d:+.1}%"), Color::Green),
+                        (Some(d), Some(false)) => (format!("{d:+.1}%"), Color::Red),
+                        (Some(d), None) => (format!("{d:+.1}%"), Color::Reset),
+                    };
+                    let desc = descriptions.get(&c.test).copied().flatten();
+                    Row::new(vec![
+                        Cell::from(describe(&c.test, desc)),
+                        Cell::from(c.key.clone()),
+                        Cell::from(c.baseline.map(format).unwrap_or_default()),
+                        Cell::from(format(c.current)),
+                        Cell::from(delta).style(Style::default().fg(color)),
+                    ])
+                })
+                .collect_vec();
+            let empty = rows.is_empty();
+            let table = Table::new(rows)
+                .header(
+                    Row::new(["Test", "Measure", "Baseline", "Current", "Change"])
+                        .style(Style::default().add_modifier(Modifier::BOLD)),
+                )
+                .block(
+                    Block::default()
+                        .title("Benchmarks (s: save as baseline)")
+                        .borders(Borders::ALL),
+                )
+                .widths(&[
+                    Constraint::Percentage(40),
+                    Constraint::Percentage(18),
+                    Constraint::Percentage(14),
+                    Constraint::Percentage(14),
+                    Constraint::Percentage(14),
+                ])
+                .highlight_style(Style::default().bg(self.theme.highlight));
+            f.render_stateful_widget(table, chunks[2], state);
+            if empty {
+                let hint = Paragraph::new("No test has timing or throughput diagnostics");
+                f.render_widget(
+                    hint,
+                    chunks[2].inner(&Margin {
+                        vertical: 2,
+                        horizontal: 1,
+                    }),
+                );
+            }
+            return;
+        }
+
+        if let Some(view) = &mut self.timeline {
+            let rows = self
+                .tests
+                .iter()
+                .filter(|t| t.parents.is_empty())
+                .filter_map(|t| Some((t, self.timings.get(&t.hierarchical_number())?)))
+                .collect_vec();
+            let total = rows.iter().map(|(_, span)| span.end).max();
+            let mut title = "Timeline".to_owned();
+            if let Some(total) = total {
+                title.push_str(&format!(
+                    " ({} top-level tests in {:.2}s)",
+                    rows.len(),
+                    total.as_secs_f64()
+                ));
+            }
+            let block = Block::default().title(title).borders(Borders::ALL);
+            let area = block.inner(chunks[2]);
+            f.render_widget(block, chunks[2]);
+
+            let total = total.map_or(0., |t| t.as_secs_f64()).max(f64::EPSILON);
+            let label_width = (area.width as usize * 2 / 5).min(50);
+            let bar_width = (area.width as usize).saturating_sub(label_width + 1);
+            let height = area.height as usize;
+            view.scroll = view.scroll.min(rows.len().saturating_sub(height));
+            let lines = rows
+                .iter()
+                .skip(view.scroll)
+                .take(height)
+                .map(|(test, span)| {
+                    let column = |at: Duration| at.as_secs_f64() / total * bar_width as f64;
+                    let from = (column(span.start) as usize).min(bar_width.saturating_sub(1));
+                    let to = (column(span.end).ceil() as usize).clamp(from + 1, bar_width.max(1));
+                    let label = format!(
+                        "{} ({:.2}s)",
+                        describe(&test.hierarchical_number(), test.desc.as_deref()),
+                        (span.end - span.start).as_secs_f64()
+                    );
+                    let label = widgets::truncate(
+                        Spans::from(label),
+                        label_width,
+                        self.layout.truncation(),
+                    );
+                    let padding = label_width.saturating_sub(label.width()) + 1;
+                    let mut line = label.0;
+                    line.push(Span::raw(" ".repeat(padding + from)));
+                    line.push(Span::styled(
+                        "█".repeat(to - from),
+                        Style::default().fg(self.theme.result(test.status())),
+                    ));
+                    Spans::from(line)
+                })
+                .collect_vec();
+            match self.timings.is_empty() {
+                true => f.render_widget(
+                    Paragraph::new(
+                        "No timings: the last run was not read as it ran, or its results are \
+                         the ones of a previous session",
+                    )
+                    .wrap(Wrap { trim: true }),
+                    area,
+                ),
+                false => f.render_widget(Paragraph::new(lines), area),
+            }
+            return;
+        }
+
+        if let Some(view) = &mut self.stderr_view {
+            let descriptions: HashMap<_, _> = self
+                .tests
+                .iter()
+                .map(|t| (t.hierarchical_number(), t.desc.as_deref()))
+                .collect();
+            // The lines following a same test point are grouped under it
+            let mut lines = Vec::new();
+            let mut previous = None;
+            for (number, line) in self.stderr.lines() {
+                if lines.is_empty() || number != previous {
+                    let header = match number {
+                        Some(number) => format!(
+                            "after {}",
+                            describe(number, descriptions.get(number).copied().flatten())
+                        ),
+                        None => "before the first test".to_owned(),
+                    };
+                    lines.push(Spans::from(Span::styled(
+                        format!("── {header}"),
+                        Style::default().fg(Color::DarkGray),
+                    )));
+                    previous = number;
+                }
+                lines.push(Spans::from(line.to_owned()));
+            }
+            let title = match self.past_run {
+                Some(_) => "Stderr of the last run, not of the past run shown".to_owned(),
+                None => format!("Stderr of the last run ({} lines)", self.stderr.len()),
+            };
+            let block = Block::default().title(title).borders(Borders::ALL);
+            let area = block.inner(chunks[2]);
+            f.render_widget(block, chunks[2]);
+
+            view.scroll = view
+                .scroll
+                .min(lines.len().saturating_sub(area.height as usize));
+            match lines.is_empty() {
+                true => f.render_widget(
+                    Paragraph::new("The run command wrote nothing on stderr"),
+                    area,
+                ),
+                false => f.render_widget(Paragraph::new(lines.split_off(view.scroll)), area),
+            }
+            return;
+        }
+
+        if let Some(ClusterView { clusters }) = &mut self.clusters {
+            let failures: HashMap<_, _> = self
+                .failure
+                .items()
+                .iter()
+                .map(|f| (f.number.as_str(), f))
+                .collect();
+            let block = Block::default()
+                .title(format!(
+                    "Failure clusters ({} failing tests in {} clusters, enter: go to the \
+                     example, m: mark the cluster)",
+                    failures.len(),
+                    clusters.items().len()
+                ))
+                .borders(Borders::ALL);
+            let area = block.inner(chunks[2]);
+            f.render_widget(block, chunks[2]);
+            clusters.render(f, area, &self.theme, |cluster| {
+                let example = failures[cluster[0].as_str()];
+                let message = example
+                    .message()
+                    .lines()
+                    .map(str::trim)
+                    .find(|l| !l.is_empty())
+                    .unwrap_or_default();
+                ListItem::new(vec![
+                    Spans::from(vec![
+                        Span::styled(
+                            format!("{:>4}× ", cluster.len()),
+                            Style::default().add_modifier(Modifier::BOLD),
+                        ),
+                        Span::raw(describe(&example.number, example.desc.as_deref())),
+                    ]),
+                    Spans::from(Span::styled(
+                        format!("      {message}"),
+                        Style::default().fg(Color::DarkGray),
+                    )),
+                ])
+            });
+            if clusters.items().is_empty() {
+                f.render_widget(Paragraph::new("No failing test"), area);
+            }
+            return;
+        }
+
+        if let Some(TreeView { rows, .. }) = &mut self.tree {
+            let tests = &self.tests;
+            let annotations = &self.annotations;
+            let partial_failures = &self.partial_failures;
+            let theme = &self.theme;
+            let passed = self.templates.passed.as_ref();
+            let (width, truncation) = (chunks[2].width as usize, self.layout.truncation());
+            rows.render(f, chunks[2], theme, |row| {
+                let test = &tests[row.index];
+                let mut line = vec![Span::raw("  ".repeat(row.depth))];
+                line.push(Span::raw(if !row.subtest {
+                    "  "
+                } else if row.collapsed {
+                    "▸ "
+                } else {
+                    "▾ "
+                }));
+                let icon = format!("{} ", result_symbol(test.status()));
+                line.push(Span::styled(
+                    icon,
+                    Style::default().fg(theme.result(test.status())),
+                ));
+                if let Some((_, frame)) =
+                    spinner.filter(|(t, _)| *t == RerunTarget::Subtree(row.index))
+                {
+                    line.push(Span::raw(format!("{frame} ")));
+                }
+                let style = match matches.contains(&row.index) {
+                    true => match_style,
+                    false => Style::default(),
+                };
+                let text = match passed {
+                    Some(template) if !row.subtest && test.status() == TestResult::Success => {
+                        template.render(test)
+                    }
+                    _ => describe(&test.hierarchical_number(), test.desc.as_deref()),
+                };
+                line.push(Span::styled(text, style));
+                if let Some(counts) = row.counts.filter(|_| row.collapsed) {
+                    let mut parts = vec![
+                        Span::styled(
+                            format!("{} fail", counts.failed),
+                            Style::default().fg(match counts.failed {
+                                0 => Color::Reset,
+                                _ => Color::Red,
+                            }),
+                        ),
+                        Span::raw(format!(" / {} pass", counts.passed)),
+                    ];
+                    if counts.skipped != 0 {
+                        parts.push(Span::raw(format!(" / {} skip", counts.skipped)));
+                    }
+                    if counts.todo != 0 {
+                        parts.push(Span::raw(format!(" / {} todo", counts.todo)));
+                    }
+                    line.push(Span::raw(" ("));
+                    line.extend(parts);
+                    line.push(Span::raw(")"));
+                }
+                if !test.warnings.is_empty() {
+                    line.push(Span::styled(" ⚠", Style::default().fg(Color::Yellow)));
+                }
+                if let Some(category) = &test.category {
+                    line.push(Span::styled(
+                        format!(" [{category}]"),
+                        Style::default().fg(Color::LightMagenta),
+                    ));
+                }
+                line.extend(test.tags.iter().map(tag_span));
+                line.extend(partial_failures.get(&row.index).map(|i| only_under_span(i)));
+                for note in annotations
+                    .get(&test.hierarchical_number())
+                    .into_iter()
+                    .flatten()
+                {
+                    line.push(Span::styled(
+                        format!("  » {note}"),
+                        Style::default().fg(Color::Cyan),
+                    ));
+                }
+                ListItem::new(widgets::truncate(Spans::from(line), width, truncation))
+            });
+            return;
+        }
+
+        // The tests completed so far are only summarized once the run finishes
+        if self.test_run.as_ref().is_some_and(|run| !run.waiting) && self.failure.items().is_empty()
+        {
+            let area = chunks[2];
+            let p = Paragraph::new(Span::styled(
+                "Running the tests…",
+                Style::default().fg(Color::Yellow),
+            ))
+            .alignment(Alignment::Center);
+            f.render_widget(
+                p,
+                Rect::new(
+                    area.x,
+                    area.y + area.height / 2,
+                    area.width,
+                    area.height.min(1),
+                ),
+            );
+            return;
+        }
+        if self.failure.items().is_empty() && self.statuses.contains(&TestResult::Fail) {
+            if let Some(tag) = &self.tag {
+                let p = Paragraph::new(format!("No failing test is tagged #{tag}"))
+                    .alignment(Alignment::Center);
+                f.render_widget(p, chunks[2]);
+                return;
+            }
+        }
+        if self.failure.items().is_empty()
+            && (!self.statuses.is_empty() || !self.skipped_suites.is_empty())
+        {
+            self.draw_all_passed(f, chunks[2]);
+            return;
+        }
+
+        let mut failure_location = chunks[2];
+        if self.preview && !self.zoomed {
+            if let Some(&Failure {
+                location: Some(ref location),
+                index,
+                ..
+            }) = self.failure.selected()
+            {
+                let direction = if self.vertical_preview {
+                    Direction::Vertical
+                } else {
+                    Direction::Horizontal
+                };
+                let preview_chunks = Layout::default()
+                    .direction(direction)
+                    .constraints([
+                        Constraint::Percentage(100 - self.preview_ratio()),
+                        Constraint::Percentage(self.preview_ratio()),
+                    ])
+                    .split(chunks[2]);
+
+                let title = self.breadcrumb(index, preview_chunks[1]);
+                let block = self.pane_block(Focus::Preview, title);
+                let file = match self.files_root() {
+                    Some(root) => root.join(&location.file),
+                    None => PathBuf::from(&location.file),
+                };
+                if image::is_image(&file) {
+                    match self.graphics.filter(|g| g.supports(&file)) {
+                        Some(_) => {
+                            let area = block.inner(preview_chunks[1]);
+                            f.render_widget(block, preview_chunks[1]);
+                            self.image = Some(image::Placement { path: file, area });
+                        }
+                        None => {
+                            let p = Paragraph::new(
+                                "This terminal cannot display the image (the kitty and iTerm2 \
+                                 graphics protocols are supported)",
+                            )
+                            .wrap(Wrap { trim: true })
+                            .block(block);
+                            f.render_widget(p, preview_chunks[1]);
+                        }
+                    }
+                    failure_location = preview_chunks[0];
+                } else {
+                    match generate_failure_preview(
+                        self.files_root(),
+                        &self.storage,
+                        location,
+                        preview_chunks[1],
+                        self.preview_scroll,
+                    ) {
+                        Ok(p) => {
+                            f.render_widget(Paragraph::new(p).block(block), preview_chunks[1]);
+                            failure_location = preview_chunks[0];
+                        }
+                        Err(e) => {
+                            self.toasts.push(Toast::error(e));
+                        }
+                    };
+                }
+            }
+        }
+
+        let annotations = &self.annotations;
+        if self.compact {
+            let areas = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Percentage(40), Constraint::Min(0)])
+                .split(failure_location);
+            failure_location = areas[0];
+
+            if let Some(failure) = self.failure.selected() {
+                let block = Block::default()
+                    .title(self.breadcrumb(failure.index, areas[1]))
+                    .borders(Borders::ALL);
+                let width = block.inner(areas[1]).width as usize;
+                let notes = annotations
+                    .get(&failure.number)
+                    .map_or(&[][..], Vec::as_slice);
+                let row = self
+                    .templates
+                    .failure
+                    .as_ref()
+                    .map(|t| t.render(&self.tests[failure.index]));
+                let lines = std::iter::once(Spans::from(failure.header(None, false, row)))
+                    .chain(failure.details(notes))
+                    .flat_map(|line| widgets::wrap(line, width))
+                    .collect_vec();
+                f.render_widget(Paragraph::new(lines).block(block), areas[1]);
+            }
+        }
+
+        let width = failure_location.width as usize;
+        let truncation = self.layout.truncation();
+        let compact = self.compact;
+        let focused = self.focus() == Focus::Failures;
+        self.failure.set_focused(focused);
+        let mut index = 0;
+        let (tests, template) = (&self.tests, self.templates.failure.as_ref());
+        self.failure
+            .render(f, failure_location, &self.theme, |failure| {
+                let frame = spinner
+                    .filter(|(t, _)| {
+                        *t == RerunTarget::Failure(index)
+                            || (*t == RerunTarget::Failures
+                                && tests[failure.index].status() == TestResult::Fail)
+                    })
+                    .map(|(_, frame)| frame);
+                index += 1;
+                let row = template.map(|t| t.render(&tests[failure.index]));
+                let mut header = Spans::from(failure.header(frame, compact, row));
+                if matches.contains(&failure.index) {
+                    for span in &mut header.0 {
+                        span.style = span.style.patch(match_style);
+                    }
+                }
+                if compact {
+                    return ListItem::new(widgets::truncate(header, width, truncation));
+                }
+
+                let notes = annotations
+                    .get(&failure.number)
+                    .map_or(&[][..], Vec::as_slice);
+                let mut lines = vec![match failure.wrapped {
+                    true => header,
+                    false => widgets::truncate(header, width, truncation),
+                }];
+                lines.extend(failure.details(notes));
+                if failure.wrapped {
+                    lines = lines
+                        .into_iter()
+                        .flat_map(|line| widgets::wrap(line, width))
+                        .collect();
+                }
+                ListItem::new(lines)
+            });
+    }
+
+    /// Whether the pane can be focused, i.e. it is displayed
+    fn focusable(&self, pane: Focus) -> bool {
+        let body = self.tree.is_none()
+            && self.bench.is_none()
+            && self.clusters.is_none()
+            && self.timeline.is_none()
+            && self.stderr_view.is_none()
+            && self.diff.is_none()
+            && self.raw.is_none();
+        match pane {
+            Focus::Failures => true,
+            Focus::Preview => {
+                body && self.preview
+                    && !self.zoomed
+                    && self.build_job.is_none()
+                    && self
+                        .failure
+                        .selected()
+                        .is_some_and(|f| f.location.is_some())
+            }
+            Focus::Skipped => !self.zoomed && self.skipped_rows() != 0,
+            Focus::Todo => !self.zoomed && !self.todo_list.items().is_empty(),
+            Focus::Build => self.build_job.is_some(),
+        }
+    }
+
+    /// Hints of the keys handled in the current state, in the order [`Self::handle_key`] tries them
+    fn hints(&self) -> &'static [Hint] {
+        if self.help.is_some() {
+            hints::HELP
+        } else if self.task_picker.is_some() {
+            hints::TASK_PICKER
+        } else if self.explanation.is_some() {
+            hints::EXPLANATION
+        } else if self.artifact_view.is_some() {
+            hints::ARTIFACT_VIEW
+        } else if self.artifact_picker.is_some() && self.tag_picker.is_none() {
+            hints::ARTIFACT_PICKER
+        } else if self.profile_picker.is_some()
+            || self.bulk_menu.is_some()
+            || self.tag_picker.is_some()
+        {
+            hints::PICKER
+        } else if self.search.as_ref().is_some_and(|s| s.editing) {
+            hints::SEARCH
+        } else if self.filter_edit.is_some() {
+            hints::FILTER_EDIT
+        } else if self.build_job.is_none() && self.bench.is_some() {
+            hints::BENCH
+        } else if self.build_job.is_none() && self.diff.is_some() {
+            hints::DIFF
+        } else if self.build_job.is_none() && self.raw.is_some() {
+            hints::RAW
+        } else if self.build_job.is_none() && self.clusters.is_some() {
+            hints::CLUSTERS
+        } else if self.build_job.is_none() && self.timeline.is_some() {
+            hints::TIMELINE
+        } else if self.build_job.is_none() && self.stderr_view.is_some() {
+            hints::STDERR
+        } else if self.build_job.is_none() && self.tree.is_some() {
+            hints::TREE
+        } else {
+            match self.focus() {
+                Focus::Failures if self.past_run.is_some() => hints::PAST_RUN,
+                Focus::Failures if self.search.is_some() => hints::MATCHES,
+                Focus::Failures => hints::FAILURES,
+                Focus::Preview => hints::PANE,
+                Focus::Skipped | Focus::Todo => hints::LIST,
+                Focus::Build => hints::BUILD,
+            }
+        }
+    }
+
+    /// Focused pane, falling back to the failures when it is no longer displayed
+    fn focus(&self) -> Focus {
+        match self.focusable(self.focus) {
+            true => self.focus,
+            false => Focus::Failures,
+        }
+    }
+
+    fn cycle_focus(&mut self, forward: bool) {
+        let position = Focus::CYCLE
+            .iter()
+            .position(|&p| p == self.focus())
+            .unwrap();
+        let count = Focus::CYCLE.len();
+        self.focus = (1..count)
+            .map(|i| match forward {
+                true => Focus::CYCLE[(position + i) % count],
+                false => Focus::CYCLE[(position + count - i) % count],
+            })
+            .find(|&p| self.focusable(p))
+            .unwrap_or(Focus::Failures);
+    }
+
+    /// Scrolls the focused pane by `delta` lines
+    fn scroll(&mut self, delta: i32) {
+        match self.focus() {
+            Focus::Failures => (),
+            Focus::Preview => self.preview_scroll += delta,
+            Focus::Skipped => self.skipped_list.move_by(delta),
+            Focus::Todo => self.todo_list.move_by(delta),
+            Focus::Build => {
+                self.build_scroll = self.build_scroll.saturating_add_signed(-delta as isize)
+            }
+        }
+    }
+
+    /// Breadcrumb of the test at `index`, shortened to fit the title of a pane drawn in `area`
+    fn breadcrumb(&self, index: usize, area: Rect) -> Spans<'static> {
+        let breadcrumb = Spans::from(tree::breadcrumb(&self.tests, index));
+        let width = area.width.saturating_sub(2) as usize;
+        widgets::truncate(breadcrumb, width, self.layout.truncation())
+    }
+
+    /// Block of a pane, with a highlighted border when it is focused
+    fn pane_block<'a>(&self, pane: Focus, title: impl Into<Spans<'a>>) -> Block<'a> {
+        let block = Block::default().title(title).borders(Borders::ALL);
+        match self.focus() == pane && pane != Focus::Failures {
+            true => block
+                .border_type(BorderType::Thick)
+                .border_style(Style::default().fg(Color::Yellow)),
+            false => block,
+        }
+    }
+
+    /// Counts of the results of the last run, including the reruns
+    fn summary(&self) -> Summary {
+        Summary {
+            skipped_suites: self.skipped_suites.len(),
+            ..self.statuses.iter().copied().collect()
+        }
+    }
+
+    /// Freezes the current results into the snapshot pane, or closes the snapshot pane
+    fn toggle_snapshot(&mut self) -> anyhow::Result<()> {
+        if self.snapshot.take().is_some() {
+            return Ok(());
+        }
+        if self.statuses.is_empty() {
+            anyhow::bail!("No results to freeze");
+        }
+        let failures = self
+            .failure
+            .items()
+            .iter()
+            .map(|f| Frozen {
+                number: f.number.clone(),
+                desc: f.desc.clone(),
+                key: failure_key(&self.tests, f.index),
+            })
+            .collect();
+        self.snapshot = Some(Snapshot {
+            taken_at: SystemTime::now(),
+            summary: self.summary(),
+            failures,
+        });
+        Ok(())
+    }
+
+    /// Draws the failures of the `snapshot`, with their result in the current run
+    fn draw_snapshot<B: Backend>(&self, f: &mut Frame<B>, snapshot: &Snapshot, area: Rect) {
+        let current: HashMap<_, _> = (0..self.tests.len())
+            .map(|i| (failure_key(&self.tests, i), self.tests[i].status()))
+            .collect();
+        let mut lines = vec![
+            Spans::from(Span::styled(
+                snapshot.summary.to_string(),
+                Style::default().add_modifier(Modifier::BOLD),
+            )),
+            Spans::default(),
+        ];
+        if snapshot.failures.is_empty() {
+            lines.push(Spans::from(Span::styled(
+                "No failing tests",
+                Style::default().fg(Color::Green),
+            )));
+        }
+        lines.extend(snapshot.failures.iter().map(|failure| {
+            let row = describe(&failure.number, failure.desc.as_deref());
+            let (row, style) = match current.get(&failure.key) {
+                Some(TestResult::Fail) => (format!("✘ {row}"), Style::default().fg(Color::Red)),
+                Some(TestResult::Success | TestResult::Bonus) => (
+                    format!("✔ {row} (fixed)"),
+                    Style::default().fg(Color::Green),
+                ),
+                Some(TestResult::Todo) => (
+                    format!("⊘ {row} (todo)"),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Some(TestResult::Skip) => (
+                    format!("⊘ {row} (skipped)"),
+                    Style::default().fg(Color::Yellow),
+                ),
+                None => (
+                    format!("  {row} (not run)"),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            };
+            Spans::from(Span::styled(row, style))
+        }));
+        let title = format!(
+            "Snapshot of {} (F: close)",
+            transcript::timestamp(snapshot.taken_at)
+        );
+        let p = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::default().title(title).borders(Borders::ALL));
+        f.render_widget(p, area);
+    }
+
+    /// Number of rows of the skipped pane: the skipped suites, then the skipped tests
+    fn skipped_rows(&self) -> usize {
+        self.skipped_list.items().len()
+    }
+
+    /// Draws the summary of a run without failures
+    fn draw_all_passed<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let summary = self.summary();
+        // A document skipping all its tests has nothing that passed
+        let skipped_document = self
+            .skipped_suites
+            .iter()
+            .find(|s| s.name.is_none())
+            .filter(|_| summary.total == 0);
+        let (mut headline, color) = match skipped_document {
+            Some(suite) => (
+                match &suite.reason {
+                    Some(reason) => format!("⊘ All the tests were skipped: {reason}"),
+                    None => "⊘ All the tests were skipped".to_owned(),
+                },
+                Color::Yellow,
+            ),
+            None => (
+                format!("✔ All {} tests passed", summary.passed),
+                Color::Green,
+            ),
+        };
+        if let Some(duration) = self.run_duration.filter(|_| !self.stale) {
+            headline.push_str(&format!(" in {:.1}s", duration.as_secs_f64()));
+        }
+        let mut lines = vec![Spans::from(Span::styled(
+            headline,
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        ))];
+
+        let skipped = summary.skipped;
+        if skipped != 0 {
+            lines.push(Spans::from(Span::styled(
+                format!("{skipped} skipped"),
+                Style::default().fg(Color::Yellow),
+            )));
+        }
+        let suites = summary.skipped_suites;
+        if suites != 0 && skipped_document.is_none() {
+            lines.push(Spans::from(Span::styled(
+                format!("{suites} skipped suites"),
+                Style::default().fg(Color::Yellow),
+            )));
+        }
+        let todo = summary.todo;
+        if todo != 0 {
+            lines.push(Spans::from(Span::styled(
+                format!("{todo} failing TODO"),
+                Style::default().fg(Color::Magenta),
+            )));
+        }
+        let bonus = summary.bonus;
+        if bonus != 0 {
+            lines.push(Spans::from(Span::styled(
+                format!("{bonus} passing TODO"),
+                Style::default().fg(Color::Cyan),
+            )));
+        }
+
+        let padding = area.height.saturating_sub(lines.len() as u16) / 2;
+        let p = Paragraph::new(
+            std::iter::repeat_n(Spans::default(), padding as usize)
+                .chain(lines)
+                .collect_vec(),
+        )
+        .alignment(Alignment::Center);
+        f.render_widget(p, area);
+    }
+
+    fn draw_task_picker<B: Backend>(&mut self, f: &mut Frame<B>) {
+        if let Some(picker) = &mut self.task_picker {
+            let build = self.cli.build_command.as_ref();
+            draw_popup(f, &self.theme, "Tasks (b: build)", picker, |task| {
+                match Some(&task.command()) == build {
+                    true => ListItem::new(format!("{} (build)", task.label())),
+                    false => ListItem::new(task.label()),
+                }
+            });
+        }
+    }
+
+    fn draw_profile_picker<B: Backend>(&mut self, f: &mut Frame<B>) {
+        if let Some(picker) = &mut self.profile_picker {
+            let active = self.profile.as_deref();
+            draw_popup(f, &self.theme, "Profiles", picker, |name| {
+                if Some(name.as_str()) == active {
+                    ListItem::new(format!("{name} (active)"))
+                } else {
+                    ListItem::new(name.as_str())
+                }
+            });
+        }
+    }
+}
+
+/// Whether the test has the `tag`, all the tests matching without one
+fn has_tag(test: &Test, tag: Option<&str>) -> bool {
+    tag.is_none_or(|tag| test.tags.iter().any(|t| t == tag))
+}
+
+fn tag_span(tag: &String) -> Span<'static> {
+    Span::styled(format!(" #{tag}"), Style::default().fg(Color::Blue))
+}
+
+/// Marks a test failing under only some of the interpreters
+fn only_under_span(interpreters: &[String]) -> Span<'static> {
+    Span::styled(
+        format!(" [only under {}]", interpreters.join(", ")),
+        Style::default()
+            .fg(Color::LightRed)
+            .add_modifier(Modifier::BOLD),
+    )
+}
+
+/// Seed of a new run, below 2³¹ for the test frameworks taking a 32-bit signed seed
+fn new_seed() -> u64 {
+    use std::hash::BuildHasher;
+    std::hash::RandomState::new().hash_one(SystemTime::now()) % (1 << 31)
+}
+
+/// Parses a `KEY=VALUE` argument of `--env`
+fn env_var(arg: &str) -> anyhow::Result<(String, String)> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_owned(), value.to_owned())),
+        _ => anyhow::bail!("expected `KEY=VALUE`, got `{arg}`"),
+    }
+}
+
+/// Replaces `{seed}` in an argument of a command run with `seed`
+fn seeded(arg: &str, seed: Option<u64>) -> String {
+    match seed {
+        Some(seed) => arg.replace("{seed}", &seed.to_string()),
+        None => arg.to_owned(),
+    }
+}
+
+/// Describes a duration in the largest unit it spans, e.g. `14 minutes`
+fn describe_duration(duration: Duration) -> String {
+    let plural = |count: u64, unit: &str| match count {
+        1 => format!("1 {unit}"),
+        n => format!("{n} {unit}s"),
+    };
+    match duration.as_secs() {
+        s if s < 60 => plural(s, "second"),
+        s if s < 2 * 3600 => plural(s / 60, "minute"),
+        s => plural(s / 3600, "hour"),
+    }
+}
+
+/// Notification summarizing the warnings of `tests`, if any
+fn warnings_toast(tests: &[Test]) -> Option<Toast> {
+    let mut warned = tests.iter().filter(|t| !t.warnings.is_empty());
+    let first = warned.next()?;
+
+    let warning = format!(
+        "Test {}: {}",
+        first.hierarchical_number(),
+        first.warnings[0]
+    );
+    Some(Toast::warning(match warned.count() {
+        0 => warning,
+        n => format!("{} tests have warnings, the first one is {warning}", n + 1),
+    }))
+}
+
+/// Runs `command`, printing its tests to stdout as they complete. Returns the whole output.
+fn print_streamed(out: &mut impl Write, test: &stream::StreamedTest) -> std::io::Result<()> {
+    let paint = |text: String, color: style::Color| match color::stdout() {
+        true => text.with(color),
+        false => text.stylize(),
+    };
+    let indent = "  ".repeat(test.depth);
+    let (result, color) = if test.is_todo() && test.ok {
+        (TestResult::Bonus, style::Color::Cyan)
+    } else if test.is_todo() {
+        (TestResult::Todo, style::Color::Magenta)
+    } else if test.is_skip() {
+        (TestResult::Skip, style::Color::Yellow)
+    } else if test.ok {
+        (TestResult::Success, style::Color::Green)
+    } else {
+        (TestResult::Fail, style::Color::Red)
+    };
+    let glyph = paint(result_symbol(result).to_string(), color);
+    write!(out, "{indent}{glyph}")?;
+    if let Some(number) = test.number {
+        write!(out, " {number}")?;
+    }
+    if let Some(desc) = &test.desc {
+        write!(out, " - {desc}")?;
+    }
+    if let Some(directive) = &test.directive {
+        write!(
+            out,
+            " {}",
+            paint(format!("# {directive}"), style::Color::DarkGrey)
+        )?;
+    }
+    writeln!(out)?;
+
+    if !test.ok && !test.is_todo() {
+        for line in &test.yaml {
+            writeln!(
+                out,
+                "{indent}    {}",
+                paint(line.clone(), style::Color::Red)
+            )?;
+        }
+    }
+    out.flush()
+}
+
+/// Position in `items` of the first item after (or before) `current` for which `is_match` is
+/// true, wrapping around
+fn next_match<T>(
+    items: &[T],
+    current: Option<usize>,
+    forward: bool,
+    is_match: impl Fn(usize) -> bool,
+) -> Option<usize> {
+    let len = items.len();
+    let start = match (current, forward) {
+        (Some(c), _) => c,
+        (None, true) => len.checked_sub(1)?,
+        (None, false) => 0,
+    };
+    (1..=len)
+        .map(|step| match forward {
+            true => (start + step) % len,
+            false => (start + len - step) % len,
+        })
+        .find(|&i| is_match(i))
+}
+
+/// Symbol of a result, shown in the tree view and instead of the colors when they are disabled
+fn result_symbol(result: TestResult) -> char {
+    match result {
+        TestResult::Skip => '↷',
+        TestResult::Success => '✔',
+        TestResult::Fail => '✘',
+        TestResult::Todo => '◌',
+        TestResult::Bonus => '★',
+    }
+}
+
+/// Identity of the test at `index` across runs: its path of descriptions, or its number if it has
+/// no description
+fn failure_key(tests: &[Test], index: usize) -> String {
+    match tests[index].desc {
+        Some(_) => tree::description_path(tests, index),
+        None => tests[index].hierarchical_number(),
+    }
+}
+
+/// Draws `list` in a popup at the center of the frame
+fn draw_popup<B, T, F>(
+    f: &mut Frame<B>,
+    theme: &Theme,
+    title: &str,
+    list: &mut StatefulList<T>,
+    make_item: F,
+) where
+    B: Backend,
+    F: FnMut(&T) -> ListItem,
+{
+    let area = centered_rect(40, 50, f.size());
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded),
+        area,
+    );
+    list.render(
+        f,
+        area.inner(&Margin {
+            vertical: 1,
+            horizontal: 1,
+        }),
+        theme,
+        make_item,
+    );
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+fn generate_failure_preview(
+    root: Option<&Path>,
+    storage: &Storage,
+    location: &Location,
+    area: Rect,
+    scroll: i32,
+) -> anyhow::Result<Text<'static>> {
+    let file = root
+        .map(|r| r.join(&location.file))
+        .unwrap_or_else(|| Path::new(&location.file).to_path_buf());
+    if !file.exists() {
+        anyhow::bail!("File {} does not exist", location.file)
+    }
+    let file = file.to_str().context("file path is not UTF-8")?;
+
+    let modified = std::fs::metadata(file)?.modified()?;
+    let key = format!("{file}:{}:{}:{modified:?}", location.line, area.width);
+    let cached = storage.cache_path(&format!(
+        "{PREVIEW_CACHE}/{:016x}",
+        storage::stable_hash(key.as_bytes())
+    ));
+
+    let output = match std::fs::read(&cached) {
+        Ok(output) => output,
+        Err(_) => {
+            let shell = env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+            let output = Command::new(shell)
+                .arg("-c")
+                .arg(format!(
+                    "bat --force-colorization --terminal-width {} {} --highlight-line {}",
+                    area.width - 2,
+                    file,
+                    location.line
+                ))
+                .output()?
+                .stdout;
+            // The cache is only an optimization, failing to write to it is not an issue
+            let _ = Storage::write(&cached, &output);
+            output
+        }
+    };
+    let mut preview = output.into_text()?;
 
     let height = area.height - 2;
 
+    let mut discard = 0;
     if location.line > height as usize {
         let out = location.line - height as usize;
         let discard_to_center = out + (height as usize) / 2;
         let would_remain = preview.lines.len() - discard_to_center;
-        let discard = if would_remain > height as usize {
+        discard = if would_remain > height as usize {
             discard_to_center
         } else {
             discard_to_center - (height as usize - would_remain)
         };
-        preview.lines.drain(0..discard);
     }
+    let last = preview.lines.len().saturating_sub(1);
+    let discard = discard.saturating_add_signed(scroll as isize).min(last);
+    preview.lines.drain(0..discard);
 
     Ok(preview)
 }
 
 #[derive(Parser, Debug)]
+#[command(
+    name = "tapr",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    disable_help_subcommand = true
+)]
 struct Args {
-    #[arg(required = true)]
+    #[command(subcommand)]
+    command: Option<Subcommand>,
     run_command: Vec<String>,
-    #[arg(long, short, value_delimiter = ',')]
-    build_command: Option<Vec<String>>,
+    /// Run the tests with a recipe of the Justfile
+    #[arg(long, conflicts_with_all = ["run_command", "make"])]
+    just: Option<String>,
+    /// Run the tests with a target of the Makefile
+    #[arg(long, conflicts_with = "run_command")]
+    make: Option<String>,
+    /// Command building the tests before running them, split like by a shell, e.g. `-b "cargo
+    /// build --features a,b"`. When given several times, the commands are run one after the
+    /// other, the build stopping at the first one which fails.
+    #[arg(long, short, value_name = "COMMAND")]
+    build_command: Vec<String>,
+    /// Command running a single failing test when pressing `Enter`, `{number}`, `{desc}` and
+    /// `{path}` are replaced by the number, description and path of descriptions of the test
+    #[arg(long, value_delimiter = ',')]
+    rerun_test_command: Option<Vec<String>>,
+    /// Command running all the tests of the subtest selected in the tree view, with the same
+    /// placeholders as --rerun-test-command
+    #[arg(long, value_delimiter = ',')]
+    rerun_subtest_command: Option<Vec<String>>,
+    /// Command running all the failing tests at once when pressing `f`, `{numbers}`,
+    /// `{descriptions}` and `{paths}` are replaced by the values of the failing tests
+    #[arg(long, value_delimiter = ',')]
+    rerun_command: Option<Vec<String>>,
+    /// Shell command explaining the selected failure when pressing `i`, `{number}`, `{desc}`,
+    /// `{file}` and `{line}` are replaced by the values of the failure, e.g. `git log -L
+    /// {line},{line}:{file}`
+    #[arg(long, value_name = "COMMAND")]
+    explain: Option<String>,
+    /// Run the tests under each of these interpreters, replacing `{interp}` in the run command or
+    /// prepended to it, the tests failing under only some of them being highlighted
+    #[arg(long = "interp", value_name = "INTERPRETER")]
+    interpreters: Vec<String>,
+    /// Run only this shard of the tests, such as `2/8`, replacing `{shard}`, `{shard_index}` and
+    /// `{shard_count}` in the run command and setting `TAP_SHARD`, `TAP_SHARD_INDEX` and
+    /// `TAP_SHARD_COUNT`
+    #[arg(long, value_name = "INDEX/COUNT")]
+    shard: Option<String>,
+    /// Run all the shards of the tests in parallel, merging them in a single run
+    #[arg(long, value_name = "COUNT")]
+    shards: Option<usize>,
+    /// Seed of the runs, replacing `{seed}` in the run and rerun commands and setting `TAP_SEED`,
+    /// to replay a run of shuffled or property-based tests. A new seed is drawn for each run
+    /// otherwise, shown in the summary and kept with the past runs.
+    #[arg(long)]
+    seed: Option<u64>,
     #[arg(long, short)]
     location_filter: Option<String>,
-    #[arg(long, short, requires = "location_filter")]
+    /// jq filter producing the tags of the tests from their YAML diagnostics, instead of their
+    /// `tags` key
+    #[arg(long)]
+    tag_filter: Option<String>,
+    #[arg(long, short)]
     preview: bool,
-    #[arg(long, short, requires = "location_filter")]
+    #[arg(long, short)]
     root: Option<PathBuf>,
+    /// Directory the build and test commands are run in, the relative paths of the locations
+    /// being resolved from it
+    #[arg(long, value_name = "DIR")]
+    cwd: Option<PathBuf>,
+    /// Run the test command in a pseudo-terminal, for the harnesses which only color their
+    /// output or print some diagnostics when writing to a terminal
+    #[arg(long)]
+    pty: bool,
+    /// Extract the TAP document from a larger log, such as the output of a CI job, the prefix of
+    /// its lines being detected on its first line
+    #[arg(long)]
+    extract: bool,
+    /// Regex matching the prefix of the lines of the log to strip when extracting the document,
+    /// e.g. a timestamp. Implies --extract.
+    #[arg(long, value_name = "REGEX")]
+    log_prefix: Option<String>,
+    /// Environment variable given to the build, test and rerun commands, taking precedence over
+    /// the `env` of the config. Can be given several times.
+    #[arg(long = "env", value_name = "KEY=VALUE", value_parser = env_var)]
+    env: Vec<(String, String)>,
+    /// Run the commands in the development shell of a flake with `nix develop`, by default the
+    /// one of the current directory
+    #[arg(
+        long,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ".",
+        value_name = "FLAKE"
+    )]
+    nix: Option<String>,
+    /// Use the commands, filter and environment of a profile from the config file
+    #[arg(long, short = 'P')]
+    profile: Option<String>,
+    /// Use this project config file instead of looking up `.tap-runner.toml`
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// Print the effective configuration and exit
+    #[arg(long)]
+    show_config: bool,
+    /// Find the past run where the test with this number, description or path of descriptions
+    /// started failing, and exit
+    #[arg(long, value_name = "TEST", conflicts_with = "once")]
+    bisect: Option<String>,
+    /// Run the tests once without the TUI, exiting with a non-zero code if any test failed
+    #[arg(long)]
+    once: bool,
+    /// Only report the result through the exit code
+    #[arg(long, requires = "once", conflicts_with_all = ["summary", "verbose"])]
+    quiet: bool,
+    /// Only print the test counts
+    #[arg(long, requires = "once", conflicts_with = "verbose")]
+    summary: bool,
+    /// Print the test counts and the details of each failing test
+    #[arg(long, requires = "once")]
+    verbose: bool,
+    /// How the results are printed in headless mode
+    #[arg(long, requires = "once", value_enum, default_value_t)]
+    format: Format,
+    /// Conditions making the headless mode exit with a non-zero code
+    #[arg(
+        long,
+        requires = "once",
+        value_delimiter = ',',
+        default_value = "fail,plan-mismatch,exit-status"
+    )]
+    fail_on: Vec<FailOn>,
+    /// Where the TAP document is read from instead of the run command: `stdin`, `file:<path>`,
+    /// `follow:<path>`, `tcp:<host:port>`, `serial:<device>`, or `ssh:<host>` to run the command
+    /// on a remote host
+    #[arg(long, value_name = "SOURCE")]
+    source: Option<String>,
+    /// Read the TAP document piped to stdin, like `--source stdin` or `-` as the run command
+    #[arg(long, conflicts_with_all = ["run_command", "just", "make", "source"])]
+    stdin: bool,
+    /// Show a saved TAP document, such as the output of a CI job, reloaded with `r`. Like
+    /// `--source file:<FILE>`
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["run_command", "just", "make", "source", "stdin"]
+    )]
+    from_file: Option<PathBuf>,
+    /// Follow a TAP log written by another process, like `tail -F`, each document appended to it
+    /// being shown as a run. Like `--source follow:<FILE>`
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["run_command", "just", "make", "source", "stdin", "from_file"]
+    )]
+    follow: Option<PathBuf>,
+    /// Write a transcript of the session (commands, results and failures) to a file on exit
+    #[arg(long, value_name = "FILE")]
+    transcript: Option<PathBuf>,
+    /// Write the results of each run (tests, directives, diagnostics and locations) to a file as
+    /// JSON
+    #[arg(long, value_name = "FILE")]
+    report_json: Option<PathBuf>,
+    /// Compare the TAP output with a golden file, each group of differing lines being reported
+    /// as a failing test
+    #[arg(long, value_name = "FILE")]
+    golden: Option<PathBuf>,
+    /// File of the failures accepted as expected, `.tap-runner-expected.toml` in the project root
+    /// by default
+    #[arg(long, value_name = "FILE")]
+    expectations: Option<PathBuf>,
+    /// Accept all the failures of the run as expected, in the expectations file
+    #[arg(long, requires = "once")]
+    accept: bool,
+    /// Mirror the session to the viewers attached with `--attach`, listening on `host:port`, or
+    /// on a port of the local host
+    #[arg(long, value_name = "ADDRESS", conflicts_with = "once")]
+    mirror: Option<String>,
+    /// Show a read-only copy of the session mirrored at `host:port` (or a port of the local
+    /// host) with `--mirror`, and exit
+    #[arg(long, value_name = "ADDRESS", conflicts_with_all = ["once", "mirror"])]
+    attach: Option<String>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Subcommand {
+    /// Print a completion script for the given shell to stdout
+    Completions { shell: clap_complete::Shell },
+    /// List the profiles available in the config file
+    Profiles,
+    /// Check the configuration, the programs it runs and the terminal, printing how to fix the
+    /// problems found
+    Doctor {
+        /// Check only this profile instead of all of them
+        #[arg(long, short = 'P')]
+        profile: Option<String>,
+    },
+}
+
+impl Args {
+    fn output_level(&self) -> OutputLevel {
+        if self.quiet {
+            OutputLevel::Quiet
+        } else if self.summary {
+            OutputLevel::Summary
+        } else if self.verbose {
+            OutputLevel::Verbose
+        } else {
+            OutputLevel::Normal
+        }
+    }
+}
+
+impl Args {
+    /// Whether the document is read from stdin, with `--stdin` or `-`
+    fn stdin(&self) -> bool {
+        self.stdin || self.run_command == ["-"]
+    }
+
+    fn settings(&self) -> anyhow::Result<Settings> {
+        // The last build command is the build itself, the ones before it are run before it
+        let mut build: Vec<_> = self
+            .build_command
+            .iter()
+            .map(|command| config::parse_command(command))
+            .try_collect()
+            .context("Invalid build command")?;
+        Ok(Settings {
+            run_command: match (&self.just, &self.make, self.run_command.as_slice()) {
+                (Some(recipe), _, _) => {
+                    Some(tasks::Task::new(tasks::Runner::Just, recipe).command())
+                }
+                (_, Some(target), _) => {
+                    Some(tasks::Task::new(tasks::Runner::Make, target).command())
+                }
+                _ if self.stdin() || self.from_file.is_some() || self.follow.is_some() => None,
+                (_, _, []) => None,
+                // A whole command line given as a single argument, e.g. `tapr "cargo test"`
+                (_, _, [command]) if command.contains(char::is_whitespace) => {
+                    Some(config::split_command(command).context("Invalid run command")?)
+                }
+                (_, _, command) => Some(command.to_vec()),
+            },
+            build_command: build.pop(),
+            pre_build: (!self.build_command.is_empty()).then_some(build),
+            rerun_test_command: self.rerun_test_command.clone(),
+            rerun_subtest_command: self.rerun_subtest_command.clone(),
+            rerun_command: self.rerun_command.clone(),
+            explain: self.explain.clone(),
+            interpreters: (!self.interpreters.is_empty()).then(|| self.interpreters.clone()),
+            shard: self.shard.clone(),
+            shards: self.shards,
+            seed: self.seed,
+            location_filter: self.location_filter.clone(),
+            tag_filter: self.tag_filter.clone(),
+            sources: None,
+            preview: self.preview.then_some(true),
+            root: self.root.clone(),
+            cwd: self.cwd.as_deref().map(std::path::absolute).transpose()?,
+            build_cwd: None,
+            pty: self.pty.then_some(true),
+            extract: self.extract.then_some(true),
+            log_prefix: self.log_prefix.clone(),
+            env: self.env.iter().cloned().collect(),
+            nix: self.nix.clone(),
+            direnv: None,
+            hooks: Hooks::default(),
+            plugins: None,
+            script: None,
+            golden: self.golden.clone(),
+            expectations: self
+                .expectations
+                .as_deref()
+                .map(std::path::absolute)
+                .transpose()?,
+            layout: LayoutSettings::default(),
+            templates: TemplateSettings::default(),
+            theme: ThemeSettings::default(),
+            select_new_failure: None,
+            wrap_selection: None,
+            keys: BTreeMap::new(),
+            vim_keys: None,
+            source: match (self.stdin(), &self.from_file, &self.follow) {
+                (true, _, _) => Some("stdin".to_owned()),
+                (false, Some(file), _) => Some(format!("file:{}", file.display())),
+                (false, None, Some(file)) => Some(format!("follow:{}", file.display())),
+                (false, None, None) => self.source.clone(),
+            },
+        })
+    }
+
+    fn profile(&self) -> Option<String> {
+        self.profile
+            .clone()
+            .or_else(|| env::var("TAP_RUNNER_PROFILE").ok())
+    }
+}
+
+impl App {
+    fn from_args(args: &Args, start: Start) -> anyhow::Result<Self> {
+        let mut app = App::new(
+            Config::discover(args.config.as_deref())?,
+            args.settings()?,
+            args.profile(),
+            start,
+            args.format == Format::Stream,
+        )?;
+        app.report_json = args.report_json.clone();
+        Ok(app)
+    }
+}
+
+fn show_config(args: &Args) -> anyhow::Result<()> {
+    let config = Config::discover(args.config.as_deref())?;
+    let profile = args.profile();
+    let settings = config.resolve(profile.as_deref(), &args.settings()?)?;
+
+    if config.files.is_empty() {
+        println!("# No config file loaded");
+    } else {
+        println!("# Config files, by increasing precedence:");
+        for file in &config.files {
+            println!("#   {}", file.display());
+        }
+    }
+    let storage = Storage::for_project(&Config::project_root(args.config.as_deref())?)?;
+    println!("# State directory: {}", storage.state_dir().display());
+    println!("# Cache directory: {}", storage.cache_dir().display());
+    if let Some(profile) = profile {
+        println!("# Active profile: {profile}");
+    }
+    print!("{}", toml::to_string(&settings)?);
+
+    Ok(())
+}
+
+fn run_once(args: Args) -> anyhow::Result<ExitCode> {
+    let level = args.output_level();
+    let mut app = App::from_args(&args, Start::Run)?;
+    if app.profile_picker.is_some() {
+        anyhow::bail!("Multiple profiles are available, select one with --profile");
+    }
+    if let Some(picker) = &app.task_picker {
+        anyhow::bail!(
+            "No run command given, use one of the tasks with --just or --make: {}",
+            picker.items().iter().map(|t| t.label()).join(", ")
+        );
+    }
+
+    // The run failing to produce results fails, whatever the conditions of --fail-on
+    let run_error = app.wait_build().and_then(|()| app.wait_test_run()).err();
+    if let Some(e) = &run_error {
+        app.toasts.push(Toast::error(format!("{e:#}")));
+    }
+    if !app.could_run {
+        if let Some((job, _)) = &app.build_job {
+            std::io::stderr().write_all(&job.output)?;
+        }
+        if let Some(e) = app.toasts.last_error() {
+            anyhow::bail!("{}", e.message);
+        }
+    }
+    if level != OutputLevel::Quiet {
+        for toast in app.toasts.iter() {
+            match toast.level {
+                toast::Level::Info | toast::Level::Success => (),
+                toast::Level::Warning => eprintln!("Warning: {}", toast.message),
+                toast::Level::Error => eprintln!("Error: {}", toast.message),
+            }
+        }
+    }
+
+    if args.accept {
+        let failures = (0..app.failure.items().len())
+            .filter(|&i| !app.failure.items()[i].expected)
+            .collect_vec();
+        if !failures.is_empty() {
+            app.accept(&failures)?;
+            if level != OutputLevel::Quiet {
+                eprintln!(
+                    "Accepted {} failures as expected in {}",
+                    failures.len(),
+                    app.expectations.path().display()
+                );
+            }
+        }
+    }
+    app.print_summary(&mut std::io::stdout(), level)?;
+    if let Some(path) = &args.transcript {
+        app.transcript.save(path)?;
+    }
+    if run_error.is_some() {
+        return Ok(ExitCode::FAILURE);
+    }
+
+    let reasons = app.failure_reasons(&args.fail_on);
+    if reasons.is_empty() {
+        return Ok(ExitCode::SUCCESS);
+    }
+    if level != OutputLevel::Quiet && reasons.iter().any(|(c, _)| *c != FailOn::Fail) {
+        eprintln!(
+            "Failing because of: {}",
+            reasons.iter().map(|(_, r)| r).join(", ")
+        );
+    }
+    Ok(ExitCode::FAILURE)
 }
 
-fn main() -> anyhow::Result<()> {
+fn main() -> anyhow::Result<ExitCode> {
     let args = Args::parse();
 
+    match args.command {
+        Some(Subcommand::Completions { shell }) => {
+            completions::generate(shell, &mut std::io::stdout())?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Subcommand::Profiles) => {
+            for profile in Config::discover(args.config.as_deref())?.profile.keys() {
+                println!("{profile}");
+            }
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Subcommand::Doctor { profile }) => {
+            return match doctor::run(&mut std::io::stdout(), args.config.as_deref(), profile)? {
+                true => Ok(ExitCode::SUCCESS),
+                false => Ok(ExitCode::FAILURE),
+            };
+        }
+        None => (),
+    }
+
+    if args.show_config {
+        show_config(&args)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if let Some(test) = &args.bisect {
+        let app = App::from_args(&args, Start::Idle)?;
+        let parse = |tap: &str| Ok(app.parse(tap)?.tests);
+        bisect::bisect(&mut std::io::stdout(), &app.history().runs(), parse, test)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if args.once {
+        return run_once(args);
+    }
+
+    if let Some(address) = &args.attach {
+        mirror::attach(&mirror::address(address))?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    let mirror = args
+        .mirror
+        .as_deref()
+        .map(|address| mirror::Mirror::bind(&mirror::address(address)))
+        .transpose()?;
+    // Built before taking over the terminal, which is left as it was if the settings are invalid
+    let mut app = App::from_args(&args, Start::Restore)?;
+    app.mirror = mirror;
+
     crossterm::terminal::enable_raw_mode()?;
     let mut stdout = std::io::stdout();
+    // Saves the title of the terminal on the xterm title stack, to restore it on exit
+    write!(stdout, "\x1b[22;0t")?;
     crossterm::execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let res = App::new(
-        args.run_command,
-        args.build_command,
-        args.location_filter,
-        args.preview,
-        args.root,
-    )?
-    .run(&mut terminal, Duration::from_secs_f64(0.1));
+    let res = app.run(&mut terminal, Duration::from_secs_f64(0.1));
 
     crossterm::terminal::disable_raw_mode()?;
     crossterm::execute!(
@@ -614,7 +6235,19 @@ fn main() -> anyhow::Result<()> {
         LeaveAlternateScreen,
         DisableMouseCapture
     )?;
+    write!(terminal.backend_mut(), "\x1b[23;0t")?;
     terminal.show_cursor()?;
 
-    res
+    res?;
+    app.save_session()?;
+    if let Some(path) = &args.transcript {
+        app.transcript.record("Session ended");
+        app.transcript.save(path)?;
+    }
+
+    if app.could_run {
+        app.print_summary(&mut std::io::stdout(), OutputLevel::Normal)?;
+    }
+
+    Ok(ExitCode::SUCCESS)
 }
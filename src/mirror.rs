@@ -0,0 +1,239 @@
+use std::{
+    fmt::Write as _,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, RecvTimeoutError},
+    time::Duration,
+};
+
+use anyhow::Context;
+use crossterm::{
+    cursor::{Hide, Show},
+    event::{Event, KeyCode, KeyModifiers},
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen},
+};
+use tui::{
+    buffer::Buffer,
+    style::{Color, Modifier},
+    text::Span,
+};
+
+/// Time given to a viewer to receive a frame before it is disconnected, so that a slow viewer
+/// does not freeze the session
+const WRITE_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Address given to `--mirror` and `--attach`: `host:port`, or only a port of the local host
+pub fn address(arg: &str) -> String {
+    match arg.contains(':') {
+        true => arg.to_owned(),
+        false => format!("127.0.0.1:{arg}"),
+    }
+}
+
+/// Sends the frames drawn by the TUI to the viewers attached with `--attach`
+pub struct Mirror {
+    listener: TcpListener,
+    viewers: Vec<TcpStream>,
+    /// Last frame sent, given to the viewers as they attach
+    frame: Vec<u8>,
+}
+
+impl Mirror {
+    pub fn bind(address: &str) -> anyhow::Result<Self> {
+        let listener = TcpListener::bind(address)
+            .with_context(|| format!("Could not listen on {address} to mirror the session"))?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            viewers: Vec::new(),
+            frame: Vec::new(),
+        })
+    }
+
+    /// Sends `buffer` to the viewers if it changed, and the last frame to the new viewers. The
+    /// viewers which can't receive it are disconnected.
+    pub fn send(&mut self, buffer: &Buffer) {
+        let frame = encode(buffer);
+        if frame != self.frame {
+            self.frame = frame;
+            self.viewers
+                .retain_mut(|viewer| viewer.write_all(&self.frame).is_ok());
+        }
+        while let Ok((mut viewer, _)) = self.listener.accept() {
+            let attached = viewer
+                .set_nonblocking(false)
+                .and_then(|_| viewer.set_write_timeout(Some(WRITE_TIMEOUT)))
+                .and_then(|_| viewer.write_all(&self.frame));
+            if attached.is_ok() {
+                self.viewers.push(viewer);
+            }
+        }
+    }
+}
+
+/// Frame of the mirror protocol: a `<width> <height> <length>` header line, followed by the
+/// escape sequences drawing the cells of `buffer`, row by row
+fn encode(buffer: &Buffer) -> Vec<u8> {
+    let area = buffer.area();
+    let mut cells = String::new();
+    let mut style = None;
+    // Columns covered by the last wide character
+    let mut covered = 0;
+    for (i, cell) in buffer.content().iter().enumerate() {
+        let (x, y) = (i % area.width as usize, i / area.width as usize);
+        if x == 0 {
+            let _ = write!(cells, "\x1b[0m\x1b[{};1H", y + 1);
+            style = None;
+            covered = 0;
+        }
+        if covered != 0 {
+            covered -= 1;
+            continue;
+        }
+        if style != Some((cell.fg, cell.bg, cell.modifier)) {
+            style = Some((cell.fg, cell.bg, cell.modifier));
+            cells.push_str(&sgr(cell.fg, cell.bg, cell.modifier));
+        }
+        cells.push_str(&cell.symbol);
+        covered = Span::raw(cell.symbol.as_str()).width().saturating_sub(1);
+    }
+    cells.push_str("\x1b[0m");
+
+    let mut frame = format!("{} {} {}\n", area.width, area.height, cells.len()).into_bytes();
+    frame.extend(cells.into_bytes());
+    frame
+}
+
+/// Escape sequence selecting a style, from the default one
+fn sgr(fg: Color, bg: Color, modifier: Modifier) -> String {
+    const MODIFIERS: [(Modifier, u8); 9] = [
+        (Modifier::BOLD, 1),
+        (Modifier::DIM, 2),
+        (Modifier::ITALIC, 3),
+        (Modifier::UNDERLINED, 4),
+        (Modifier::SLOW_BLINK, 5),
+        (Modifier::RAPID_BLINK, 6),
+        (Modifier::REVERSED, 7),
+        (Modifier::HIDDEN, 8),
+        (Modifier::CROSSED_OUT, 9),
+    ];
+    let mut codes = vec!["0".to_owned()];
+    codes.extend(
+        MODIFIERS
+            .iter()
+            .filter(|(m, _)| modifier.contains(*m))
+            .map(|(_, code)| code.to_string()),
+    );
+    codes.extend(color(fg, 30));
+    codes.extend(color(bg, 40));
+    format!("\x1b[{}m", codes.join(";"))
+}
+
+/// Parameters of the escape sequence selecting a color, `base` being 30 for the foreground and
+/// 40 for the background
+fn color(color: Color, base: u8) -> Option<String> {
+    let bright = base + 60;
+    Some(match color {
+        Color::Reset => return None,
+        Color::Black => base.to_string(),
+        Color::Red => (base + 1).to_string(),
+        Color::Green => (base + 2).to_string(),
+        Color::Yellow => (base + 3).to_string(),
+        Color::Blue => (base + 4).to_string(),
+        Color::Magenta => (base + 5).to_string(),
+        Color::Cyan => (base + 6).to_string(),
+        Color::Gray => (base + 7).to_string(),
+        Color::DarkGray => bright.to_string(),
+        Color::LightRed => (bright + 1).to_string(),
+        Color::LightGreen => (bright + 2).to_string(),
+        Color::LightYellow => (bright + 3).to_string(),
+        Color::LightBlue => (bright + 4).to_string(),
+        Color::LightMagenta => (bright + 5).to_string(),
+        Color::LightCyan => (bright + 6).to_string(),
+        Color::White => (bright + 7).to_string(),
+        Color::Indexed(n) => format!("{};5;{n}", base + 8),
+        Color::Rgb(r, g, b) => format!("{};2;{r};{g};{b}", base + 8),
+    })
+}
+
+/// Reads the frames sent by the mirror, until it disconnects
+fn read_frames(stream: TcpStream, frames: mpsc::Sender<((u16, u16), Vec<u8>)>) {
+    let mut reader = BufReader::new(stream);
+    let mut header = String::new();
+    loop {
+        header.clear();
+        if !matches!(reader.read_line(&mut header), Ok(n) if n != 0) {
+            return;
+        }
+        let fields = header
+            .split_whitespace()
+            .map(|field| field.parse::<usize>().ok())
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default();
+        let [width, height, length] = fields[..] else {
+            return;
+        };
+        let mut cells = vec![0; length];
+        if reader.read_exact(&mut cells).is_err() {
+            return;
+        }
+        if frames.send(((width as u16, height as u16), cells)).is_err() {
+            return;
+        }
+    }
+}
+
+/// Shows the session mirrored at `address`, read-only, until `q`, `<esc>` or `Ctrl-C` is pressed
+/// or the session ends
+pub fn attach(address: &str) -> anyhow::Result<()> {
+    let stream = TcpStream::connect(address)
+        .with_context(|| format!("Could not attach to the session mirrored at {address}"))?;
+    let (tx, frames) = mpsc::channel();
+    std::thread::spawn(move || read_frames(stream, tx));
+
+    crossterm::terminal::enable_raw_mode()?;
+    let mut stdout = std::io::stdout();
+    // The rows wider than the terminal are clipped instead of wrapped
+    crossterm::execute!(stdout, EnterAlternateScreen, Hide)?;
+    write!(stdout, "\x1b[?7l")?;
+    let result = show_frames(&mut stdout, &frames);
+    write!(stdout, "\x1b[?7h")?;
+    crossterm::execute!(stdout, Show, LeaveAlternateScreen)?;
+    crossterm::terminal::disable_raw_mode()?;
+
+    if result? {
+        println!("The mirrored session ended");
+    }
+    Ok(())
+}
+
+/// Draws the frames received until a key quits, returns whether the session ended instead
+fn show_frames(
+    out: &mut impl Write,
+    frames: &mpsc::Receiver<((u16, u16), Vec<u8>)>,
+) -> anyhow::Result<bool> {
+    let mut size = None;
+    loop {
+        match frames.recv_timeout(Duration::from_millis(50)) {
+            Ok((frame_size, cells)) => {
+                // Clears what a larger frame left around the new one
+                if size.replace(frame_size) != Some(frame_size) {
+                    write!(out, "\x1b[0m\x1b[2J")?;
+                }
+                out.write_all(&cells)?;
+                out.flush()?;
+            }
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => return Ok(true),
+        }
+        while crossterm::event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = crossterm::event::read()? {
+                let ctrl_c =
+                    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                    return Ok(false);
+                }
+            }
+        }
+    }
+}
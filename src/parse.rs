@@ -0,0 +1,265 @@
+use itertools::Itertools;
+use jaq_core::{Definitions, Filter};
+use tap_parser::{DirectiveKind, TapParser, TapStatement, TapTest};
+
+use crate::{test::yaml_strings, Directive, Test};
+
+enum Either3<T, U, V> {
+    One(T),
+    Two(U),
+    Three(V),
+}
+
+impl<T, U, V, X> Iterator for Either3<T, U, V>
+where
+    T: Iterator<Item = X>,
+    U: Iterator<Item = X>,
+    V: Iterator<Item = X>,
+{
+    type Item = X;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Either3::One(v) => v.next(),
+            Either3::Two(v) => v.next(),
+            Either3::Three(v) => v.next(),
+        }
+    }
+}
+
+/// Compiles the jq `filter` extracting the location of the failures from their diagnostics
+pub fn parse_filter(filter: &str) -> anyhow::Result<Filter> {
+    let defs = Definitions::core();
+
+    let (f, errs) = jaq_core::parse::parse(filter, jaq_core::parse::main());
+    let f = match f {
+        None => {
+            anyhow::bail!("Errors parsing the filter: {}", errs.iter().join("\n"))
+        }
+        Some(f) => f,
+    };
+    let mut errs = Vec::new();
+    let f = defs.finish(f, Vec::new(), &mut errs);
+    if !errs.is_empty() {
+        anyhow::bail!("Errors finishing the filter: {}", errs.iter().join("\n"))
+    }
+
+    Ok(f)
+}
+
+/// Strings produced by the jq `filter` from the YAML diagnostics `yaml`, the arrays of strings
+/// being flattened
+pub fn filter_strings(filter: &Filter, yaml: &str) -> anyhow::Result<Vec<String>> {
+    let value: serde_yaml::Value = serde_yaml::from_str(yaml)?;
+    let json = serde_json::to_value(&value)?;
+    let inputs = jaq_core::RcIter::new(core::iter::empty());
+    let mut strings = Vec::new();
+    for output in filter.run(jaq_core::Ctx::new([], &inputs), jaq_core::Val::from(json)) {
+        match serde_json::Value::from(output.map_err(|e| anyhow::anyhow!("{e}"))?) {
+            serde_json::Value::String(s) => strings.push(s),
+            serde_json::Value::Array(values) => {
+                for value in values {
+                    match value {
+                        serde_json::Value::String(s) => strings.push(s),
+                        v => anyhow::bail!("Expected a string, got {v}"),
+                    }
+                }
+            }
+            serde_json::Value::Null => (),
+            v => anyhow::bail!("Expected a string or a list of strings, got {v}"),
+        }
+    }
+    Ok(strings)
+}
+
+/// Test points of a TAP document
+pub struct Document {
+    pub tests: Vec<Test>,
+    /// Differences between the plans of the document and its subtests and the number of tests
+    /// they ran. The mismatches of the subtests are also reported as warnings of the subtests.
+    pub plan_mismatches: Vec<String>,
+    /// Document and subtests skipped entirely, with a `1..0 # SKIP <reason>` plan
+    pub skipped_suites: Vec<SkippedSuite>,
+}
+
+/// (Sub)document whose plan skips all its tests
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SkippedSuite {
+    /// Name of the subtest, `None` for the whole document
+    pub name: Option<String>,
+    pub reason: Option<String>,
+}
+
+/// Reason of the plan of a (sub)document skipping all its tests, `Some(None)` if it gives none
+fn skip_all(statements: &[TapStatement]) -> Option<Option<String>> {
+    let plan = statements.iter().find_map(|s| match s {
+        TapStatement::Plan(p) if p.count == 0 => Some(p),
+        _ => None,
+    })?;
+    let reason = plan.reason.map(|reason| {
+        // The directive is case insensitive, e.g. `# SKIP no database` or `# skip`
+        match reason
+            .get(..4)
+            .is_some_and(|d| d.eq_ignore_ascii_case("skip"))
+        {
+            true => reason[4..].trim(),
+            false => reason,
+        }
+    });
+    Some(reason.filter(|r| !r.is_empty()).map(ToOwned::to_owned))
+}
+
+/// Skipped suites of a document and all its subtests
+fn skipped_suites(statements: &[TapStatement], name: Option<&str>) -> Vec<SkippedSuite> {
+    let mut suites = Vec::from_iter(skip_all(statements).map(|reason| SkippedSuite {
+        name: name.map(ToOwned::to_owned),
+        reason,
+    }));
+    for statement in statements {
+        if let TapStatement::Subtest(s) = statement {
+            suites.extend(skipped_suites(
+                &s.statements,
+                Some(s.name.unwrap_or("unnamed")),
+            ));
+        }
+    }
+    suites
+}
+
+/// Describes the difference between the plan of a (sub)document and the number of tests it ran
+fn plan_mismatch(statements: &[TapStatement]) -> Option<String> {
+    let planned = statements.iter().find_map(|s| match s {
+        TapStatement::Plan(p) => Some(p.count),
+        _ => None,
+    })?;
+    let ran = statements
+        .iter()
+        .filter(|s| matches!(s, TapStatement::TestPoint(_) | TapStatement::Subtest(_)))
+        .count();
+    (planned != ran).then(|| format!("Planned {planned} tests but ran {ran}"))
+}
+
+/// Plan mismatches of a document and all its subtests
+fn plan_mismatches(statements: &[TapStatement]) -> Vec<String> {
+    let mut mismatches = Vec::from_iter(plan_mismatch(statements));
+    for statement in statements {
+        if let TapStatement::Subtest(s) = statement {
+            let name = s.name.unwrap_or("unnamed");
+            mismatches.extend(
+                plan_mismatches(&s.statements)
+                    .into_iter()
+                    .map(|m| format!("Subtest `{name}`: {m}")),
+            );
+        }
+    }
+    mismatches
+}
+
+/// Parses a TAP document into its test points, flattening the subtests.
+///
+/// The location of the failures is extracted from their diagnostics with the `filter`, if any.
+pub fn parse_tap(tap: &str, filter: &Option<Filter>) -> anyhow::Result<Document> {
+    let mut parser = TapParser::new();
+    let document = parser.parse(tap)?;
+    let mismatches = plan_mismatches(&document);
+    let skipped = skipped_suites(&document, None);
+
+    fn handle_body<'a, 'f: 'a>(
+        body: Vec<TapStatement<'a>>,
+        parents: Vec<usize>,
+        filter: &'f Option<Filter>,
+    ) -> impl Iterator<Item = Test> + 'a {
+        body.into_iter()
+            .enumerate()
+            .flat_map(move |(i, st)| handle_statement(st, i, parents.clone(), filter))
+    }
+
+    fn handle_statement<'a, 'f: 'a>(
+        statement: TapStatement<'a>,
+        number: usize,
+        parents: Vec<usize>,
+        filter: &'f Option<Filter>,
+    ) -> impl Iterator<Item = Test> + 'a {
+        fn handle_test_point(
+            test: TapTest,
+            parents: Vec<usize>,
+            number: usize,
+            filter: &Option<Filter>,
+        ) -> Test {
+            let mut warnings = Vec::new();
+            let yaml = test.yaml.join("\n");
+            let location = match filter {
+                Some(f) if !yaml.is_empty() => {
+                    match serde_yaml::from_str::<serde_yaml::Value>(&yaml) {
+                        Ok(v) => {
+                            let json = serde_json::to_value(&v)
+                                .expect("Could not parse back YAML into JSON");
+                            let inputs = jaq_core::RcIter::new(core::iter::empty());
+                            let mut out =
+                                f.run(jaq_core::Ctx::new([], &inputs), jaq_core::Val::from(json));
+                            match out.next().map(|v| v.map(|r| r.to_str().map(|s| s.parse()))) {
+                                None => None,
+                                Some(Err(e)) | Some(Ok(Err(e))) => {
+                                    warnings.push(format!("Location filter: {e}"));
+                                    None
+                                }
+                                Some(Ok(Ok(Err(e)))) => {
+                                    warnings.push(format!("Invalid location: {e}"));
+                                    None
+                                }
+                                Some(Ok(Ok(Ok(v)))) => Some(v),
+                            }
+                        }
+                        Err(e) => {
+                            warnings.push(format!("Invalid YAML diagnostics: {e}"));
+                            None
+                        }
+                    }
+                }
+                _ => None,
+            };
+            let tags = yaml_strings(&yaml, "tags");
+            Test {
+                result: test.result,
+                number: test.number.unwrap_or(number),
+                desc: test.desc.map(ToString::to_string),
+                directive: test.directive.as_ref().map(|d| Directive {
+                    key: match &d.kind {
+                        DirectiveKind::Skip => DirectiveKind::Skip,
+                        DirectiveKind::Todo => DirectiveKind::Todo,
+                    },
+                    reason: d.reason.map(ToString::to_string),
+                }),
+                yaml,
+                location,
+                category: None,
+                tags,
+                warnings,
+                parents: parents.to_vec(),
+            }
+        }
+
+        match statement {
+            TapStatement::Subtest(s) => {
+                let mut child_lineage = parents.to_vec();
+                child_lineage.push(number);
+                let mismatch = plan_mismatch(&s.statements);
+                let b: Box<dyn Iterator<Item = _>> =
+                    Box::new(handle_body(s.statements, child_lineage, filter));
+                let mut ending = handle_test_point(s.ending, parents, number, filter);
+                ending.warnings.extend(mismatch);
+                Either3::One(b.chain(std::iter::once(ending)))
+            }
+            TapStatement::TestPoint(t) => Either3::Two(std::iter::once(handle_test_point(
+                t, parents, number, filter,
+            ))),
+            _ => Either3::Three(std::iter::empty()),
+        }
+    }
+
+    Ok(Document {
+        tests: handle_body(document, Vec::new(), filter).collect(),
+        plan_mismatches: mismatches,
+        skipped_suites: skipped,
+    })
+}
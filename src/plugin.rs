@@ -0,0 +1,122 @@
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Stdio},
+    sync::mpsc::{self, Receiver},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Event sent to the plugins, as one JSON object per line
+#[derive(Serialize, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    RunStarted {
+        profile: Option<&'a str>,
+    },
+    Test {
+        number: &'a str,
+        desc: Option<&'a str>,
+        result: &'static str,
+        yaml: &'a str,
+    },
+    RunFinished {
+        total: usize,
+        passed: usize,
+        failed: usize,
+        skipped: usize,
+        todo: usize,
+    },
+}
+
+/// Command emitted by a plugin, as one JSON object per line
+#[derive(Deserialize, Debug)]
+#[serde(tag = "command", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Command {
+    /// Shows a message to the user
+    Toast { message: String },
+    /// Attaches a note to the test with the hierarchical number `test`, until the next run
+    Annotate { test: String, text: String },
+}
+
+/// An executable receiving the events on its stdin and emitting commands on its stdout
+pub struct Plugin {
+    pub command: String,
+    child: Child,
+    stdin: ChildStdin,
+    commands: Receiver<Result<Command, String>>,
+}
+
+impl Plugin {
+    pub fn spawn(command: &str, env: &BTreeMap<String, String>) -> std::io::Result<Self> {
+        let mut child = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .envs(env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        let (tx, commands) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    return;
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let command = serde_json::from_str(&line)
+                    .map_err(|e| format!("Invalid plugin command `{line}`: {e}"));
+                if tx.send(command).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Ok(Self {
+            command: command.to_owned(),
+            child,
+            stdin,
+            commands,
+        })
+    }
+
+    pub fn send(&mut self, event: &Event) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+        self.stdin.write_all(&line)?;
+        self.stdin.flush()
+    }
+
+    /// Commands emitted since the last call
+    pub fn poll(&self) -> Vec<Result<Command, String>> {
+        self.commands.try_iter().collect()
+    }
+}
+
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Sends `events` to the `plugins`, stopping the ones that can't receive them. Returns the
+/// errors of the stopped plugins.
+pub fn broadcast(plugins: &mut Vec<Plugin>, events: &[Event]) -> Vec<String> {
+    let mut errors = Vec::new();
+    plugins.retain_mut(
+        |plugin| match events.iter().try_for_each(|e| plugin.send(e)) {
+            Ok(()) => true,
+            Err(e) => {
+                errors.push(format!("Plugin `{}` stopped: {e}", plugin.command));
+                false
+            }
+        },
+    );
+    errors
+}
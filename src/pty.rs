@@ -0,0 +1,88 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+};
+
+/// Pseudo-terminal the test command is run in with `--pty`, for the harnesses which only color
+/// their output or print some diagnostics when writing to a terminal
+pub struct Pty {
+    /// Side read by tap_runner
+    pub master: Master,
+    /// Side given to the command as its stdout
+    pub slave: File,
+}
+
+/// Reader of the output of the command, ending once no process has the terminal open anymore
+pub struct Master(File);
+
+impl Read for Master {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.0.read(buf) {
+            // Linux reports that the other side was closed this way
+            #[cfg(unix)]
+            Err(e) if e.raw_os_error() == Some(libc::EIO) => Ok(0),
+            read => read,
+        }
+    }
+}
+
+/// Opens a pseudo-terminal of the size of the current terminal
+#[cfg(unix)]
+pub fn open() -> io::Result<Pty> {
+    use std::{
+        ffi::{CStr, OsStr},
+        fs::OpenOptions,
+        os::unix::{
+            ffi::OsStrExt,
+            fs::OpenOptionsExt,
+            io::{AsRawFd, FromRawFd},
+        },
+    };
+
+    let check = |result: libc::c_int| match result {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(result),
+    };
+    // SAFETY: the descriptor is owned by the file as soon as it is opened, and the name returned
+    // by `ptsname` is copied before any other call can overwrite it
+    let (master, path) = unsafe {
+        let fd = check(libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY))?;
+        let master = File::from_raw_fd(fd);
+        check(libc::grantpt(fd))?;
+        check(libc::unlockpt(fd))?;
+        let name = libc::ptsname(fd);
+        if name.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let path = OsStr::from_bytes(CStr::from_ptr(name).to_bytes()).to_owned();
+        (master, path)
+    };
+
+    let (cols, rows) = crossterm::terminal::size().unwrap_or((80, 24));
+    let size = libc::winsize {
+        ws_row: rows,
+        ws_col: cols,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: `size` outlives the call
+    check(unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &size) })?;
+
+    let slave = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_NOCTTY)
+        .open(path)?;
+    Ok(Pty {
+        master: Master(master),
+        slave,
+    })
+}
+
+#[cfg(not(unix))]
+pub fn open() -> io::Result<Pty> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "pseudo-terminals are only supported on Unix",
+    ))
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+/// Raw TAP document of the results shown, toggled with `V`
+pub struct RawView {
+    pub lines: Vec<String>,
+    /// Line of each test point, by hierarchical number
+    pub tests: HashMap<String, usize>,
+    pub scroll: usize,
+}
+
+impl RawView {
+    pub fn new(tap: &str) -> Self {
+        RawView {
+            lines: tap.lines().map(str::to_owned).collect(),
+            tests: test_lines(tap),
+            scroll: 0,
+        }
+    }
+
+    /// Shows another document, keeping the scroll position
+    pub fn replace(&mut self, tap: &str) {
+        let scroll = self.scroll;
+        *self = RawView::new(tap);
+        self.scroll = scroll.min(self.lines.len().saturating_sub(1));
+    }
+}
+
+/// Lines of the test points of `tap`, by hierarchical number. The subtests are indented by four
+/// spaces and their test points come before the one ending them, giving their number.
+pub fn test_lines(tap: &str) -> HashMap<String, usize> {
+    // Test points of each depth not yet attached to their parent, with their path relative to it
+    let mut pending: Vec<Vec<(String, usize)>> = Vec::new();
+    // Test points seen at each depth, numbering the ones without a number
+    let mut counts: Vec<usize> = Vec::new();
+    for (index, line) in tap.lines().enumerate() {
+        let content = line.trim_start_matches(' ');
+        let depth = (line.len() - content.len()) / 4;
+        let Some(rest) = content
+            .strip_prefix("not ok")
+            .or_else(|| content.strip_prefix("ok"))
+        else {
+            continue;
+        };
+        if !(rest.is_empty() || rest.starts_with(' ')) {
+            continue;
+        }
+        if pending.len() <= depth + 1 {
+            pending.resize_with(depth + 2, Vec::new);
+            counts.resize(depth + 2, 0);
+        }
+        counts[depth] += 1;
+        let number = rest
+            .split_whitespace()
+            .next()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(counts[depth]);
+
+        let children = std::mem::take(&mut pending[depth + 1]);
+        counts[depth + 1] = 0;
+        pending[depth].push((number.to_string(), index));
+        pending[depth].extend(
+            children
+                .into_iter()
+                .map(|(path, line)| (format!("{number}.{path}"), line)),
+        );
+    }
+    pending
+        .into_iter()
+        .next()
+        .unwrap_or_default()
+        .into_iter()
+        .collect()
+}
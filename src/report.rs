@@ -0,0 +1,111 @@
+use std::path::Path;
+
+use serde::Serialize;
+use tap_parser::DirectiveKind;
+
+use crate::{storage::Storage, Summary, Test};
+
+/// Results of a run, as written by `--report-json`
+#[derive(Serialize)]
+struct Report<'a> {
+    summary: SummaryReport,
+    plan_mismatches: &'a [String],
+    tests: Vec<TestReport<'a>>,
+}
+
+#[derive(Serialize)]
+struct SummaryReport {
+    total: usize,
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+    todo: usize,
+    /// Tests marked as TODO which passed
+    bonus: usize,
+    /// Document and subtests skipped entirely by their plan
+    skipped_suites: usize,
+}
+
+#[derive(Serialize)]
+struct TestReport<'a> {
+    /// Hierarchical number, e.g. `2.1.3`
+    id: String,
+    /// Number of the test in its (sub)document
+    number: usize,
+    parents: &'a [usize],
+    desc: Option<&'a str>,
+    ok: bool,
+    status: &'static str,
+    directive: Option<DirectiveReport<'a>>,
+    yaml: &'a str,
+    /// The YAML diagnostics as JSON, `null` if there are none or they are invalid
+    diagnostics: serde_json::Value,
+    location: Option<LocationReport<'a>>,
+    category: Option<&'a str>,
+    tags: &'a [String],
+    warnings: &'a [String],
+}
+
+#[derive(Serialize)]
+struct DirectiveReport<'a> {
+    kind: &'static str,
+    reason: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct LocationReport<'a> {
+    file: &'a str,
+    line: usize,
+}
+
+impl<'a> From<&'a Test> for TestReport<'a> {
+    fn from(test: &'a Test) -> Self {
+        TestReport {
+            id: test.hierarchical_number(),
+            number: test.number,
+            parents: &test.parents,
+            desc: test.desc.as_deref(),
+            ok: test.result,
+            status: test.status().name(),
+            directive: test.directive.as_ref().map(|d| DirectiveReport {
+                kind: match d.key {
+                    DirectiveKind::Skip => "skip",
+                    DirectiveKind::Todo => "todo",
+                },
+                reason: d.reason.as_deref(),
+            }),
+            yaml: &test.yaml,
+            diagnostics: serde_yaml::from_str(&test.yaml).unwrap_or_default(),
+            location: test.location.as_ref().map(|l| LocationReport {
+                file: &l.file,
+                line: l.line,
+            }),
+            category: test.category.as_deref(),
+            tags: &test.tags,
+            warnings: &test.warnings,
+        }
+    }
+}
+
+/// Writes the `tests` of a run and their `summary` to `path` as JSON
+pub fn write(
+    path: &Path,
+    tests: &[Test],
+    summary: Summary,
+    plan_mismatches: &[String],
+) -> anyhow::Result<()> {
+    let report = Report {
+        summary: SummaryReport {
+            total: summary.total,
+            passed: summary.passed,
+            failed: summary.failed,
+            skipped: summary.skipped,
+            todo: summary.todo,
+            bonus: summary.bonus,
+            skipped_suites: summary.skipped_suites,
+        },
+        plan_mismatches,
+        tests: tests.iter().map(TestReport::from).collect(),
+    };
+    Storage::write(path, serde_json::to_vec_pretty(&report)?)
+}
@@ -0,0 +1,96 @@
+use std::path::Path;
+
+use anyhow::Context;
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+
+use crate::{Location, Test};
+
+/// User script processing the results, which can define the following functions:
+///
+/// - `on_test(test)`: called for each test, may return a map overriding its `desc` or
+///   `location` (as `"file:line"`)
+/// - `classify(test)`: returns the category of the test, displayed along with it
+///
+/// `test` is a map with the `number`, `desc`, `result` (`"pass"`, `"fail"` or `"skip"`), `yaml`
+/// and `diagnostics` (the parsed YAML) of the test, and its `location` if one was found.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    on_test: bool,
+    classify: bool,
+}
+
+impl Script {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let engine = Engine::new();
+        let ast = engine
+            .compile_file(path.to_owned())
+            .map_err(|e| anyhow::anyhow!("{e}"))
+            .with_context(|| format!("Could not load script {}", path.display()))?;
+
+        let defines = |name: &str| {
+            ast.iter_functions()
+                .any(|f| f.name == name && f.params.len() == 1)
+        };
+        let on_test = defines("on_test");
+        let classify = defines("classify");
+
+        Ok(Self {
+            on_test,
+            classify,
+            engine,
+            ast,
+        })
+    }
+
+    fn call(&self, name: &str, test: &Test) -> anyhow::Result<Dynamic> {
+        self.engine
+            .call_fn(&mut Scope::new(), &self.ast, name, (to_map(test),))
+            .map_err(|e| anyhow::anyhow!("Error in `{name}` of the script: {e}"))
+    }
+
+    pub fn apply(&self, test: &mut Test) -> anyhow::Result<()> {
+        if self.on_test {
+            if let Some(map) = self.call("on_test", test)?.try_cast::<Map>() {
+                if let Some(desc) = map.get("desc") {
+                    test.desc = desc.clone().into_string().ok();
+                }
+                if let Some(location) = map.get("location") {
+                    test.location = match location.clone().into_string() {
+                        Ok(l) => Some(l.parse::<Location>()?),
+                        Err(_) => None,
+                    };
+                }
+            }
+        }
+
+        if self.classify {
+            test.category = self.call("classify", test)?.into_string().ok();
+        }
+
+        Ok(())
+    }
+}
+
+fn to_map(test: &Test) -> Map {
+    let mut map = Map::new();
+    map.insert("number".into(), test.hierarchical_number().into());
+    map.insert(
+        "desc".into(),
+        test.desc.clone().map(Dynamic::from).unwrap_or_default(),
+    );
+    map.insert("result".into(), test.status().name().into());
+    map.insert("yaml".into(), test.yaml.clone().into());
+    let diagnostics = serde_yaml::from_str::<serde_yaml::Value>(&test.yaml)
+        .ok()
+        .and_then(|v| rhai::serde::to_dynamic(v).ok())
+        .unwrap_or_default();
+    map.insert("diagnostics".into(), diagnostics);
+    if let Some(location) = &test.location {
+        map.insert(
+            "location".into(),
+            format!("{}:{}", location.file, location.line).into(),
+        );
+    }
+    map
+}
@@ -0,0 +1,44 @@
+use crate::{Test, TestResult};
+
+/// Search of the tests by number or description, started with `/`
+pub struct Search {
+    pub query: String,
+    /// The query is being typed
+    pub editing: bool,
+    /// Search all the tests instead of only the failing ones, toggled with `<tab>`
+    pub all: bool,
+}
+
+impl Search {
+    pub fn new(all: bool) -> Self {
+        Self {
+            query: String::new(),
+            editing: true,
+            all,
+        }
+    }
+
+    /// Whether `test` matches the query, ignoring the case of the description
+    pub fn matches(&self, test: &Test) -> bool {
+        if self.query.is_empty() || (!self.all && test.status() != TestResult::Fail) {
+            return false;
+        }
+
+        let query = self.query.to_lowercase();
+        test.hierarchical_number() == self.query
+            || test
+                .desc
+                .as_deref()
+                .is_some_and(|d| d.to_lowercase().contains(&query))
+    }
+
+    /// Text of the search bar
+    pub fn prompt(&self, matches: usize) -> String {
+        let scope = match self.all {
+            true => "all tests",
+            false => "failures",
+        };
+        let cursor = if self.editing { "█" } else { "" };
+        format!("/{}{cursor}  [{scope}, {matches} matches]", self.query)
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::storage::Storage;
+
+const SESSION_FILE: &str = "session.json";
+
+/// UI state of a project, restored on the next launch
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Session {
+    /// Active profile
+    pub profile: Option<String>,
+    /// Hierarchical number of the selected failure
+    pub selected: Option<String>,
+    /// Percentage of the width given to the preview, when resized with `<` and `>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview_ratio: Option<u16>,
+    /// Tag the failures are filtered by, selected with `#`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Query of the search, and whether it searches all the tests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search: Option<(String, bool)>,
+    /// Tree view, if it is open
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tree: Option<TreeSession>,
+    #[serde(default)]
+    pub scroll: Scroll,
+}
+
+/// State of the tree view opened with `T`
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct TreeSession {
+    /// Subtests collapsed or expanded by the user, by hierarchical number
+    pub collapsed: BTreeMap<String, bool>,
+    /// Hierarchical number of the selected test
+    pub selected: Option<String>,
+}
+
+/// Scroll positions of the lists and of the preview
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Scroll {
+    /// First failure shown in the list
+    pub failures: usize,
+    /// First row shown in the tree view
+    pub tree: usize,
+    /// Lines the preview is scrolled by from the failing line
+    pub preview: i32,
+}
+
+impl Session {
+    /// Loads the last saved session, a missing or invalid session is treated as empty
+    pub fn load(storage: &Storage) -> Self {
+        std::fs::read(storage.state_path(SESSION_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_slice(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, storage: &Storage) -> anyhow::Result<()> {
+        Storage::write(&storage.state_path(SESSION_FILE), serde_json::to_vec(self)?)
+    }
+}
@@ -0,0 +1,177 @@
+use std::{collections::BTreeMap, fmt, str::FromStr, sync::mpsc};
+
+use anyhow::Context as _;
+
+use crate::source::{self, emit, indent, passed, Context, TapSource};
+
+/// Part of the tests run by a command, `index` being between 1 and `count`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shard {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl FromStr for Shard {
+    type Err = anyhow::Error;
+
+    fn from_str(shard: &str) -> anyhow::Result<Self> {
+        let parsed = shard.split_once('/').and_then(|(index, count)| {
+            Some((index.trim().parse().ok()?, count.trim().parse().ok()?))
+        });
+        match parsed {
+            Some((index, count)) if (1..=count).contains(&index) => Ok(Shard { index, count }),
+            _ => anyhow::bail!("Invalid shard `{shard}`, expected `<index>/<count>` such as `2/8`"),
+        }
+    }
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+impl Shard {
+    /// Replaces `{shard}`, `{shard_index}` and `{shard_count}` in the arguments of `command`
+    pub fn command(&self, command: &[String]) -> Vec<String> {
+        command
+            .iter()
+            .map(|arg| {
+                arg.replace("{shard}", &self.to_string())
+                    .replace("{shard_index}", &self.index.to_string())
+                    .replace("{shard_count}", &self.count.to_string())
+            })
+            .collect()
+    }
+
+    /// Adds the `TAP_SHARD`, `TAP_SHARD_INDEX` and `TAP_SHARD_COUNT` variables to `env`
+    pub fn env(&self, env: &mut BTreeMap<String, String>) {
+        env.insert("TAP_SHARD".to_owned(), self.to_string());
+        env.insert("TAP_SHARD_INDEX".to_owned(), self.index.to_string());
+        env.insert("TAP_SHARD_COUNT".to_owned(), self.count.to_string());
+    }
+}
+
+/// Runs all the shards of the test command in parallel, the document of each shard becoming a
+/// subtest, shown once the shard completes
+pub struct Shards {
+    /// Source of each shard
+    sources: Vec<Box<dyn TapSource>>,
+}
+
+/// Runs the shards of the source of `spec` in parallel, if there are several
+pub fn wrap(
+    spec: &str,
+    source: Box<dyn TapSource>,
+    count: Option<usize>,
+) -> anyhow::Result<Box<dyn TapSource>> {
+    let Some(count) = count.filter(|&c| c > 1) else {
+        return Ok(source);
+    };
+    let mut sources = vec![source];
+    for _ in 1..count {
+        sources.push(source::from_spec(spec)?);
+    }
+    Ok(Box::new(Shards { sources }))
+}
+
+impl Shards {
+    fn shard(&self, index: usize) -> Shard {
+        Shard {
+            index: index + 1,
+            count: self.sources.len(),
+        }
+    }
+}
+
+impl TapSource for Shards {
+    fn needs_command(&self) -> bool {
+        true
+    }
+
+    fn describe(&self, context: &Context) -> String {
+        let shard = self.shard(0);
+        let command = shard.command(context.command);
+        format!(
+            "{} shards in parallel: {}",
+            shard.count,
+            self.sources[0].describe(&Context {
+                command: &command,
+                ..*context
+            })
+        )
+    }
+
+    fn read(&mut self, context: &Context) -> anyhow::Result<String> {
+        let count = self.sources.len();
+        let mut tap = String::new();
+        emit(&mut tap, context, "TAP version 14".to_owned());
+        emit(&mut tap, context, format!("1..{count}"));
+
+        let shards = (0..count).map(|i| self.shard(i)).collect::<Vec<_>>();
+        let mut documents = vec![None; count];
+        std::thread::scope(|scope| -> anyhow::Result<()> {
+            let (tx, done) = mpsc::channel();
+            for ((index, source), shard) in self.sources.iter_mut().enumerate().zip(&shards) {
+                let tx = tx.clone();
+                scope.spawn(move || {
+                    let command = shard.command(context.command);
+                    let mut env = context.env.clone();
+                    shard.env(&mut env);
+                    // The lines of the shards are interleaved, each document is only sent once
+                    // complete
+                    let document = source.read(&Context {
+                        command: &command,
+                        env: &env,
+                        lines: None,
+                        ..*context
+                    });
+                    let _ = tx.send((index, document));
+                });
+            }
+            drop(tx);
+
+            // Numbered in the order they complete while running, like the live view expects
+            for (completed, (index, document)) in done.into_iter().enumerate() {
+                let shard = shards[index];
+                let document =
+                    document.with_context(|| format!("Could not run the shard {shard}"))?;
+                if let Some(lines) = context.lines {
+                    for line in subtest(completed + 1, &shard, &document) {
+                        let _ = lines.send(line);
+                    }
+                }
+                documents[index] = Some(document);
+            }
+            Ok(())
+        })?;
+
+        // The subtests of the document are in the order of the shards
+        for (index, document) in documents.into_iter().enumerate() {
+            let document = document.expect("all the shards were read");
+            for line in subtest(index + 1, &shards[index], &document) {
+                tap.push_str(&line);
+                tap.push('\n');
+            }
+        }
+        Ok(tap)
+    }
+}
+
+/// Lines of the subtest numbered `number` of the `document` of a shard
+fn subtest(number: usize, shard: &Shard, document: &str) -> Vec<String> {
+    let name = format!("shard {shard}");
+    let ending = match passed(document) {
+        true => "ok",
+        false => "not ok",
+    };
+    std::iter::once(format!("# Subtest: {name}"))
+        .chain(
+            document
+                .lines()
+                .filter(|l| !l.starts_with("TAP version"))
+                .map(indent),
+        )
+        .chain(std::iter::once(format!("{ending} {number} - {name}")))
+        .collect()
+}
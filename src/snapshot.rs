@@ -0,0 +1,19 @@
+use std::time::SystemTime;
+
+use tap_runner::Summary;
+
+/// Failing test of a snapshot
+pub struct Frozen {
+    pub number: String,
+    pub desc: Option<String>,
+    /// Identity of the test across runs, to find its current result
+    pub key: String,
+}
+
+/// Results frozen with `F`, shown read-only next to the results of the following runs to compare
+/// them before and after a fix
+pub struct Snapshot {
+    pub taken_at: SystemTime,
+    pub summary: Summary,
+    pub failures: Vec<Frozen>,
+}
@@ -0,0 +1,582 @@
+use std::{
+    collections::BTreeMap,
+    fs::Metadata,
+    io::{BufRead, BufReader, ErrorKind, Read, Seek, SeekFrom},
+    net::TcpStream,
+    path::{Path, PathBuf},
+    process::{ChildStderr, Command, ExitStatus, Stdio},
+    sync::mpsc::Sender,
+    time::Duration,
+};
+
+use anyhow::Context as _;
+
+use crate::{ansi, extract, print_streamed, pty, stream, transcript};
+
+/// What the sources need to know about the run
+pub struct Context<'a> {
+    pub command: &'a [String],
+    /// Prefix of the commands run locally, loading the environment of the project
+    pub wrapper: &'a [String],
+    pub env: &'a BTreeMap<String, String>,
+    /// Directory the commands are run in, the current one if unset
+    pub dir: Option<&'a Path>,
+    /// Run the commands in a pseudo-terminal, the escape sequences being stripped from the
+    /// document but not from the lines sent
+    pub pty: bool,
+    /// Extract the document from a log, with the prefix of its lines
+    pub extract: Option<&'a extract::Prefix>,
+    /// Print the tests to stdout as they complete
+    pub stream: bool,
+    /// Receives the lines of the document as they are read
+    pub lines: Option<&'a Sender<String>>,
+    /// Receives the output of the commands on stderr, along with the test points of the lines
+    /// sent
+    pub stderr: Option<&'a Sender<Stderr>>,
+    /// Receives the exit status of the commands run
+    pub status: Option<&'a Sender<ExitStatus>>,
+}
+
+/// Output of the commands on stderr, sent in the order it is read relative to the test points
+/// of the document, for each line to be attached to the test point before it
+pub enum Stderr {
+    /// A test point of the document was read
+    Point,
+    Line(String),
+}
+
+/// Transport the TAP document of a run is obtained from, selected with `--source`. The document
+/// is read on a worker thread, while the TUI keeps running.
+pub trait TapSource: Send {
+    /// Whether the source runs the test command, which must then be configured
+    fn needs_command(&self) -> bool {
+        false
+    }
+
+    /// Where the document is read from, for the transcript
+    fn describe(&self, context: &Context) -> String;
+
+    /// Reads the TAP document of a run
+    fn read(&mut self, context: &Context) -> anyhow::Result<String>;
+
+    /// Whether the source keeps producing documents, the next run starting as soon as one is read
+    fn continuous(&self) -> bool {
+        false
+    }
+}
+
+type Constructor = fn(Option<&str>) -> anyhow::Result<Box<dyn TapSource>>;
+
+/// Sources available to `--source`, by name, with a description of their argument
+const REGISTRY: &[(&str, Option<&str>, Constructor)] = &[
+    ("command", None, |_| Ok(Box::new(CommandSource))),
+    ("stdin", None, |_| Ok(Box::<Stdin>::default())),
+    ("file", Some("path"), |arg| {
+        Ok(Box::new(File(PathBuf::from(arg.unwrap()))))
+    }),
+    ("follow", Some("path"), |arg| {
+        Ok(Box::new(Follow::new(PathBuf::from(arg.unwrap()))))
+    }),
+    ("tcp", Some("host:port"), |arg| {
+        Ok(Box::new(Tcp(arg.unwrap().to_owned())))
+    }),
+    ("serial", Some("device"), |arg| {
+        Ok(Box::new(Serial(PathBuf::from(arg.unwrap()))))
+    }),
+    ("ssh", Some("host"), |arg| {
+        Ok(Box::new(Ssh(arg.unwrap().to_owned())))
+    }),
+];
+
+/// Creates the source described by `spec`, as `<name>` or `<name>:<argument>`
+pub fn from_spec(spec: &str) -> anyhow::Result<Box<dyn TapSource>> {
+    let (name, arg) = match spec.split_once(':') {
+        Some((name, arg)) => (name, Some(arg)),
+        None => (spec, None),
+    };
+    let Some((_, expected, constructor)) = REGISTRY.iter().find(|(n, _, _)| *n == name) else {
+        anyhow::bail!(
+            "Unknown source `{name}`, expected one of: {}",
+            REGISTRY
+                .iter()
+                .map(|(name, arg, _)| match arg {
+                    Some(arg) => format!("{name}:<{arg}>"),
+                    None => name.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    };
+    match (expected, arg) {
+        (Some(expected), None | Some("")) => {
+            anyhow::bail!("The `{name}` source requires an argument: {name}:<{expected}>")
+        }
+        (None, Some(_)) => anyhow::bail!("The `{name}` source takes no argument"),
+        _ => constructor(arg),
+    }
+}
+
+/// When the reading of a document stops
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Until {
+    /// At the end of the input
+    Eof,
+    /// Also at a `Bail out!` or once all the tests of a leading plan ran, which lets the sources
+    /// that are never closed (such as serial ports) end a run
+    Complete,
+    /// Like `Complete`, but waiting for more lines when the input has none available yet
+    /// (`WouldBlock`) instead of ending the document
+    Follow,
+}
+
+/// Reads the lines of a TAP document, until the end given by `until`, printing the tests as they
+/// complete if requested by the `context`
+fn read_document(
+    mut reader: impl BufRead,
+    context: &Context,
+    until: Until,
+) -> anyhow::Result<String> {
+    let stream = context.stream;
+    // Only locked when streaming, as the TUI draws to stdout while the document is read
+    let mut out = stream.then(|| std::io::stdout().lock());
+    let mut streamed = stream::LineReader::default();
+    let mut extractor = context.extract.map(extract::Extractor::new);
+    let mut tap = String::new();
+    let mut plan = None;
+    let mut tests = 0;
+    let mut line = String::new();
+    loop {
+        match reader.read_line(&mut line) {
+            Ok(0) if line.is_empty() => break,
+            Ok(_) => (),
+            // The line read so far is kept, and completed once more input is available
+            Err(e) if e.kind() == ErrorKind::WouldBlock && until == Until::Follow => {
+                std::thread::sleep(FOLLOW_INTERVAL);
+                continue;
+            }
+            // Sources with a read timeout report the end of their output this way
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => break,
+            Err(e) => return Err(e.into()),
+        }
+        let read = std::mem::take(&mut line);
+        let read = read.trim_end_matches(['\n', '\r']);
+        let stripped = match context.pty {
+            true => ansi::strip(read),
+            false => read.into(),
+        };
+        let line = match &mut extractor {
+            Some(extractor) => match extractor.line(&stripped) {
+                Some(line) => line,
+                None => continue,
+            },
+            None => stripped.as_ref(),
+        };
+        if let Some(lines) = context.lines {
+            // The colored line is sent when it is the line of the document
+            let sent = match line == stripped.as_ref() {
+                true => read,
+                false => line,
+            };
+            // The receivers are only gone if the run was abandoned
+            let _ = lines.send(sent.to_owned());
+            if let Some(stderr) = context.stderr.filter(|_| is_test_point(line)) {
+                let _ = stderr.send(Stderr::Point);
+            }
+        }
+        if let Some(out) = &mut out {
+            if let Some(test) = streamed.push(line) {
+                print_streamed(out, &test)?;
+            }
+        }
+        tap.push_str(line);
+        tap.push('\n');
+
+        if until == Until::Eof {
+            continue;
+        }
+        if line.starts_with("Bail out!") {
+            break;
+        }
+        if let Some(count) = line.strip_prefix("1..") {
+            let count = count.split_whitespace().next().and_then(|c| c.parse().ok());
+            match tests {
+                0 => plan = count,
+                _ => break,
+            }
+        } else if line.starts_with("ok") || line.starts_with("not ok") {
+            tests += 1;
+        }
+        if plan.is_some_and(|p| tests >= p) {
+            // Waits for the end of the diagnostics of the last test, if it has any
+            let done = match line.starts_with(char::is_whitespace) {
+                true => line.trim() == "...",
+                false => !continues_with_yaml(&mut reader)?,
+            };
+            if done {
+                break;
+            }
+        }
+    }
+    if let Some(out) = &mut out {
+        if let Some(test) = streamed.finish() {
+            print_streamed(out, &test)?;
+        }
+    }
+    Ok(tap)
+}
+
+/// Whether `line` is a test point, of the document or of a subtest
+fn is_test_point(line: &str) -> bool {
+    let content = line.trim_start();
+    content
+        .strip_prefix("not ok")
+        .or_else(|| content.strip_prefix("ok"))
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+}
+
+/// Whether the next line is indented, i.e. part of the diagnostics of the last test
+fn continues_with_yaml(reader: &mut impl BufRead) -> anyhow::Result<bool> {
+    match reader.fill_buf() {
+        Ok(buf) => Ok(buf
+            .first()
+            .is_some_and(|b| b.is_ascii_whitespace() && *b != b'\n')),
+        Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Runs `line` and reads its stdout
+fn run(line: &[String], context: &Context) -> anyhow::Result<String> {
+    let mut command = Command::new(&line[0]);
+    if let Some(dir) = context.dir {
+        command.current_dir(dir);
+    }
+    command
+        .args(&line[1..])
+        .envs(context.env)
+        .stdin(Stdio::null())
+        .stderr(match context.stderr {
+            Some(_) => Stdio::piped(),
+            None => Stdio::null(),
+        });
+    let spawn = |command: &mut Command| {
+        command
+            .spawn()
+            .with_context(|| format!("Could not run `{}`", line[0]))
+    };
+
+    let (mut child, stdout): (_, Box<dyn Read>) = match context.pty {
+        true => {
+            let pty = pty::open().context("Could not open a pseudo-terminal")?;
+            command.stdout(pty.slave);
+            let child = spawn(&mut command)?;
+            // Closes the terminal on this side, for the reading to end once the command exits
+            drop(command);
+            (child, Box::new(pty.master))
+        }
+        false => {
+            let mut child = spawn(command.stdout(Stdio::piped()))?;
+            let stdout = child.stdout.take().expect("stdout is piped");
+            (child, Box::new(stdout))
+        }
+    };
+    let stderr = child.stderr.take();
+    let tap = std::thread::scope(|scope| {
+        if let (Some(stderr), Some(tx)) = (stderr, context.stderr) {
+            scope.spawn(move || forward_stderr(stderr, tx));
+        }
+        read_document(BufReader::new(stdout), context, Until::Eof)
+    })?;
+    let status = child.wait()?;
+    if let Some(tx) = context.status {
+        let _ = tx.send(status);
+    }
+    Ok(tap)
+}
+
+/// Sends the lines written by a command on stderr, without their escape sequences
+fn forward_stderr(stderr: ChildStderr, tx: &Sender<Stderr>) {
+    let mut reader = BufReader::new(stderr);
+    let mut line = Vec::new();
+    while matches!(reader.read_until(b'\n', &mut line), Ok(n) if n != 0) {
+        let text = String::from_utf8_lossy(&line);
+        let text = ansi::strip(text.trim_end_matches(['\n', '\r'])).into_owned();
+        if tx.send(Stderr::Line(text)).is_err() {
+            return;
+        }
+        line.clear();
+    }
+}
+
+/// Whether no test of the `document` failed and it did not bail out, the failing `TODO` tests
+/// being expected to
+pub fn passed(document: &str) -> bool {
+    if document.lines().any(|line| line.starts_with("Bail out!")) {
+        return false;
+    }
+    let mut reader = stream::LineReader::default();
+    let mut tests = document
+        .lines()
+        .filter_map(|line| reader.push(line))
+        .collect::<Vec<_>>();
+    tests.extend(reader.finish());
+    tests.iter().all(|test| test.ok || test.is_todo())
+}
+
+/// Adds a line to a document combining the documents of several runs as subtests, also sent to
+/// the TUI
+pub fn emit(tap: &mut String, context: &Context, line: String) {
+    if let Some(lines) = context.lines {
+        let _ = lines.send(line.clone());
+        if let Some(stderr) = context.stderr.filter(|_| is_test_point(&line)) {
+            let _ = stderr.send(Stderr::Point);
+        }
+    }
+    tap.push_str(&line);
+    tap.push('\n');
+}
+
+/// Indents a line of a document into a subtest
+pub fn indent(line: &str) -> String {
+    match line.is_empty() {
+        true => String::new(),
+        false => format!("    {line}"),
+    }
+}
+
+/// Runs the test command, the default
+struct CommandSource;
+
+impl TapSource for CommandSource {
+    fn needs_command(&self) -> bool {
+        true
+    }
+
+    fn describe(&self, context: &Context) -> String {
+        transcript::command_line(&[context.wrapper, context.command].concat())
+    }
+
+    fn read(&mut self, context: &Context) -> anyhow::Result<String> {
+        let line = [context.wrapper, context.command].concat();
+        run(&line, context)
+    }
+}
+
+/// Reads the document piped to tap_runner once, each run showing it again
+#[derive(Default)]
+struct Stdin {
+    document: Option<String>,
+}
+
+impl TapSource for Stdin {
+    fn describe(&self, _: &Context) -> String {
+        "stdin".into()
+    }
+
+    fn read(&mut self, context: &Context) -> anyhow::Result<String> {
+        if let Some(document) = &self.document {
+            return Ok(document.clone());
+        }
+        let document = read_document(std::io::stdin().lock(), context, Until::Complete)?;
+        Ok(self.document.insert(document).clone())
+    }
+}
+
+/// Reads a file, again on each run
+struct File(PathBuf);
+
+impl TapSource for File {
+    fn describe(&self, _: &Context) -> String {
+        format!("file {}", self.0.display())
+    }
+
+    fn read(&mut self, context: &Context) -> anyhow::Result<String> {
+        let file = std::fs::File::open(&self.0)
+            .with_context(|| format!("Could not open {}", self.0.display()))?;
+        read_document(BufReader::new(file), context, Until::Complete)
+    }
+}
+
+/// Tails a file written by another process, like `tail -F`: each document appended to the file
+/// is a run, and the file being truncated or replaced starts a new document
+struct Follow {
+    path: PathBuf,
+    /// The file, at the start of the next document
+    tail: Option<Tail>,
+    /// Whether the file was opened before, the next file being then read from its start
+    opened: bool,
+}
+
+/// How often a followed file is checked for new lines
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(200);
+
+impl Follow {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            tail: None,
+            opened: false,
+        }
+    }
+
+    /// Opens the file once it exists
+    fn open(&mut self) -> anyhow::Result<Tail> {
+        let mut file = loop {
+            match std::fs::File::open(&self.path) {
+                Ok(file) => break file,
+                Err(e) if e.kind() == ErrorKind::NotFound => std::thread::sleep(FOLLOW_INTERVAL),
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Could not open {}", self.path.display()))
+                }
+            }
+        };
+        // The first file is shown from its last document, the ones replacing it from their start
+        let mut position = 0;
+        if !std::mem::replace(&mut self.opened, true) {
+            let mut content = String::new();
+            file.read_to_string(&mut content)?;
+            position = content
+                .match_indices("TAP version")
+                .map(|(i, _)| i)
+                .filter(|&i| i == 0 || content.as_bytes()[i - 1] == b'\n')
+                .last()
+                .unwrap_or(0) as u64;
+            file.seek(SeekFrom::Start(position))?;
+        }
+        Ok(Tail {
+            file,
+            path: self.path.clone(),
+            position,
+            replaced: false,
+        })
+    }
+}
+
+impl TapSource for Follow {
+    fn describe(&self, _: &Context) -> String {
+        format!("follow {}", self.path.display())
+    }
+
+    fn read(&mut self, context: &Context) -> anyhow::Result<String> {
+        loop {
+            let tail = match self.tail.take() {
+                Some(tail) => tail,
+                None => self.open()?,
+            };
+            let mut reader = BufReader::new(tail);
+            let tap = read_document(&mut reader, context, Until::Follow)?;
+            // The lines buffered after the end of the document are part of the next one
+            let buffered = reader.buffer().len() as u64;
+            let mut tail = reader.into_inner();
+            if !tail.replaced {
+                tail.position -= buffered;
+                tail.file.seek(SeekFrom::Start(tail.position))?;
+                self.tail = Some(tail);
+            }
+            if !tap.trim().is_empty() {
+                return Ok(tap);
+            }
+        }
+    }
+
+    fn continuous(&self) -> bool {
+        true
+    }
+}
+
+/// Reader of a followed file, reporting that no input is available yet at its end, and ending
+/// once the file is truncated or replaced
+struct Tail {
+    file: std::fs::File,
+    path: PathBuf,
+    position: u64,
+    replaced: bool,
+}
+
+impl Read for Tail {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.file.read(buf)?;
+        if read != 0 {
+            self.position += read as u64;
+            return Ok(read);
+        }
+        let open = self.file.metadata()?;
+        // A removed file is waited for, as it is usually recreated right after
+        self.replaced = open.len() < self.position
+            || std::fs::metadata(&self.path).is_ok_and(|current| !same_file(&open, &current));
+        match self.replaced {
+            true => Ok(0),
+            false => Err(ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    (a.dev(), a.ino()) == (b.dev(), b.ino())
+}
+
+#[cfg(not(unix))]
+fn same_file(a: &Metadata, b: &Metadata) -> bool {
+    a.created().ok() == b.created().ok()
+}
+
+/// Connects to a TCP server sending a TAP document
+struct Tcp(String);
+
+impl TapSource for Tcp {
+    fn describe(&self, _: &Context) -> String {
+        format!("tcp {}", self.0)
+    }
+
+    fn read(&mut self, context: &Context) -> anyhow::Result<String> {
+        let stream = TcpStream::connect(&self.0)
+            .with_context(|| format!("Could not connect to {}", self.0))?;
+        stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+        read_document(BufReader::new(stream), context, Until::Complete)
+    }
+}
+
+/// Reads a document from a serial port, whose speed must be configured beforehand (e.g. with
+/// `stty`)
+struct Serial(PathBuf);
+
+impl TapSource for Serial {
+    fn describe(&self, _: &Context) -> String {
+        format!("serial {}", self.0.display())
+    }
+
+    fn read(&mut self, context: &Context) -> anyhow::Result<String> {
+        let device = std::fs::File::open(&self.0)
+            .with_context(|| format!("Could not open {}", self.0.display()))?;
+        read_document(BufReader::new(device), context, Until::Complete)
+    }
+}
+
+/// Runs the test command on a remote host
+struct Ssh(String);
+
+impl Ssh {
+    fn line(&self, context: &Context) -> Vec<String> {
+        ["ssh".to_owned(), self.0.clone(), "--".to_owned()]
+            .into_iter()
+            .chain(context.command.iter().cloned())
+            .collect()
+    }
+}
+
+impl TapSource for Ssh {
+    fn needs_command(&self) -> bool {
+        true
+    }
+
+    fn describe(&self, context: &Context) -> String {
+        transcript::command_line(&self.line(context))
+    }
+
+    fn read(&mut self, context: &Context) -> anyhow::Result<String> {
+        run(&self.line(context), context)
+    }
+}
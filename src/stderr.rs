@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use crate::{raw, source::Stderr};
+
+/// Output of the test command on stderr during the last run, toggled with `S`. Each line is
+/// attached to the test point read before it, the output of a test usually being written before
+/// the next one starts.
+#[derive(Default)]
+pub struct StderrLog {
+    /// Lines, with the number of test points read before them
+    lines: Vec<(usize, String)>,
+    /// Test points read so far
+    points: usize,
+    /// Hierarchical numbers of the test points, in the order they were read
+    numbers: Vec<String>,
+}
+
+impl StderrLog {
+    pub fn push(&mut self, event: Stderr) {
+        match event {
+            Stderr::Point => self.points += 1,
+            Stderr::Line(line) => self.lines.push((self.points, line)),
+        }
+    }
+
+    /// Records the number of the next test point, as the tests complete while they run
+    pub fn complete(&mut self, number: String) {
+        self.numbers.push(number);
+    }
+
+    /// Numbers the test points from the whole document, once it is read
+    pub fn number(&mut self, tap: &str) {
+        self.numbers = raw::test_lines(tap)
+            .into_iter()
+            .sorted_by_key(|(_, line)| *line)
+            .map(|(number, _)| number)
+            .collect();
+    }
+
+    /// Number of the test point a line was written after, `None` before the first one
+    fn test(&self, points: usize) -> Option<&str> {
+        let index = points.checked_sub(1)?;
+        self.numbers.get(index).map(String::as_str)
+    }
+
+    /// Lines, with the number of the test point they were written after
+    pub fn lines(&self) -> impl Iterator<Item = (Option<&str>, &str)> {
+        self.lines
+            .iter()
+            .map(|(points, line)| (self.test(*points), line.as_str()))
+    }
+
+    /// Lines written after each test point, by hierarchical number
+    pub fn by_test(&self) -> HashMap<String, Vec<String>> {
+        let mut tests = HashMap::<_, Vec<_>>::new();
+        for (number, line) in self.lines() {
+            if let Some(number) = number {
+                tests
+                    .entry(number.to_owned())
+                    .or_default()
+                    .push(line.to_owned());
+            }
+        }
+        tests
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+}
@@ -0,0 +1,97 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context;
+
+const APP_DIR: &str = "tap-runner";
+
+/// Stable hash (FNV-1a) used to derive file names, as the std hashers may change between releases
+pub fn stable_hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Locations of the persisted data of a project.
+///
+/// State (history, archived runs, baselines, notes, ...) is stored under `$XDG_STATE_HOME` and
+/// caches (previews, ...) under `$XDG_CACHE_HOME`, or their platform equivalents. Each project
+/// gets its own sub-directory, named after its root.
+#[derive(Debug, Clone)]
+pub struct Storage {
+    state: PathBuf,
+    cache: PathBuf,
+}
+
+impl Storage {
+    pub fn for_project(root: &Path) -> anyhow::Result<Self> {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_owned());
+        let name = root
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let project = format!(
+            "{name}-{:016x}",
+            stable_hash(root.as_os_str().to_string_lossy().as_bytes())
+        );
+
+        let state = dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .context("Could not find a directory to store state in")?;
+        let cache = dirs::cache_dir().context("Could not find a directory to store caches in")?;
+
+        Ok(Self {
+            state: state.join(APP_DIR).join(&project),
+            cache: cache.join(APP_DIR).join(&project),
+        })
+    }
+
+    pub fn state_dir(&self) -> &Path {
+        &self.state
+    }
+
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache
+    }
+
+    /// Path of a state file of the project
+    pub fn state_path(&self, name: &str) -> PathBuf {
+        self.state.join(name)
+    }
+
+    /// Path of a cache file of the project
+    pub fn cache_path(&self, name: &str) -> PathBuf {
+        self.cache.join(name)
+    }
+
+    /// Writes `contents` to `path`, creating the missing directories
+    pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Could not create directory {}", parent.display()))?;
+        }
+        fs::write(path, contents).with_context(|| format!("Could not write {}", path.display()))
+    }
+
+    /// Removes the cache files of `dir` that were not written to since `max_age`
+    pub fn prune_cache(&self, dir: &str, max_age: Duration) {
+        let Ok(entries) = fs::read_dir(self.cache_path(dir)) else {
+            return;
+        };
+
+        let now = SystemTime::now();
+        for entry in entries.flatten() {
+            let expired = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .map(|m| now.duration_since(m).unwrap_or_default() > max_age)
+                .unwrap_or(true);
+            if expired {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+}
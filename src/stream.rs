@@ -0,0 +1,168 @@
+use tap_parser::DirectiveKind;
+
+use crate::{test::yaml_strings, Directive, Test};
+
+/// Test point recognized in a TAP stream, before the whole document is available
+pub struct StreamedTest {
+    /// Nesting level of the test, 0 for the tests of the top-level document
+    pub depth: usize,
+    pub ok: bool,
+    pub number: Option<usize>,
+    pub desc: Option<String>,
+    /// Directive of the test, such as `SKIP not supported`
+    pub directive: Option<String>,
+    pub yaml: Vec<String>,
+}
+
+impl StreamedTest {
+    /// Parses a test point line, without its indentation
+    fn parse(depth: usize, line: &str) -> Option<Self> {
+        let (ok, rest) = if let Some(rest) = line.strip_prefix("not ok") {
+            (false, rest)
+        } else {
+            (true, line.strip_prefix("ok")?)
+        };
+        if !rest.is_empty() && !rest.starts_with(' ') {
+            return None;
+        }
+
+        let (rest, directive) = match rest.split_once(" # ") {
+            Some((rest, directive)) => (rest, Some(directive.trim().to_owned())),
+            None => (rest, None),
+        };
+        let rest = rest.trim_start();
+        let (number, rest) = match rest.split_once(' ') {
+            Some((n, rest)) if n.parse::<usize>().is_ok() => (n.parse().ok(), rest),
+            None if rest.parse::<usize>().is_ok() => (rest.parse().ok(), ""),
+            _ => (None, rest),
+        };
+        let desc = rest.trim_start().trim_start_matches("- ").trim();
+
+        Some(Self {
+            depth,
+            ok,
+            number,
+            desc: (!desc.is_empty()).then(|| desc.to_owned()),
+            directive,
+            yaml: Vec::new(),
+        })
+    }
+
+    fn has_directive(&self, name: &str) -> bool {
+        self.directive
+            .as_deref()
+            .is_some_and(|d| d.to_lowercase().starts_with(name))
+    }
+
+    pub fn is_skip(&self) -> bool {
+        self.has_directive("skip")
+    }
+
+    pub fn is_todo(&self) -> bool {
+        self.has_directive("todo")
+    }
+}
+
+/// Recognizes the test points of a TAP document line by line. A test is complete once the line
+/// following its YAML diagnostics is read.
+#[derive(Default)]
+pub struct LineReader {
+    pending: Option<StreamedTest>,
+    in_yaml: bool,
+}
+
+impl LineReader {
+    /// Feeds the next line of the document, returns the test completed by it, if any
+    pub fn push(&mut self, line: &str) -> Option<StreamedTest> {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+
+        if let Some(pending) = &mut self.pending {
+            match trimmed {
+                "---" if !self.in_yaml && pending.yaml.is_empty() => {
+                    self.in_yaml = true;
+                    return None;
+                }
+                "..." if self.in_yaml => {
+                    self.in_yaml = false;
+                    return None;
+                }
+                _ if self.in_yaml => {
+                    // Diagnostics are indented 2 columns past their test
+                    let start = (pending.depth * 4 + 2).min(indent);
+                    pending.yaml.push(line[start..].to_owned());
+                    return None;
+                }
+                _ => (),
+            }
+        }
+
+        let test = StreamedTest::parse(indent / 4, trimmed);
+        if test.is_some() {
+            return std::mem::replace(&mut self.pending, test);
+        }
+        // Anything else (comments, plans, subtest headers) ends the pending test
+        self.pending.take()
+    }
+
+    /// Ends the document, returning the last test
+    pub fn finish(&mut self) -> Option<StreamedTest> {
+        self.in_yaml = false;
+        self.pending.take()
+    }
+}
+
+/// Tests of a document as it is being produced, numbered like [`crate::parse_tap`] would once
+/// the document is complete
+#[derive(Default)]
+pub struct LiveDocument {
+    reader: LineReader,
+    /// Number of tests read at each depth of the subtests being read
+    counts: Vec<usize>,
+}
+
+impl LiveDocument {
+    /// Feeds the next line of the document, returns the test completed by it, if any
+    pub fn push(&mut self, line: &str) -> Option<Test> {
+        self.reader.push(line).map(|t| self.number(t))
+    }
+
+    /// Ends the document, returning the last test
+    pub fn finish(&mut self) -> Option<Test> {
+        self.reader.finish().map(|t| self.number(t))
+    }
+
+    fn number(&mut self, test: StreamedTest) -> Test {
+        // Leaving a subtest resets the counts of its children
+        self.counts.resize(test.depth + 1, 0);
+        self.counts[test.depth] += 1;
+
+        let directive = test.directive.as_deref().and_then(|directive| {
+            let (kind, reason) = directive.split_once(' ').unwrap_or((directive, ""));
+            let key = match kind.to_lowercase() {
+                k if k.starts_with("skip") => DirectiveKind::Skip,
+                k if k.starts_with("todo") => DirectiveKind::Todo,
+                _ => return None,
+            };
+            let reason = reason.trim();
+            Some(Directive {
+                key,
+                reason: (!reason.is_empty()).then(|| reason.to_owned()),
+            })
+        });
+        let yaml = test.yaml.join("\n");
+        Test {
+            result: test.ok,
+            number: test.number.unwrap_or(self.counts[test.depth]),
+            desc: test.desc,
+            directive,
+            location: None,
+            category: None,
+            tags: yaml_strings(&yaml, "tags"),
+            yaml,
+            warnings: Vec::new(),
+            // The subtests end after their children, with the next number of their level
+            parents: self.counts[..test.depth].iter().map(|c| c + 1).collect(),
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::fmt;
+
+use crate::{Test, TestResult};
+
+/// Number of tests with each result
+#[derive(Default, Clone, Copy, Debug)]
+pub struct Summary {
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    /// Failing `TODO` tests
+    pub todo: usize,
+    /// Passing `TODO` tests
+    pub bonus: usize,
+    /// Document and subtests skipped entirely by their plan, counted apart from the tests
+    pub skipped_suites: usize,
+}
+
+impl Summary {
+    pub fn of(tests: &[Test]) -> Self {
+        tests.iter().map(Test::status).collect()
+    }
+}
+
+impl FromIterator<TestResult> for Summary {
+    fn from_iter<I: IntoIterator<Item = TestResult>>(iter: I) -> Self {
+        let mut summary = Summary::default();
+        for result in iter {
+            summary.total += 1;
+            *match result {
+                TestResult::Success => &mut summary.passed,
+                TestResult::Fail => &mut summary.failed,
+                TestResult::Skip => &mut summary.skipped,
+                TestResult::Todo => &mut summary.todo,
+                TestResult::Bonus => &mut summary.bonus,
+            } += 1;
+        }
+        summary
+    }
+}
+
+/// Formats the counts as `N tests: N passed, N failed, N skipped`, followed by the `TODO` tests
+/// and the skipped suites if there are any
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} tests: {} passed, {} failed, {} skipped",
+            self.total, self.passed, self.failed, self.skipped
+        )?;
+        if self.todo + self.bonus != 0 {
+            write!(f, ", {} todo", self.todo + self.bonus)?;
+            if self.bonus != 0 {
+                write!(f, " ({} passing)", self.bonus)?;
+            }
+        }
+        if self.skipped_suites != 0 {
+            write!(f, ", {} skipped suites", self.skipped_suites)?;
+        }
+        Ok(())
+    }
+}
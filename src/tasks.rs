@@ -0,0 +1,109 @@
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Runner {
+    Just,
+    Make,
+}
+
+impl Runner {
+    fn program(&self) -> &'static str {
+        match self {
+            Runner::Just => "just",
+            Runner::Make => "make",
+        }
+    }
+}
+
+/// Recipe of a Justfile or target of a Makefile
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Task {
+    pub runner: Runner,
+    pub name: String,
+}
+
+impl Task {
+    pub fn new(runner: Runner, name: &str) -> Self {
+        Self {
+            runner,
+            name: name.to_owned(),
+        }
+    }
+
+    pub fn command(&self) -> Vec<String> {
+        let program = self.runner.program().to_owned();
+        match self.runner {
+            Runner::Just => vec![program, self.name.clone()],
+            // Make echoes the commands it runs, which would mix with the TAP output
+            Runner::Make => vec![program, "-s".to_owned(), self.name.clone()],
+        }
+    }
+
+    pub fn label(&self) -> String {
+        format!("{} {}", self.runner.program(), self.name)
+    }
+}
+
+/// Name of the rule defined by a line of a Justfile or Makefile, ignoring assignments
+fn rule(line: &str) -> Option<&str> {
+    if line.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let (head, rest) = line.split_once(':')?;
+    if rest.starts_with('=') || rest.starts_with(":=") {
+        return None;
+    }
+    Some(head)
+}
+
+fn just_recipes(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(rule)
+        .filter_map(|head| {
+            let name = head.trim_start_matches('@').split_whitespace().next()?;
+            let valid = name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            // Recipes starting with `_` are private
+            (valid && !name.starts_with('_')).then(|| name.to_owned())
+        })
+        .collect()
+}
+
+fn make_targets(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(rule)
+        .filter(|head| !head.contains('='))
+        .flat_map(str::split_whitespace)
+        // Special targets, patterns and variables are not runnable tasks
+        .filter(|t| !t.starts_with('.') && !t.contains(['%', '$']))
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+/// Tasks of the Justfile and Makefile of `dir`
+pub fn discover(dir: &Path) -> Vec<Task> {
+    let find = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| std::fs::read_to_string(dir.join(name)).ok())
+    };
+
+    let mut tasks = Vec::new();
+    if let Some(justfile) = find(&["justfile", "Justfile", ".justfile"]) {
+        for recipe in just_recipes(&justfile) {
+            tasks.push(Task::new(Runner::Just, &recipe));
+        }
+    }
+    if let Some(makefile) = find(&["GNUmakefile", "makefile", "Makefile"]) {
+        for target in make_targets(&makefile) {
+            let task = Task::new(Runner::Make, &target);
+            if !tasks.contains(&task) {
+                tasks.push(task);
+            }
+        }
+    }
+    tasks
+}
@@ -0,0 +1,165 @@
+use itertools::Itertools;
+
+use crate::{config::TemplateSettings, Test};
+
+/// Keys of the YAML diagnostics read by `{duration}`, with the unit appended to their value
+const DURATION_KEYS: [(&str, &str); 4] = [
+    ("duration_ms", "ms"),
+    ("duration", ""),
+    ("time", ""),
+    ("elapsed", ""),
+];
+
+/// Value of a test replacing a placeholder
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Field {
+    Number,
+    Desc,
+    File,
+    Line,
+    Duration,
+    Category,
+    /// Tags of the test, as `#tag`
+    Tags,
+    Reason,
+    Status,
+    /// Top-level key of the YAML diagnostics, as `{yaml.<key>}`
+    Yaml(String),
+}
+
+impl Field {
+    fn parse(name: &str) -> anyhow::Result<Field> {
+        Ok(match name {
+            "number" => Field::Number,
+            "desc" => Field::Desc,
+            "file" => Field::File,
+            "line" => Field::Line,
+            "duration" => Field::Duration,
+            "category" => Field::Category,
+            "tags" => Field::Tags,
+            "reason" => Field::Reason,
+            "status" => Field::Status,
+            _ => match name.strip_prefix("yaml.") {
+                Some(key) if !key.is_empty() => Field::Yaml(key.to_owned()),
+                _ => anyhow::bail!(
+                    "Unknown placeholder `{{{name}}}`, expected one of {{number}}, {{desc}}, \
+                     {{file}}, {{line}}, {{duration}}, {{category}}, {{tags}}, {{reason}}, \
+                     {{status}} or {{yaml.<key>}}"
+                ),
+            },
+        })
+    }
+
+    fn value(&self, test: &Test) -> Option<String> {
+        match self {
+            Field::Number => Some(test.hierarchical_number()),
+            Field::Desc => test.desc.clone(),
+            Field::File => test.location.as_ref().map(|l| l.file.clone()),
+            Field::Line => test.location.as_ref().map(|l| l.line.to_string()),
+            Field::Duration => DURATION_KEYS
+                .iter()
+                .find_map(|(key, unit)| Some(format!("{}{unit}", yaml_value(test, key)?))),
+            Field::Category => test.category.clone(),
+            Field::Tags => Some(test.tags.iter().map(|t| format!("#{t}")).join(" ")),
+            Field::Reason => test.directive.as_ref().and_then(|d| d.reason.clone()),
+            Field::Status => Some(test.status().name().to_owned()),
+            Field::Yaml(key) => yaml_value(test, key),
+        }
+    }
+}
+
+/// Scalar value of the top-level `key` of the diagnostics of the test
+fn yaml_value(test: &Test, key: &str) -> Option<String> {
+    let serde_yaml::Value::Mapping(map) = serde_yaml::from_str(&test.yaml).ok()? else {
+        return None;
+    };
+    match map.get(key)? {
+        serde_yaml::Value::String(s) => Some(s.clone()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        serde_yaml::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Field(Field),
+}
+
+/// Format of a row of a list, such as `"{number} {desc} [{file}:{line}]"`. `{{` and `}}` are a
+/// literal brace, and the placeholders without a value for a test are left empty.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template(Vec<Part>);
+
+impl Template {
+    pub fn parse(template: &str) -> anyhow::Result<Template> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let Some(end) = rest.find('}') else {
+                        anyhow::bail!("Unclosed placeholder in `{template}`");
+                    };
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Field(Field::parse(&rest[..end])?));
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => anyhow::bail!("Unmatched `}}` in `{template}`, write `}}}}` for a brace"),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template(parts))
+    }
+
+    pub fn render(&self, test: &Test) -> String {
+        self.0
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.clone(),
+                Part::Field(field) => field.value(test).unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
+/// Templates of the rows of each list, the default rows being used for the unset ones
+#[derive(Default)]
+pub struct Templates {
+    pub failure: Option<Template>,
+    pub skipped: Option<Template>,
+    pub passed: Option<Template>,
+}
+
+impl Templates {
+    pub fn parse(settings: &TemplateSettings) -> anyhow::Result<Templates> {
+        let parse = |name, template: &Option<String>| {
+            template
+                .as_deref()
+                .map(Template::parse)
+                .transpose()
+                .map_err(|e| e.context(format!("Invalid templates.{name}")))
+        };
+        Ok(Templates {
+            failure: parse("failure", &settings.failure)?,
+            skipped: parse("skipped", &settings.skipped)?,
+            passed: parse("passed", &settings.passed)?,
+        })
+    }
+}
@@ -0,0 +1,152 @@
+use std::{io::Write, str::FromStr};
+
+use itertools::Itertools;
+use tap_parser::DirectiveKind;
+
+#[derive(Debug)]
+pub struct Directive {
+    pub key: DirectiveKind,
+    pub reason: Option<String>,
+}
+
+/// Test point of a TAP document, or the ending of a subtest
+#[derive(Debug)]
+pub struct Test {
+    pub result: bool,
+    /// Number of the test in its (sub)document
+    pub number: usize,
+    pub desc: Option<String>,
+    pub directive: Option<Directive>,
+    /// YAML diagnostics of the test, empty if it has none
+    pub yaml: String,
+    pub location: Option<Location>,
+    /// Category returned by the `classify` function of the script
+    pub category: Option<String>,
+    /// Tags of the test, from the `tags` key of its diagnostics or the tag filter
+    pub tags: Vec<String>,
+    /// Problems found while processing the test, which did not prevent its display
+    pub warnings: Vec<String>,
+
+    /// Numbers of the subtests containing the test, outermost first
+    pub parents: Vec<usize>,
+}
+
+impl Test {
+    /// Number of the test prefixed by the numbers of the subtests containing it, e.g. `2.1.3`
+    pub fn hierarchical_number(&self) -> String {
+        self.parents
+            .iter()
+            .chain(std::iter::once(&self.number))
+            .join(".")
+    }
+
+    pub fn status(&self) -> TestResult {
+        match &self.directive {
+            Some(d) if d.key == DirectiveKind::Todo && self.result => TestResult::Bonus,
+            Some(d) if d.key == DirectiveKind::Todo => TestResult::Todo,
+            _ if !self.result => TestResult::Fail,
+            Some(d) if d.key == DirectiveKind::Skip => TestResult::Skip,
+            _ => TestResult::Success,
+        }
+    }
+
+    /// Files and URLs listed by the `artifacts` key of the diagnostics, e.g. screenshots or logs
+    pub fn artifacts(&self) -> Vec<String> {
+        yaml_strings(&self.yaml, "artifacts")
+    }
+
+    /// Writes the test as text, with its YAML diagnostics if `verbose`
+    pub fn write(&self, out: &mut impl Write, indent: &str, verbose: bool) -> std::io::Result<()> {
+        write!(out, "{indent}{}", self.hierarchical_number())?;
+        if let Some(d) = &self.desc {
+            write!(out, " - {d}")?;
+        }
+        if let Some(category) = &self.category {
+            write!(out, " [{category}]")?;
+        }
+        if let Some(location) = &self.location {
+            write!(out, " ({}:{})", location.file, location.line)?;
+        }
+        writeln!(out)?;
+
+        if verbose {
+            for line in self.yaml.split('\n').filter(|s| !s.is_empty()) {
+                writeln!(out, "{indent}  {line}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Strings of the top-level `key` of the YAML diagnostics, given as a string or a list
+pub(crate) fn yaml_strings(yaml: &str, key: &str) -> Vec<String> {
+    if yaml.is_empty() {
+        return Vec::new();
+    }
+    let Ok(serde_yaml::Value::Mapping(map)) = serde_yaml::from_str(yaml) else {
+        return Vec::new();
+    };
+    match map.get(key) {
+        Some(serde_yaml::Value::String(value)) => vec![value.clone()],
+        Some(serde_yaml::Value::Sequence(values)) => values
+            .iter()
+            .filter_map(|v| v.as_str().map(ToOwned::to_owned))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Place in the sources where a test failed, parsed from `file:line`
+#[derive(Debug, Clone)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+}
+
+impl FromStr for Location {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((file, line)) = s.split_once(':') else {
+            anyhow::bail!("Missing `:` in location")
+        };
+
+        Ok(Self {
+            file: file.into(),
+            line: line.parse()?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TestResult {
+    Skip,
+    Success,
+    Fail,
+    /// Failing `TODO` test, which is not counted as a failure
+    Todo,
+    /// Passing `TODO` test
+    Bonus,
+}
+
+impl TestResult {
+    /// Name of the result given to the plugins and the script
+    pub fn name(&self) -> &'static str {
+        match self {
+            TestResult::Skip => "skip",
+            TestResult::Success => "pass",
+            TestResult::Fail => "fail",
+            TestResult::Todo => "todo",
+            TestResult::Bonus => "bonus",
+        }
+    }
+}
+
+/// Formats a test as `number - description`
+pub fn describe(number: &str, desc: Option<&str>) -> String {
+    match desc {
+        None => number.to_owned(),
+        Some(d) => format!("{number} - {d}"),
+    }
+}
@@ -0,0 +1,158 @@
+use anyhow::Context;
+use itertools::Itertools;
+use tap_runner::TestResult;
+use tui::style::Color;
+
+use crate::config::ThemeSettings;
+
+/// Colors of the results and of the selection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Theme {
+    pub skip: Color,
+    pub success: Color,
+    pub fail: Color,
+    pub todo: Color,
+    pub bonus: Color,
+    /// Background of the selected row of the focused list
+    pub highlight: Color,
+    /// Background of the selected row of the other lists
+    pub unfocused_highlight: Color,
+}
+
+/// Themes shipped with tap_runner, selected with `name` in the `theme` section
+const THEMES: &[(&str, Theme)] = &[
+    ("default", Theme::DEFAULT),
+    (
+        "colorblind",
+        Theme {
+            skip: Color::Rgb(0xf0, 0xe4, 0x42),
+            success: Color::Rgb(0x00, 0x72, 0xb2),
+            fail: Color::Rgb(0xd5, 0x5e, 0x00),
+            todo: Color::Rgb(0xcc, 0x79, 0xa7),
+            bonus: Color::Rgb(0x56, 0xb4, 0xe9),
+            ..Theme::DEFAULT
+        },
+    ),
+    (
+        "solarized",
+        Theme {
+            skip: Color::Rgb(0xb5, 0x89, 0x00),
+            success: Color::Rgb(0x26, 0x8b, 0xd2),
+            fail: Color::Rgb(0xdc, 0x32, 0x2f),
+            todo: Color::Rgb(0xd3, 0x36, 0x82),
+            bonus: Color::Rgb(0x2a, 0xa1, 0x98),
+            highlight: Color::Rgb(0x20, 0x50, 0x5f),
+            unfocused_highlight: Color::Rgb(0x07, 0x36, 0x42),
+        },
+    ),
+    (
+        "light",
+        Theme {
+            skip: Color::Rgb(0xa0, 0x70, 0x00),
+            success: Color::Rgb(0x00, 0x50, 0xc0),
+            fail: Color::Rgb(0xc0, 0x00, 0x00),
+            todo: Color::Magenta,
+            bonus: Color::Rgb(0x00, 0x80, 0x80),
+            highlight: Color::Rgb(0xc5, 0xd7, 0xf2),
+            unfocused_highlight: Color::Rgb(0xe0, 0xe0, 0xe0),
+        },
+    ),
+];
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Theme {
+    const DEFAULT: Theme = Theme {
+        skip: Color::Yellow,
+        success: Color::Blue,
+        fail: Color::Rgb(255, 0, 0),
+        todo: Color::Magenta,
+        bonus: Color::Cyan,
+        highlight: Color::Rgb(0x33, 0x46, 0x7c),
+        unfocused_highlight: Color::Rgb(0x3a, 0x3a, 0x3a),
+    };
+
+    /// Theme of the `theme` section: the named theme, `default` if none, with the colors set in
+    /// the section taking precedence
+    pub fn parse(settings: &ThemeSettings) -> anyhow::Result<Self> {
+        let name = settings.name.as_deref().unwrap_or("default");
+        let Some(&(_, mut theme)) = THEMES.iter().find(|(n, _)| *n == name) else {
+            anyhow::bail!(
+                "Unknown theme `{name}`, the themes are {}",
+                THEMES.iter().map(|(n, _)| format!("`{n}`")).join(", ")
+            );
+        };
+        let colors = [
+            ("skip", &settings.skip, &mut theme.skip),
+            ("success", &settings.success, &mut theme.success),
+            ("fail", &settings.fail, &mut theme.fail),
+            ("todo", &settings.todo, &mut theme.todo),
+            ("bonus", &settings.bonus, &mut theme.bonus),
+            ("highlight", &settings.highlight, &mut theme.highlight),
+            (
+                "unfocused_highlight",
+                &settings.unfocused_highlight,
+                &mut theme.unfocused_highlight,
+            ),
+        ];
+        for (key, setting, color) in colors {
+            if let Some(setting) = setting {
+                *color = parse_color(setting).with_context(|| format!("Invalid theme.{key}"))?;
+            }
+        }
+        Ok(theme)
+    }
+
+    pub fn result(&self, result: TestResult) -> Color {
+        match result {
+            TestResult::Skip => self.skip,
+            TestResult::Success => self.success,
+            TestResult::Fail => self.fail,
+            TestResult::Todo => self.todo,
+            TestResult::Bonus => self.bonus,
+        }
+    }
+}
+
+/// Parses a color: `#rrggbb`, the number of a color of the 256-color palette, or the name of a
+/// terminal color such as `red`, `light-blue` or `dark-gray`
+pub fn parse_color(color: &str) -> anyhow::Result<Color> {
+    if let Some(hex) = color.strip_prefix('#') {
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+        };
+        return match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color::Rgb(r, g, b)),
+            _ => Err(anyhow::anyhow!("`{color}` is not a `#rrggbb` color")),
+        };
+    }
+    if let Ok(index) = color.parse() {
+        return Ok(Color::Indexed(index));
+    }
+    let name = color.to_lowercase().replace(['-', '_', ' '], "");
+    Ok(match name.as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => anyhow::bail!("Unknown color `{color}`"),
+    })
+}
@@ -0,0 +1,41 @@
+use std::{collections::HashMap, time::Duration};
+
+/// Times a test started and finished at, from the start of the run
+#[derive(Clone, Copy)]
+pub struct Span {
+    pub start: Duration,
+    pub end: Duration,
+}
+
+/// Times the tests of the last run were read at, for the timeline view. TAP only tells when a
+/// test finishes: a test is considered to start when the one before it finished, and a subtest
+/// when its first test started.
+#[derive(Default)]
+pub struct Timeline {
+    spans: HashMap<String, Span>,
+    /// Start of the first test read at each depth since its subtest started
+    first: Vec<Option<Duration>>,
+    /// When the last test finished
+    last: Duration,
+}
+
+impl Timeline {
+    /// Records that the test numbered `number` at `depth` finished at `at`
+    pub fn record(&mut self, depth: usize, number: String, at: Duration) {
+        if self.first.len() < depth + 2 {
+            self.first.resize(depth + 2, None);
+        }
+        let start = self.first[depth + 1].take().unwrap_or(self.last);
+        self.first[depth].get_or_insert(start);
+        self.spans.insert(number, Span { start, end: at });
+        self.last = at;
+    }
+
+    pub fn get(&self, number: &str) -> Option<Span> {
+        self.spans.get(number).copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+}
@@ -0,0 +1,172 @@
+use std::time::{Duration, Instant};
+
+use tui::{
+    backend::Backend,
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Level {
+    Info,
+    /// Good news, such as the failures being fixed
+    Success,
+    Warning,
+    Error,
+}
+
+pub struct Toast {
+    pub level: Level,
+    pub message: String,
+    /// Number of times the same notification was sent while it was shown
+    count: usize,
+    created_at: Instant,
+}
+
+impl Toast {
+    fn new(level: Level, message: impl ToString) -> Self {
+        Self {
+            level,
+            message: message.to_string(),
+            count: 1,
+            created_at: Instant::now(),
+        }
+    }
+
+    pub fn info(message: impl ToString) -> Self {
+        Self::new(Level::Info, message)
+    }
+
+    pub fn success(message: impl ToString) -> Self {
+        Self::new(Level::Success, message)
+    }
+
+    pub fn warning(message: impl ToString) -> Self {
+        Self::new(Level::Warning, message)
+    }
+
+    pub fn error(message: impl ToString) -> Self {
+        Self::new(Level::Error, message)
+    }
+
+    fn title(&self) -> String {
+        let title = match self.level {
+            Level::Info => "Info",
+            Level::Success => "Success",
+            Level::Warning => "Warning",
+            Level::Error => "Error",
+        };
+        match self.count {
+            1 => title.to_string(),
+            n => format!("{title} (×{n})"),
+        }
+    }
+
+    /// Errors stay longer as they usually prevent the tests from running
+    fn lifetime(&self) -> Duration {
+        match self.level {
+            Level::Info => Duration::from_secs(4),
+            Level::Success => Duration::from_secs(8),
+            Level::Warning => Duration::from_secs(8),
+            Level::Error => Duration::from_secs(20),
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self.level {
+            Level::Info => Color::Blue,
+            Level::Success => Color::Green,
+            Level::Warning => Color::Yellow,
+            Level::Error => Color::Red,
+        }
+    }
+}
+
+/// Notifications stacked in the top right corner, expiring after a delay depending on their level
+#[derive(Default)]
+pub struct Toasts {
+    toasts: Vec<Toast>,
+}
+
+impl Toasts {
+    const MAX: usize = 5;
+    const MAX_HEIGHT: u16 = 10;
+
+    /// Adds a toast, or refreshes the identical one if it is already shown
+    pub fn push(&mut self, toast: Toast) {
+        match self
+            .toasts
+            .iter_mut()
+            .find(|t| t.level == toast.level && t.message == toast.message)
+        {
+            Some(shown) => {
+                shown.count += 1;
+                shown.created_at = toast.created_at;
+            }
+            None => self.toasts.push(toast),
+        }
+
+        if self.toasts.len() > Self::MAX {
+            self.toasts.remove(0);
+        }
+    }
+
+    pub fn expire(&mut self) {
+        self.toasts
+            .retain(|t| t.created_at.elapsed() < t.lifetime());
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Toast> {
+        self.toasts.iter()
+    }
+
+    pub fn last_error(&self) -> Option<&Toast> {
+        self.toasts.iter().rev().find(|t| t.level == Level::Error)
+    }
+
+    /// Draws the toasts, the most recent ones at the top
+    pub fn render<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let width = (area.width / 2).max(area.width.min(40));
+        let x = area.x + area.width - width;
+        let mut y = area.y;
+
+        for toast in self.toasts.iter().rev() {
+            let text_width = width.saturating_sub(2).max(1) as usize;
+            let lines: usize = toast
+                .message
+                .lines()
+                .map(|l| l.chars().count().max(1).div_ceil(text_width))
+                .sum();
+            let height = (lines as u16 + 2).min(Self::MAX_HEIGHT);
+            if y + height > area.y + area.height {
+                break;
+            }
+
+            let toast_area = Rect::new(x, y, width, height);
+            let color = toast.color();
+            f.render_widget(Clear, toast_area);
+            f.render_widget(
+                Paragraph::new(toast.message.as_str())
+                    .block(
+                        Block::default()
+                            .title(toast.title())
+                            .borders(Borders::ALL)
+                            .border_style(Style::default().fg(color)),
+                    )
+                    .wrap(Wrap { trim: true }),
+                toast_area,
+            );
+            y += height;
+        }
+    }
+}
+
+impl Extend<Toast> for Toasts {
+    fn extend<T: IntoIterator<Item = Toast>>(&mut self, iter: T) {
+        for toast in iter {
+            self.push(toast);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::{fmt::Write, path::Path, time::SystemTime};
+
+use crate::storage::Storage;
+
+/// Plain text log of what happened during the session, exported with `X` or `--transcript`
+#[derive(Default)]
+pub struct Transcript {
+    text: String,
+}
+
+impl Transcript {
+    /// Appends a timestamped entry
+    pub fn record(&mut self, message: impl AsRef<str>) {
+        let _ = writeln!(
+            self.text,
+            "[{}] {}",
+            timestamp(SystemTime::now()),
+            message.as_ref()
+        );
+    }
+
+    /// Appends the `details` of the last entry, indented below it
+    pub fn attach(&mut self, details: &str) {
+        for line in details.lines() {
+            let _ = writeln!(self.text, "    {line}");
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        Storage::write(path, &self.text)
+    }
+}
+
+/// Command line as it could be typed in a shell, quoting the arguments when needed
+pub fn command_line(line: &[String]) -> String {
+    let quote = |arg: &String| {
+        let plain = !arg.is_empty()
+            && arg
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+        match plain {
+            true => arg.clone(),
+            false => format!("'{}'", arg.replace('\'', r"'\''")),
+        }
+    };
+    line.iter().map(quote).collect::<Vec<_>>().join(" ")
+}
+
+/// Formats `time` as `YYYY-MM-DD HH:MM:SS UTC`
+pub fn timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // Converts the days since the epoch to a civil date (http://howardhinnant.github.io/date_algorithms.html)
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
@@ -0,0 +1,175 @@
+use std::{collections::HashMap, ops::Range};
+
+use itertools::Itertools;
+
+use crate::{describe, Test, TestResult};
+
+/// Line of the tree view
+pub struct Row {
+    /// Index of the test in the flattened document
+    pub index: usize,
+    pub depth: usize,
+    pub subtest: bool,
+    /// Results of the tests of the subtree, for subtests
+    pub counts: Option<Counts>,
+    /// The children of the subtest are hidden
+    pub collapsed: bool,
+}
+
+/// Number of tests of a subtree with each result, not counting the subtests themselves
+#[derive(Default, Clone, Copy)]
+pub struct Counts {
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    /// Passing and failing `TODO` tests
+    pub todo: usize,
+}
+
+impl Counts {
+    fn all_passed(&self) -> bool {
+        self.failed == 0 && self.skipped == 0
+    }
+
+    fn add(&mut self, other: Counts) {
+        self.passed += other.passed;
+        self.failed += other.failed;
+        self.skipped += other.skipped;
+        self.todo += other.todo;
+    }
+}
+
+/// Counts of each subtree, computed bottom-up in a single pass. The counts of a test which is not
+/// a subtest are its own result.
+pub fn counts(tests: &[Test]) -> Vec<Counts> {
+    // Sums of the tests seen at each depth since their parent was closed
+    let mut pending: Vec<Counts> = Vec::new();
+    let mut counts = Vec::with_capacity(tests.len());
+    for (index, test) in tests.iter().enumerate() {
+        let depth = test.parents.len();
+        if pending.len() <= depth + 1 {
+            pending.resize(depth + 2, Counts::default());
+        }
+
+        let own = if is_subtest(tests, index) {
+            std::mem::take(&mut pending[depth + 1])
+        } else {
+            let mut own = Counts::default();
+            match test.status() {
+                TestResult::Skip => own.skipped += 1,
+                TestResult::Success => own.passed += 1,
+                TestResult::Fail => own.failed += 1,
+                TestResult::Todo | TestResult::Bonus => own.todo += 1,
+            }
+            own
+        };
+        pending[depth].add(own);
+        counts.push(own);
+    }
+    counts
+}
+
+/// Whether the test at `index` has children, which precede it in the flattened document
+pub fn is_subtest(tests: &[Test], index: usize) -> bool {
+    index > 0 && tests[index - 1].parents.len() > tests[index].parents.len()
+}
+
+/// Index of the first test of the subtree ending at `index`.
+///
+/// Subtests come after their children in the flattened document, so the subtree is the run of
+/// deeper tests preceding it.
+pub fn subtree_start(tests: &[Test], index: usize) -> usize {
+    let depth = tests[index].parents.len();
+    let mut start = index;
+    while start > 0 && tests[start - 1].parents.len() > depth {
+        start -= 1;
+    }
+    start
+}
+
+/// Descriptions of the test at `index` and of the subtests containing it, e.g. `suite/subtest/test`
+pub fn description_path(tests: &[Test], index: usize) -> String {
+    ancestors(tests, index)
+        .iter()
+        .rev()
+        .chain(std::iter::once(&index))
+        .map(|&i| tests[i].desc.as_deref().unwrap_or(""))
+        .join("/")
+}
+
+/// Indices of the subtests containing the test at `index`, innermost first
+pub fn ancestors(tests: &[Test], index: usize) -> Vec<usize> {
+    let mut depth = tests[index].parents.len();
+    let mut ancestors = Vec::new();
+    for (i, test) in tests.iter().enumerate().skip(index + 1) {
+        if depth == 0 {
+            break;
+        }
+        if test.parents.len() < depth {
+            depth = test.parents.len();
+            ancestors.push(i);
+        }
+    }
+    ancestors
+}
+
+/// Path from the outermost subtest to the test at `index`, as `1 - suite ▸ 1.2 - test`
+pub fn breadcrumb(tests: &[Test], index: usize) -> String {
+    let mut path = ancestors(tests, index);
+    path.reverse();
+    path.push(index);
+    path.into_iter()
+        .map(|i| describe(&tests[i].hierarchical_number(), tests[i].desc.as_deref()))
+        .join(" ▸ ")
+}
+
+/// Rows of the tree in display order, the subtests being shown before their children.
+///
+/// The children of the subtests are hidden if they all passed, unless overridden by the entry of
+/// the subtest in `collapsed` (by hierarchical number).
+pub fn rows(tests: &[Test], collapsed: &HashMap<String, bool>) -> Vec<Row> {
+    fn visit(
+        tests: &[Test],
+        counts: &[Counts],
+        range: Range<usize>,
+        depth: usize,
+        collapsed: &HashMap<String, bool>,
+        rows: &mut Vec<Row>,
+    ) {
+        let mut start = range.start;
+        for index in range {
+            if tests[index].parents.len() != depth {
+                continue;
+            }
+
+            let subtest = start < index;
+            let is_collapsed = subtest
+                && collapsed
+                    .get(&tests[index].hierarchical_number())
+                    .copied()
+                    .unwrap_or_else(|| counts[index].all_passed());
+            rows.push(Row {
+                index,
+                depth,
+                subtest,
+                counts: subtest.then_some(counts[index]),
+                collapsed: is_collapsed,
+            });
+            if subtest && !is_collapsed {
+                visit(tests, counts, start..index, depth + 1, collapsed, rows);
+            }
+            start = index + 1;
+        }
+    }
+
+    let mut rows = Vec::new();
+    visit(
+        tests,
+        &counts(tests),
+        0..tests.len(),
+        0,
+        collapsed,
+        &mut rows,
+    );
+    rows
+}
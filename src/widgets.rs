@@ -1,24 +1,73 @@
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use itertools::Itertools;
 use tui::{
     backend::Backend,
     layout::Rect,
-    style::{Color, Style},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
     widgets::{Block, List, ListItem, ListState, Widget},
     Frame,
 };
 
+use crate::{config::Truncation, theme::Theme};
+
 pub struct ColoredList<'a> {
     colors: Vec<Color>,
+    highlighted: Vec<bool>,
     block: Option<Block<'a>>,
+    cell_width: u16,
+    cell_gap: u16,
+    glyph: Option<char>,
+    /// Symbol of each cell, drawn instead of its color
+    symbols: Vec<char>,
+    per_row: Option<u16>,
 }
 
 impl<'a> ColoredList<'a> {
     pub fn new(colors: Vec<Color>) -> Self {
         Self {
             colors,
+            highlighted: Vec::new(),
             block: None,
+            cell_width: 1,
+            cell_gap: 0,
+            glyph: None,
+            symbols: Vec::new(),
+            per_row: None,
         }
     }
 
+    /// Sets the width of the cells and the number of blank columns between them
+    pub fn cells(mut self, width: u16, gap: u16) -> Self {
+        self.cell_width = width.max(1);
+        self.cell_gap = gap;
+        self
+    }
+
+    /// Draws `glyph` in the color of each cell instead of filling it
+    pub fn glyph(mut self, glyph: Option<char>) -> Self {
+        self.glyph = glyph;
+        self
+    }
+
+    /// Draws the symbol of each cell instead of filling it, for the terminals without colors
+    pub fn symbols(mut self, symbols: Vec<char>) -> Self {
+        self.symbols = symbols;
+        self
+    }
+
+    /// Limits the number of cells on each row
+    pub fn per_row(mut self, per_row: Option<u16>) -> Self {
+        self.per_row = per_row;
+        self
+    }
+
+    /// Marks the cells for which `highlighted` is true with a dot
+    pub fn highlight(mut self, highlighted: Vec<bool>) -> Self {
+        self.highlighted = highlighted;
+        self
+    }
+
     pub fn block(mut self, block: Block<'a>) -> Self {
         self.block = Some(block);
         self
@@ -36,35 +85,224 @@ impl Widget for ColoredList<'_> {
             None => area,
         };
 
-        let available_space = list_area.area() as usize;
+        let stride = self.cell_width + self.cell_gap;
+        let fitting = ((list_area.width + self.cell_gap) / stride).max(1);
+        let per_row = self.per_row.map_or(fitting, |n| n.clamp(1, fitting));
+        if list_area.width < self.cell_width || list_area.height == 0 {
+            return;
+        }
+        let available_space = per_row as usize * list_area.height as usize;
 
         let (print_count, lack_space) = if available_space < self.colors.len() {
-            (available_space - 3, true)
+            // Leaves room for the ellipsis at the end of the last row
+            let reserved = 3u16.div_ceil(stride).min(per_row) as usize;
+            (available_space - reserved, true)
         } else {
             (self.colors.len(), false)
         };
 
         for (idx, &c) in self.colors[..print_count].iter().enumerate() {
-            let x = idx as u16 % list_area.width;
-            let y = idx as u16 / list_area.width;
+            let x = list_area.left() + (idx as u16 % per_row) * stride;
+            let y = list_area.top() + idx as u16 / per_row;
+            let highlighted = self.highlighted.get(idx).copied().unwrap_or(false);
 
-            buf.get_mut(list_area.left() + x, list_area.top() + y)
-                .set_symbol(" ")
-                .set_bg(c);
+            match self.symbols.get(idx).copied().or(self.glyph) {
+                Some(glyph) => {
+                    let cell = buf.get_mut(x, y);
+                    cell.set_char(glyph).set_fg(c);
+                    if highlighted {
+                        cell.set_bg(Color::DarkGray);
+                    }
+                }
+                None => {
+                    for dx in 0..self.cell_width {
+                        let symbol = match highlighted && dx == 0 {
+                            true => "•",
+                            false => " ",
+                        };
+                        buf.get_mut(x + dx, y)
+                            .set_symbol(symbol)
+                            .set_fg(Color::Black)
+                            .set_bg(c);
+                    }
+                }
+            }
         }
 
         if lack_space {
-            for i in 0..3 {
-                buf.get_mut(list_area.right() - i, list_area.bottom())
-                    .set_symbol(".");
+            let end = list_area.left() + per_row * stride - self.cell_gap;
+            for i in 1..=3.min(end - list_area.left()) {
+                buf.get_mut(end - i, list_area.bottom() - 1).set_symbol(".");
+            }
+        }
+    }
+}
+
+/// Removes the colors of the cells of its area, for the terminals without colors. The cells with
+/// a background, such as the selection, are shown in reverse video instead.
+pub struct Monochrome;
+
+impl Widget for Monochrome {
+    fn render(self, area: Rect, buf: &mut tui::buffer::Buffer) {
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = buf.get_mut(x, y);
+                if cell.bg != Color::Reset {
+                    cell.modifier.insert(Modifier::REVERSED);
+                }
+                cell.fg = Color::Reset;
+                cell.bg = Color::Reset;
             }
         }
     }
 }
 
+/// Splits `line` in lines of at most `width` characters, breaking at spaces when possible. The
+/// continuation lines are indented two columns past the indentation of `line`.
+pub fn wrap(line: Spans<'static>, width: usize) -> Vec<Spans<'static>> {
+    let chars: Vec<(char, Style)> = line
+        .0
+        .iter()
+        .flat_map(|span| span.content.chars().map(|c| (c, span.style)))
+        .collect();
+    let indent = chars.iter().take_while(|(c, _)| *c == ' ').count() + 2;
+    if chars.len() <= width || width <= indent {
+        return vec![line];
+    }
+
+    let mut lines = Vec::new();
+    let mut rest = &chars[..];
+    while !rest.is_empty() {
+        let prefix = if lines.is_empty() { 0 } else { indent };
+        let available = width - prefix;
+        let end = if rest.len() <= available {
+            rest.len()
+        } else {
+            match rest[..=available].iter().rposition(|(c, _)| *c == ' ') {
+                Some(space) if space > 0 => space + 1,
+                _ => available,
+            }
+        };
+
+        let mut spans = vec![Span::raw(" ".repeat(prefix))];
+        for (style, group) in &rest[..end].iter().group_by(|(_, style)| *style) {
+            spans.push(Span::styled(
+                group.map(|(c, _)| c).collect::<String>(),
+                style,
+            ));
+        }
+        lines.push(Spans::from(spans));
+
+        rest = &rest[end..];
+    }
+    lines
+}
+
+/// Spans of the first `count` characters of `spans`
+fn head(spans: &[Span<'static>], count: usize) -> Vec<Span<'static>> {
+    let mut remaining = count;
+    let mut taken = Vec::new();
+    for span in spans {
+        if remaining == 0 {
+            break;
+        }
+        let content: String = span.content.chars().take(remaining).collect();
+        remaining -= content.chars().count();
+        taken.push(Span::styled(content, span.style));
+    }
+    taken
+}
+
+/// Spans of the last `count` characters of `spans`
+fn tail(spans: &[Span<'static>], count: usize) -> Vec<Span<'static>> {
+    let mut remaining = count;
+    let mut taken = Vec::new();
+    for span in spans.iter().rev() {
+        if remaining == 0 {
+            break;
+        }
+        let length = span.content.chars().count();
+        let skipped = length.saturating_sub(remaining);
+        remaining -= length - skipped;
+        taken.push(Span::styled(
+            span.content.chars().skip(skipped).collect::<String>(),
+            span.style,
+        ));
+    }
+    taken.reverse();
+    taken
+}
+
+/// Cuts `line` to `width` characters, replacing the part removed with an ellipsis if it was too
+/// long. The `truncation` tells which part is removed.
+pub fn truncate(line: Spans<'static>, width: usize, truncation: Truncation) -> Spans<'static> {
+    if line.width() <= width {
+        return line;
+    }
+
+    let kept = width.saturating_sub(1);
+    let (start, end) = match truncation {
+        Truncation::End => (kept, 0),
+        // The end of a path is usually what tells the tests apart
+        Truncation::Middle => (kept / 2, kept - kept / 2),
+    };
+    let mut spans = head(&line.0, start);
+    spans.push(Span::raw("…"));
+    spans.extend(tail(&line.0, end));
+    Spans::from(spans)
+}
+
 pub struct StatefulList<T> {
     state: ListState,
     items: Vec<T>,
+    /// Moving past the last item selects the first one, and conversely
+    wrap: bool,
+    /// The selection is dimmed when the list is not focused
+    focused: bool,
+    /// First item shown, following the offset `List` keeps in the state
+    offset: usize,
+    /// Area of the last render, and of each item it showed with its index
+    area: Rect,
+    shown: Vec<(Rect, usize)>,
+}
+
+/// Items of a list of items of `heights` shown in `max_height` rows, the first one being `offset`
+/// if the `selected` one stays visible, as computed by `List`
+fn items_bounds(
+    heights: &[usize],
+    selected: Option<usize>,
+    offset: usize,
+    max_height: usize,
+) -> (usize, usize) {
+    let offset = offset.min(heights.len().saturating_sub(1));
+    let (mut start, mut end) = (offset, offset);
+    let mut height = 0;
+    for item in &heights[offset..] {
+        if height + item > max_height {
+            break;
+        }
+        height += item;
+        end += 1;
+    }
+
+    let selected = selected.unwrap_or(0).min(heights.len() - 1);
+    while selected >= end {
+        height = height.saturating_add(heights[end]);
+        end += 1;
+        while height > max_height {
+            height = height.saturating_sub(heights[start]);
+            start += 1;
+        }
+    }
+    while selected < start {
+        start -= 1;
+        height = height.saturating_add(heights[start]);
+        while height > max_height {
+            end -= 1;
+            height = height.saturating_sub(heights[end]);
+        }
+    }
+    (start, end)
 }
 
 impl<T> StatefulList<T> {
@@ -72,37 +310,144 @@ impl<T> StatefulList<T> {
         Self::with_items(Vec::new())
     }
 
-    pub fn render<B, F>(&mut self, frame: &mut Frame<B>, area: Rect, make_item: F)
+    pub fn render<B, F>(&mut self, frame: &mut Frame<B>, area: Rect, theme: &Theme, make_item: F)
     where
         B: Backend,
         F: FnMut(&T) -> ListItem,
     {
+        let highlight = match self.focused {
+            true => theme.highlight,
+            false => theme.unfocused_highlight,
+        };
+        let items = Vec::from_iter(self.items.iter().map(make_item));
+
+        self.area = area;
+        self.shown.clear();
+        if !items.is_empty() && area.width != 0 && area.height != 0 {
+            let heights = items.iter().map(ListItem::height).collect_vec();
+            let (start, end) = items_bounds(
+                &heights,
+                self.selected_index(),
+                self.offset,
+                area.height as usize,
+            );
+            self.offset = start;
+            let mut y = area.y;
+            for (index, &height) in heights.iter().enumerate().take(end).skip(start) {
+                self.shown
+                    .push((Rect::new(area.x, y, area.width, height as u16), index));
+                y += height as u16;
+            }
+        }
+
+        // `List` can't be given the offset, it is drawn from the first item shown instead
+        let mut state = ListState::default();
+        state.select(self.selected_index().and_then(|i| i.checked_sub(self.offset)));
+        let items = items.into_iter().skip(self.offset).collect_vec();
         frame.render_stateful_widget(
-            List::new(Vec::from_iter(self.items.iter().map(make_item)))
-                .highlight_style(Style::default().bg(Color::Rgb(0x33, 0x46, 0x7c))),
+            List::new(items).highlight_style(Style::default().bg(highlight)),
             area,
-            &mut self.state,
+            &mut state,
         )
     }
 
+    /// First item shown
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Shows the items from `offset` on, as far as the selected one stays visible
+    pub fn set_offset(&mut self, offset: usize) {
+        self.offset = offset;
+    }
+
+    /// Selects the item clicked, or the previous/next one when scrolling over the list, returns
+    /// whether the event was over the list
+    pub fn mouse(&mut self, event: MouseEvent) -> bool {
+        let under = |area: &Rect| {
+            (area.left()..area.right()).contains(&event.column)
+                && (area.top()..area.bottom()).contains(&event.row)
+        };
+        if !under(&self.area) {
+            return false;
+        }
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(&(_, index)) = self.shown.iter().find(|(area, _)| under(area)) {
+                    self.select(Some(index));
+                }
+            }
+            MouseEventKind::ScrollUp if !self.items.is_empty() => self.previous(),
+            MouseEventKind::ScrollDown if !self.items.is_empty() => self.next(),
+            _ => (),
+        }
+        true
+    }
+
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    pub fn set_wrapping(&mut self, wrap: bool) {
+        self.wrap = wrap;
+    }
+
+    /// Replaces the items, keeping the selection at the same position as far as possible
+    pub fn set_items(&mut self, items: Vec<T>) {
+        let selected = self
+            .selected_index()
+            .map(|i| i.min(items.len().saturating_sub(1)))
+            .filter(|_| !items.is_empty());
+        self.items = items;
+        self.select(selected);
+    }
+
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    pub fn items_mut(&mut self) -> &mut [T] {
+        &mut self.items
+    }
+
     pub fn selected(&self) -> Option<&T> {
         self.state.selected().map(|i| &self.items[i])
     }
 
+    pub fn selected_index(&self) -> Option<usize> {
+        self.state.selected()
+    }
+
     pub fn with_items(items: Vec<T>) -> StatefulList<T> {
         StatefulList {
             state: ListState::default(),
             items,
+            wrap: true,
+            focused: true,
+            offset: 0,
+            area: Rect::default(),
+            shown: Vec::new(),
         }
     }
 
+    /// Sets whether the selection wraps around the ends of the list
+    pub fn wrapping(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
     pub fn next(&mut self) {
+        if self.items.is_empty() {
+            return self.unselect();
+        }
         let i = match self.state.selected() {
             Some(i) => {
-                if i >= self.items.len() - 1 {
+                if i < self.items.len() - 1 {
+                    i + 1
+                } else if self.wrap {
                     0
                 } else {
-                    i + 1
+                    i
                 }
             }
             None => 0,
@@ -111,12 +456,17 @@ impl<T> StatefulList<T> {
     }
 
     pub fn previous(&mut self) {
+        if self.items.is_empty() {
+            return self.unselect();
+        }
         let i = match self.state.selected() {
             Some(i) => {
-                if i == 0 {
+                if i > 0 {
+                    i - 1
+                } else if self.wrap {
                     self.items.len() - 1
                 } else {
-                    i - 1
+                    0
                 }
             }
             None => 0,
@@ -124,7 +474,24 @@ impl<T> StatefulList<T> {
         self.state.select(Some(i));
     }
 
+    /// Moves the selection by `delta` items, backwards when negative
+    pub fn move_by(&mut self, delta: i32) {
+        for _ in 0..delta.unsigned_abs() {
+            match delta > 0 {
+                true => self.next(),
+                false => self.previous(),
+            }
+        }
+    }
+
+    pub fn select(&mut self, index: Option<usize>) {
+        self.state.select(index);
+        if index.is_none() {
+            self.offset = 0;
+        }
+    }
+
     pub fn unselect(&mut self) {
-        self.state.select(None);
+        self.select(None);
     }
 }